@@ -1,31 +1,109 @@
-use bitcoin::{consensus, Network, Transaction};
-use reqwest::blocking::Client;
+use bitcoin::{consensus, Network, OutPoint, Transaction, Txid};
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::{errors::ChainError, ChainSource};
+
 pub struct MempoolClient {
     http_client: Client,
     base_url: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TxStatus {
+    pub confirmed: bool,
+    pub block_height: Option<u32>,
+    pub block_hash: Option<String>,
+}
+
+/// Spending status of an output, as esplora reports it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct Outspend {
+    spent: bool,
+    txid: Option<Txid>,
+}
+
 impl MempoolClient {
-    pub fn new(network: Network) -> Self {
+    /// Client of mempool.space for the public networks, regtest expects an esplora instance
+    /// on the default local port.
+    pub fn new(network: Network) -> Result<Self, ChainError> {
         let base_url = match network {
             Network::Bitcoin => "https://mempool.space/api",
             Network::Testnet => "https://mempool.space/testnet/api",
             Network::Signet => "https://mempool.space/signet/api",
-            _ => panic!("Network not supported"),
-        }
-        .to_string();
+            Network::Regtest => "http://127.0.0.1:3002/api",
+            _ => return Err(ChainError::UnsupportedNetwork(network)),
+        };
+
+        Ok(Self::with_base_url(base_url))
+    }
 
+    /// Client of a self-hosted esplora/mempool instance, e.g. `http://127.0.0.1:3002/api`.
+    pub fn with_base_url(base_url: &str) -> Self {
         Self {
             http_client: Client::new(),
-            base_url,
+            base_url: base_url.trim_end_matches('/').to_string(),
         }
     }
+}
 
-    pub fn get_tx(&self, txid: &str) -> Result<Transaction, reqwest::Error> {
+impl ChainSource for MempoolClient {
+    async fn get_tx(&self, txid: &Txid) -> Result<Transaction, ChainError> {
         let url = format!("{}/tx/{}/hex", self.base_url, txid);
-        let response = self.http_client.get(&url).send()?;
+        let response = self.http_client.get(&url).send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(ChainError::TxNotFound(*txid));
+        }
+
+        let tx_bytes = hex::decode(response.error_for_status()?.text().await?.trim())
+            .map_err(|e| ChainError::Decode(e.to_string()))?;
+        consensus::deserialize(&tx_bytes).map_err(|e| ChainError::Decode(e.to_string()))
+    }
+
+    async fn get_tx_status(&self, txid: &Txid) -> Result<TxStatus, ChainError> {
+        let url = format!("{}/tx/{}/status", self.base_url, txid);
+        let response = self.http_client.get(&url).send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(ChainError::TxNotFound(*txid));
+        }
+
+        Ok(response.error_for_status()?.json::<TxStatus>().await?)
+    }
+
+    async fn get_outspend(&self, outpoint: &OutPoint) -> Result<Option<Txid>, ChainError> {
+        let url = format!(
+            "{}/tx/{}/outspend/{}",
+            self.base_url, outpoint.txid, outpoint.vout
+        );
+        let outspend = self
+            .http_client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Outspend>()
+            .await?;
+
+        Ok(outspend.txid.filter(|_| outspend.spent))
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<Txid, ChainError> {
+        let url = format!("{}/tx", self.base_url);
+        let response = self
+            .http_client
+            .post(&url)
+            .body(consensus::encode::serialize_hex(tx))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(ChainError::Rejected(response.text().await?));
+        }
 
-        let tx_bytes = hex::decode(response.text()?.trim()).unwrap();
-        Ok(consensus::deserialize(&tx_bytes).unwrap())
+        response
+            .text()
+            .await?
+            .trim()
+            .parse()
+            .map_err(|e: bitcoin::hex::HexToArrayError| ChainError::Decode(e.to_string()))
     }
 }
@@ -0,0 +1,16 @@
+use bitcoin::{Network, Txid};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ChainError {
+    #[error("http request failed with error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("transaction {0} not found")]
+    TxNotFound(Txid),
+    #[error("failed to decode response with error: {0}")]
+    Decode(String),
+    #[error("transaction rejected: {0}")]
+    Rejected(String),
+    #[error("no default bitcoin backend for network {0}")]
+    UnsupportedNetwork(Network),
+}
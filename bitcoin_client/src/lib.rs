@@ -1 +1,28 @@
+use std::future::Future;
+
+use api_client::TxStatus;
+use bitcoin::{OutPoint, Transaction, Txid};
+use errors::ChainError;
+
 pub mod api_client;
+pub mod errors;
+pub mod simulator;
+
+/// Read and broadcast access to the bitcoin chain, independent of the committee.
+pub trait ChainSource {
+    fn get_tx(&self, txid: &Txid) -> impl Future<Output = Result<Transaction, ChainError>> + Send;
+
+    fn get_tx_status(
+        &self,
+        txid: &Txid,
+    ) -> impl Future<Output = Result<TxStatus, ChainError>> + Send;
+
+    /// Txid of the tx spending `outpoint`, in a block or the mempool, `None` while the output
+    /// is unspent or does not exist.
+    fn get_outspend(
+        &self,
+        outpoint: &OutPoint,
+    ) -> impl Future<Output = Result<Option<Txid>, ChainError>> + Send;
+
+    fn broadcast(&self, tx: &Transaction) -> impl Future<Output = Result<Txid, ChainError>> + Send;
+}
@@ -0,0 +1,533 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use bitcoin::{
+    absolute,
+    hashes::{sha256d, Hash},
+    key::Secp256k1,
+    locktime::relative,
+    secp256k1::Message,
+    sighash::{Prevouts, SighashCache},
+    taproot::{self, ControlBlock, LeafVersion, TAPROOT_ANNEX_PREFIX},
+    transaction, Amount, OutPoint, Script, ScriptBuf, Sequence, TapLeafHash, Transaction, TxIn,
+    TxOut, Txid, Witness, XOnlyPublicKey,
+};
+use bitcoin_scriptexec::{Exec, ExecCtx, Options, TxTemplate};
+
+use crate::{api_client::TxStatus, errors::ChainError, ChainSource};
+
+/// Time of the simulated genesis block, the regtest genesis time.
+const GENESIS_TIME: u32 = 1_296_688_602;
+/// Seconds between two simulated blocks.
+const BLOCK_INTERVAL: u32 = 600;
+
+struct Utxo {
+    txout: TxOut,
+    /// Height of the block confirming the tx, `None` while it is in the mempool.
+    height: Option<u32>,
+}
+
+#[derive(Default)]
+struct State {
+    height: u32,
+    utxos: HashMap<OutPoint, Utxo>,
+    txs: HashMap<Txid, (Transaction, Option<u32>)>,
+    /// Txids of the mempool in acceptance order.
+    mempool: Vec<Txid>,
+}
+
+/// In-memory bitcoin chain: a UTXO set, a mempool and blocks mined on demand.
+///
+/// Every broadcast tx is checked like a node's mempool does: the inputs must be unspent, the
+/// inputs must cover the outputs, the absolute and BIP68 relative timelocks must be met at
+/// the next block and every input script must verify with libbitcoinconsensus. The
+/// libbitcoinconsensus of rust-bitcoin predates taproot, so the simulator checks taproot
+/// spends itself: key path signatures, and leaf scripts executed with bitcoin-scriptexec. Block `n` is timestamped 10 minutes after block `n - 1`,
+/// and the time of the tip stands in for the median time past of time based locks.
+#[derive(Default)]
+pub struct ChainSimulator {
+    state: Mutex<State>,
+}
+
+impl ChainSimulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Height of the tip, the genesis block is height 0.
+    pub fn height(&self) -> u32 {
+        self.state().height
+    }
+
+    /// Creates a coin of `amount` locked to `script_pubkey` in a new block, like a coinbase
+    /// output without the maturity period. The block also confirms the mempool.
+    pub fn faucet(&self, script_pubkey: ScriptBuf, amount: Amount) -> OutPoint {
+        let mut state = self.state();
+        let height = state.height + 1;
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                // Unique coinbase txs, like the BIP34 height.
+                script_sig: ScriptBuf::from_bytes(height.to_le_bytes().to_vec()),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: amount,
+                script_pubkey,
+            }],
+        };
+
+        let txid = tx.compute_txid();
+        state.mine_block();
+        state.add_tx(tx, Some(height));
+        OutPoint { txid, vout: 0 }
+    }
+
+    /// Mines `blocks` blocks, the first one confirms the whole mempool. Returns the new height.
+    pub fn mine(&self, blocks: u32) -> u32 {
+        let mut state = self.state();
+        for _ in 0..blocks {
+            state.mine_block();
+        }
+        state.height
+    }
+
+    /// Unspent output at `outpoint`, also the outputs of mempool txs.
+    pub fn utxo(&self, outpoint: &OutPoint) -> Option<TxOut> {
+        self.state()
+            .utxos
+            .get(outpoint)
+            .map(|utxo| utxo.txout.clone())
+    }
+
+    /// Txids of the mempool in acceptance order.
+    pub fn mempool(&self) -> Vec<Txid> {
+        self.state().mempool.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // Checks run before any update, a panic cannot leave a half applied tx behind.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl State {
+    fn block_time(height: u32) -> u32 {
+        GENESIS_TIME + height * BLOCK_INTERVAL
+    }
+
+    fn mine_block(&mut self) {
+        self.height += 1;
+        let height = self.height;
+        for txid in std::mem::take(&mut self.mempool) {
+            let (tx, confirmed) = self.txs.get_mut(&txid).expect("mempool txs are stored");
+            *confirmed = Some(height);
+            for vout in 0..tx.output.len() as u32 {
+                if let Some(utxo) = self.utxos.get_mut(&OutPoint { txid, vout }) {
+                    utxo.height = Some(height);
+                }
+            }
+        }
+    }
+
+    fn add_tx(&mut self, tx: Transaction, height: Option<u32>) {
+        let txid = tx.compute_txid();
+        for input in &tx.input {
+            self.utxos.remove(&input.previous_output);
+        }
+        for (vout, txout) in tx.output.iter().enumerate() {
+            let outpoint = OutPoint {
+                txid,
+                vout: vout as u32,
+            };
+            let txout = txout.clone();
+            self.utxos.insert(outpoint, Utxo { txout, height });
+        }
+        if height.is_none() {
+            self.mempool.push(txid);
+        }
+        self.txs.insert(txid, (tx, height));
+    }
+
+    /// Checks `tx` for the next block, see [`ChainSimulator`].
+    fn check(&self, tx: &Transaction) -> Result<(), ChainError> {
+        let txid = tx.compute_txid();
+        if self.txs.contains_key(&txid) {
+            return Err(ChainError::Rejected(format!("txn-already-known {}", txid)));
+        }
+
+        let mut prevouts = vec![];
+        for input in &tx.input {
+            let utxo = self.utxos.get(&input.previous_output).ok_or_else(|| {
+                ChainError::Rejected(format!(
+                    "bad-txns-inputs-missingorspent {}",
+                    input.previous_output
+                ))
+            })?;
+            prevouts.push(utxo);
+        }
+
+        let input_value = prevouts.iter().map(|utxo| utxo.txout.value).sum::<Amount>();
+        let output_value = tx.output.iter().map(|txout| txout.value).sum::<Amount>();
+        if input_value < output_value {
+            return Err(ChainError::Rejected(format!(
+                "bad-txns-in-belowout, value in {} < value out {}",
+                input_value, output_value
+            )));
+        }
+
+        let next_height = self.height + 1;
+        let tip_time = Self::block_time(self.height);
+        if tx.is_lock_time_enabled() {
+            let height = absolute::Height::from_consensus(next_height)
+                .map_err(|e| ChainError::Rejected(e.to_string()))?;
+            let time = absolute::Time::from_consensus(tip_time)
+                .map_err(|e| ChainError::Rejected(e.to_string()))?;
+            if !tx.lock_time.is_satisfied_by(height, time) {
+                return Err(ChainError::Rejected(format!("non-final {}", txid)));
+            }
+        }
+
+        if tx.version >= transaction::Version::TWO {
+            for (input, utxo) in tx.input.iter().zip(&prevouts) {
+                let Some(lock) = input.sequence.to_relative_lock_time() else {
+                    continue;
+                };
+                // An unconfirmed parent only satisfies a zero lock.
+                let confirmed = utxo.height.unwrap_or(next_height);
+                let satisfied = match lock {
+                    relative::LockTime::Blocks(blocks) => {
+                        next_height - confirmed >= u32::from(blocks.value())
+                    }
+                    relative::LockTime::Time(time) => {
+                        let since = Self::block_time(confirmed.saturating_sub(1));
+                        tip_time - since >= u32::from(time.value()) * 512
+                    }
+                };
+                if !satisfied {
+                    return Err(ChainError::Rejected(format!(
+                        "non-BIP68-final {}",
+                        input.previous_output
+                    )));
+                }
+            }
+        }
+
+        let spent = |outpoint: &OutPoint| self.utxos.get(outpoint).map(|utxo| utxo.txout.clone());
+        tx.verify(spent).map_err(|e| {
+            ChainError::Rejected(format!("mandatory-script-verify-flag-failed ({})", e))
+        })?;
+
+        let prevouts = prevouts
+            .iter()
+            .map(|utxo| utxo.txout.clone())
+            .collect::<Vec<_>>();
+        for index in 0..tx.input.len() {
+            check_taproot_spend(tx, index, &prevouts)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks input `index` when it spends a taproot output: the signature of a key path spend,
+/// the commitment and the execution of the leaf script of a script path spend.
+fn check_taproot_spend(
+    tx: &Transaction,
+    index: usize,
+    prevouts: &[TxOut],
+) -> Result<(), ChainError> {
+    let script_pubkey = &prevouts[index].script_pubkey;
+    // Other outputs are verified by libbitcoinconsensus.
+    if !script_pubkey.is_p2tr() {
+        return Ok(());
+    }
+
+    let invalid = |reason: String| {
+        ChainError::Rejected(format!(
+            "mandatory-script-verify-flag-failed (invalid taproot spend of input {}: {})",
+            index, reason
+        ))
+    };
+    let output_key = XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..])
+        .map_err(|e| invalid(e.to_string()))?;
+    let witness = &tx.input[index].witness;
+    // The annex is the last of at least two elements and starts with its prefix.
+    let annex = witness
+        .last()
+        .filter(|e| witness.len() >= 2 && e.first() == Some(&TAPROOT_ANNEX_PREFIX));
+    let elements = witness
+        .iter()
+        .take(witness.len() - usize::from(annex.is_some()))
+        .collect::<Vec<_>>();
+
+    let secp = Secp256k1::verification_only();
+    match elements.as_slice() {
+        [] => Err(invalid("empty witness".to_string())),
+        [signature] => {
+            let signature =
+                taproot::Signature::from_slice(signature).map_err(|e| invalid(e.to_string()))?;
+            let sighash = SighashCache::new(tx)
+                .taproot_key_spend_signature_hash(
+                    index,
+                    &Prevouts::All(prevouts),
+                    signature.sighash_type,
+                )
+                .map_err(|e| invalid(e.to_string()))?;
+            secp.verify_schnorr(&signature.signature, &Message::from(sighash), &output_key)
+                .map_err(|e| invalid(e.to_string()))
+        }
+        [stack @ .., script, control_block] => {
+            let control_block =
+                ControlBlock::decode(control_block).map_err(|e| invalid(e.to_string()))?;
+            let script = Script::from_bytes(script);
+            if !control_block.verify_taproot_commitment(&secp, output_key, script) {
+                return Err(invalid("leaf script is not committed to".to_string()));
+            }
+            if control_block.leaf_version != LeafVersion::TapScript {
+                return Err(invalid(format!(
+                    "unknown leaf version {}",
+                    control_block.leaf_version
+                )));
+            }
+
+            let tx_template = TxTemplate {
+                tx: tx.clone(),
+                prevouts: prevouts.to_vec(),
+                input_idx: index,
+                taproot_annex_scriptleaf: Some((
+                    TapLeafHash::from_script(script, control_block.leaf_version),
+                    annex.map(|annex| annex.to_vec()),
+                )),
+            };
+            let mut exec = Exec::new(
+                ExecCtx::Tapscript,
+                Options::default(),
+                tx_template,
+                script.to_owned(),
+                stack.iter().map(|element| element.to_vec()).collect(),
+            )
+            .map_err(|e| invalid(format!("{:?}", e)))?;
+            while exec.exec_next().is_ok() {}
+
+            match exec.result() {
+                Some(result) if result.success => Ok(()),
+                Some(result) => Err(invalid(format!("{:?}", result.error))),
+                None => Err(invalid("leaf script did not finish".to_string())),
+            }
+        }
+    }
+}
+
+impl ChainSource for ChainSimulator {
+    async fn get_tx(&self, txid: &Txid) -> Result<Transaction, ChainError> {
+        self.state()
+            .txs
+            .get(txid)
+            .map(|(tx, _)| tx.clone())
+            .ok_or(ChainError::TxNotFound(*txid))
+    }
+
+    async fn get_tx_status(&self, txid: &Txid) -> Result<TxStatus, ChainError> {
+        let state = self.state();
+        let (_, height) = state.txs.get(txid).ok_or(ChainError::TxNotFound(*txid))?;
+        Ok(TxStatus {
+            confirmed: height.is_some(),
+            block_height: *height,
+            block_hash: height.map(|height| sha256d::Hash::hash(&height.to_le_bytes()).to_string()),
+        })
+    }
+
+    async fn get_outspend(&self, outpoint: &OutPoint) -> Result<Option<Txid>, ChainError> {
+        Ok(self.state().txs.iter().find_map(|(txid, (tx, _))| {
+            tx.input
+                .iter()
+                .any(|input| input.previous_output == *outpoint)
+                .then_some(*txid)
+        }))
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<Txid, ChainError> {
+        let mut state = self.state();
+        state.check(tx)?;
+        state.add_tx(tx.clone(), None);
+        Ok(tx.compute_txid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{
+        key::{Keypair, TapTweak},
+        opcodes::{
+            all::{OP_CSV, OP_DROP, OP_EQUAL},
+            OP_FALSE, OP_TRUE,
+        },
+        script::Builder,
+        secp256k1::SecretKey,
+        taproot::TaprootBuilder,
+        TapSighashType,
+    };
+
+    use super::*;
+
+    fn spend(previous_output: OutPoint, sequence: Sequence, value: Amount) -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output,
+                script_sig: ScriptBuf::new(),
+                sequence,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value,
+                script_pubkey: ScriptBuf::new_op_return([]),
+            }],
+        }
+    }
+
+    fn sign_key_spend(tx: &mut Transaction, prevout: &TxOut, keypair: &Keypair) {
+        let secp = Secp256k1::new();
+        let sighash = SighashCache::new(&*tx)
+            .taproot_key_spend_signature_hash(
+                0,
+                &Prevouts::All(&[prevout]),
+                TapSighashType::Default,
+            )
+            .unwrap();
+        let tweaked = keypair.tap_tweak(&secp, None).to_inner();
+        let signature = taproot::Signature {
+            signature: secp.sign_schnorr(&Message::from(sighash), &tweaked),
+            sighash_type: TapSighashType::Default,
+        };
+        tx.input[0].witness = Witness::from_slice(&[signature.to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn test_taproot_key_spend() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[1; 32]).unwrap());
+        let script_pubkey = ScriptBuf::new_p2tr(&secp, keypair.x_only_public_key().0, None);
+
+        let chain = ChainSimulator::new();
+        let coin = chain.faucet(script_pubkey, Amount::from_sat(10_000));
+        let prevout = chain.utxo(&coin).unwrap();
+
+        let mut tx = spend(coin, Sequence::MAX, Amount::from_sat(11_000));
+        sign_key_spend(&mut tx, &prevout, &keypair);
+        assert!(
+            chain.broadcast(&tx).await.is_err(),
+            "outputs exceed the inputs"
+        );
+
+        let mut tx = spend(coin, Sequence::MAX, Amount::from_sat(9_000));
+        sign_key_spend(&mut tx, &prevout, &keypair);
+        let mut tampered = tx.clone();
+        tampered.output[0].value = Amount::from_sat(8_000);
+        assert!(
+            chain.broadcast(&tampered).await.is_err(),
+            "signature commits to the outputs"
+        );
+
+        let txid = chain.broadcast(&tx).await.unwrap();
+        assert_eq!(chain.mempool(), vec![txid]);
+        assert!(!chain.get_tx_status(&txid).await.unwrap().confirmed);
+        assert!(chain.broadcast(&tx).await.is_err(), "already known");
+        assert!(chain.utxo(&coin).is_none());
+        assert_eq!(chain.get_outspend(&coin).await.unwrap(), Some(txid));
+
+        let height = chain.mine(1);
+        let status = chain.get_tx_status(&txid).await.unwrap();
+        assert!(status.confirmed);
+        assert_eq!(status.block_height, Some(height));
+        assert!(chain.mempool().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_taproot_script_spend() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[1; 32]).unwrap());
+        let equal_script = Builder::new()
+            .push_int(1)
+            .push_opcode(OP_EQUAL)
+            .into_script();
+        let false_script = Builder::new().push_opcode(OP_FALSE).into_script();
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(1, equal_script.clone())
+            .unwrap()
+            .add_leaf(1, false_script)
+            .unwrap()
+            .finalize(&secp, keypair.x_only_public_key().0)
+            .unwrap();
+        let control_block = spend_info
+            .control_block(&(equal_script.clone(), LeafVersion::TapScript))
+            .unwrap()
+            .serialize();
+
+        let chain = ChainSimulator::new();
+        let coin = chain.faucet(
+            ScriptBuf::new_p2tr_tweaked(spend_info.output_key()),
+            Amount::from_sat(10_000),
+        );
+        let mut tx = spend(coin, Sequence::MAX, Amount::from_sat(9_000));
+
+        tx.input[0].witness =
+            Witness::from_slice(&[&[2][..], equal_script.as_bytes(), &control_block]);
+        assert!(chain.broadcast(&tx).await.is_err(), "leaf script fails");
+
+        let uncommitted = Builder::new().push_opcode(OP_TRUE).into_script();
+        tx.input[0].witness = Witness::from_slice(&[uncommitted.as_bytes(), &control_block]);
+        assert!(
+            chain.broadcast(&tx).await.is_err(),
+            "leaf script is not in the tree"
+        );
+
+        tx.input[0].witness =
+            Witness::from_slice(&[&[1][..], equal_script.as_bytes(), &control_block]);
+        let txid = chain.broadcast(&tx).await.unwrap();
+        assert_eq!(chain.mempool(), vec![txid]);
+    }
+
+    #[tokio::test]
+    async fn test_relative_timelock() {
+        let csv_script = Builder::new()
+            .push_int(2)
+            .push_opcode(OP_CSV)
+            .push_opcode(OP_DROP)
+            .push_opcode(OP_TRUE)
+            .into_script();
+        let true_script = Builder::new().push_opcode(OP_TRUE).into_script();
+        let chain = ChainSimulator::new();
+        let coin = chain.faucet(
+            ScriptBuf::new_p2wsh(&csv_script.wscript_hash()),
+            Amount::from_sat(10_000),
+        );
+
+        let mut tx = spend(coin, Sequence::from_height(2), Amount::from_sat(9_000));
+        tx.output[0].script_pubkey = ScriptBuf::new_p2wsh(&true_script.wscript_hash());
+        tx.input[0].witness = Witness::from_slice(&[csv_script.as_bytes()]);
+
+        // The coin has one confirmation in the next block, it needs two.
+        assert!(chain.broadcast(&tx).await.is_err());
+        chain.mine(1);
+        let txid = chain.broadcast(&tx).await.unwrap();
+
+        // A child of an unconfirmed parent cannot use a relative lock.
+        let mut child = spend(
+            OutPoint { txid, vout: 0 },
+            Sequence::from_height(1),
+            Amount::ZERO,
+        );
+        child.input[0].witness = Witness::from_slice(&[true_script.as_bytes()]);
+        assert!(chain.broadcast(&child).await.is_err());
+        child.input[0].sequence = Sequence::ENABLE_RBF_NO_LOCKTIME;
+        assert!(chain.broadcast(&child).await.is_ok());
+    }
+}
@@ -1,5 +1,21 @@
+use std::{path::PathBuf, time::Duration};
+
+use bitcoin_client::errors::ChainError;
 use clap::{CommandFactory, Parser, Subcommand};
-use subcommands::{Challenge, Disprove, Register, Signer, Tx};
+use sdk::committee_error;
+use subcommands::{
+    Bundle, Challenge, Circuit, Disprove, Graph, History, Register, Settings, Signer, Tx, Vk,
+};
+use types::{
+    constants::DUST_AMOUNT,
+    error::{
+        BundleError, ChallengeError, CircuitError, DisproveError, FcliError, GraphError,
+        ParamsError, PolicyError, ProofError, RegisterNodeError, TransactionError, VkError,
+    },
+    file::default_data_dir,
+};
+use wallet::config::ClientConfig;
+use web3_decl::{error::Web3Error, jsonrpsee::core::ClientError};
 
 pub mod subcommands;
 
@@ -10,6 +26,34 @@ pub struct Cli {
     pub command: Option<Subcommands>,
     #[clap(long = "version", short = 'V', help = "Print version info and exit")]
     pub version: bool,
+    #[clap(
+        long,
+        global = true,
+        env = "FCLI_DATA_DIR",
+        help = "Directory of the local state and circuit store, defaults to ~/.fcli"
+    )]
+    pub data_dir: Option<PathBuf>,
+    #[clap(
+        long,
+        global = true,
+        env = "FCLI_RPC_TIMEOUT",
+        help = "Timeout of a committee RPC request in seconds, defaults to 60"
+    )]
+    pub rpc_timeout: Option<u64>,
+    #[clap(
+        long,
+        global = true,
+        env = "FCLI_RPC_MAX_RESPONSE_SIZE",
+        help = "Largest committee RPC response accepted in bytes, defaults to 10 MiB"
+    )]
+    pub rpc_max_response_size: Option<u32>,
+    #[clap(
+        long,
+        global = true,
+        env = "FCLI_RPC_RETRIES",
+        help = "Retries of a committee query failing with a transport error or a timeout, defaults to 3"
+    )]
+    pub rpc_retries: Option<usize>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -24,9 +68,34 @@ pub enum Subcommands {
     Disprove(Disprove),
     #[clap(about = "Register commands")]
     Register(Register),
+    #[clap(about = "List past operations")]
+    History(History),
+    #[clap(about = "Verifier key commands")]
+    Vk(Vk),
+    #[clap(about = "Circuit store commands")]
+    Circuit(Circuit),
+    #[clap(about = "Export the validator's tx graph as a DOT or Mermaid diagram")]
+    Graph(Graph),
+    #[clap(about = "Export and broadcast the validator's presigned txs without the committee")]
+    Bundle(Bundle),
 }
 
 pub async fn run_command(cli: Cli) -> anyhow::Result<()> {
+    let mut client_config = ClientConfig::default();
+    if let Some(secs) = cli.rpc_timeout {
+        client_config.request_timeout = Duration::from_secs(secs);
+    }
+    if let Some(size) = cli.rpc_max_response_size {
+        client_config.max_response_size = size;
+    }
+    if let Some(retries) = cli.rpc_retries {
+        client_config.max_retries = retries;
+    }
+    let settings = Settings {
+        data_dir: cli.data_dir.unwrap_or_else(default_data_dir),
+        client_config,
+    };
+
     match (cli.version, cli.command) {
         (false, None) => Ok(Cli::command().print_help()?),
         (true, _) => {
@@ -35,10 +104,131 @@ pub async fn run_command(cli: Cli) -> anyhow::Result<()> {
         }
         (false, Some(command)) => match command {
             Subcommands::Signer(cmd) => cmd.run().await,
-            Subcommands::Challenge(cmd) => cmd.run().await,
-            Subcommands::Tx(cmd) => cmd.run().await,
-            Subcommands::Disprove(cmd) => cmd.run().await,
-            Subcommands::Register(cmd) => cmd.run().await,
+            Subcommands::Challenge(cmd) => cmd.run(&settings).await,
+            Subcommands::Tx(cmd) => cmd.run(&settings).await,
+            Subcommands::Disprove(cmd) => cmd.run(&settings).await,
+            Subcommands::Register(cmd) => cmd.run(&settings).await,
+            Subcommands::History(cmd) => cmd.run(&settings).await,
+            Subcommands::Vk(cmd) => cmd.run().await,
+            Subcommands::Circuit(cmd) => cmd.run(&settings).await,
+            Subcommands::Graph(cmd) => cmd.run(&settings).await,
+            Subcommands::Bundle(cmd) => cmd.run(&settings).await,
+        },
+    }
+}
+
+/// Exit code of `err`, decided by the first error in its chain with a category, see
+/// [`FcliError::exit_code`].
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    for cause in err.chain() {
+        if let Some(err) = cause.downcast_ref::<FcliError>() {
+            return err.exit_code();
+        }
+        if cause.is::<ChainError>() {
+            return FcliError::EXIT_CHAIN;
+        }
+        if cause.is::<ClientError>() {
+            return FcliError::EXIT_COMMITTEE;
+        }
+        if cause.is::<ParamsError>() {
+            return FcliError::EXIT_INVALID_ARGUMENT;
+        }
+        if cause.is::<RegisterNodeError>()
+            || cause.is::<ChallengeError>()
+            || cause.is::<DisproveError>()
+            || cause.is::<CircuitError>()
+            || cause.is::<TransactionError>()
+            || cause.is::<GraphError>()
+            || cause.is::<PolicyError>()
+            || cause.is::<BundleError>()
+            || cause.is::<VkError>()
+            || cause.is::<ProofError>()
+        {
+            return FcliError::EXIT_VALIDATION;
+        }
+    }
+    FcliError::EXIT_OTHER
+}
+
+/// How to fix the committee error carried by `err`, for the errors with a known remedy.
+pub fn hint(err: &anyhow::Error) -> Option<String> {
+    let hint = match committee_error(err)? {
+        Web3Error::RegisterNodeError(err) => match err {
+            RegisterNodeError::StakeTxInvalidConnectorValue(value) => format!(
+                "stake tx has invalid connector value {} \u{2192} expected DUST_AMOUNT {}",
+                value,
+                DUST_AMOUNT.to_sat()
+            ),
+            RegisterNodeError::StakeTxInvalidStakeValue(value) => format!(
+                "stake tx has invalid stake value {} \u{2192} expected the stake amount plus the assert fee of the committee's protocol params, check the mismatch warnings",
+                value
+            ),
+            RegisterNodeError::ValidatorKeyRegistered(validator_key) => format!(
+                "validator {} is already registered, resume it with `register finish` or `register run`",
+                validator_key
+            ),
+            RegisterNodeError::NoCommitteeRotation(_) => {
+                "the committee is not rotating, register with `register start`".to_string()
+            }
+            _ => return None,
+        },
+        Web3Error::ChallengeNodeError(err) => match err {
+            ChallengeError::ChallengeExists(proof_id) => format!(
+                "follow the challenge with `challenge info -p {}` or resume it with `challenge run`",
+                proof_id
+            ),
+            ChallengeError::ChallengeRawCommitteeTxNotReady => {
+                "retry `challenge finish` once `challenge info` reports the challenge tx ready to submit".to_string()
+            }
+            _ => return None,
         },
+        Web3Error::DisproveError(DisproveError::AssertTxIsNotConfirmed(proof_id)) => format!(
+            "retry once the assert tx of proof {} is confirmed, `challenge info` shows its txid",
+            proof_id
+        ),
+        _ => return None,
+    };
+    Some(hint)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+    use bitcoin::Amount;
+
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let err = anyhow::Error::from(FcliError::InsufficientFunds {
+            available: Amount::from_sat(1),
+            required: Amount::from_sat(2),
+        });
+        assert_eq!(exit_code(&err), FcliError::EXIT_INSUFFICIENT_FUNDS);
+
+        let err = Err::<(), _>(ChainError::Rejected("dust".to_string()))
+            .context("Failed to broadcast")
+            .unwrap_err();
+        assert_eq!(exit_code(&err), FcliError::EXIT_CHAIN);
+
+        let err = anyhow::Error::from(ChallengeError::ProofIsValid("proof".to_string()));
+        assert_eq!(exit_code(&err), FcliError::EXIT_VALIDATION);
+
+        assert_eq!(exit_code(&anyhow::anyhow!("other")), FcliError::EXIT_OTHER);
+    }
+
+    #[test]
+    fn test_hint() {
+        let object = Web3Error::from(RegisterNodeError::StakeTxInvalidConnectorValue(546)).into();
+        let err = Err::<(), _>(ClientError::Call(object))
+            .context("Failed to register")
+            .unwrap_err();
+        assert_eq!(exit_code(&err), FcliError::EXIT_COMMITTEE);
+        assert_eq!(
+            hint(&err).unwrap(),
+            "stake tx has invalid connector value 546 \u{2192} expected DUST_AMOUNT 1000"
+        );
+
+        assert!(hint(&anyhow::anyhow!("other")).is_none());
     }
 }
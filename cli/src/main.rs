@@ -1,11 +1,25 @@
 use clap::Parser;
 use colored::Colorize;
-use fcli::{run_command, Cli};
+use fcli::{exit_code, hint, run_command, Cli};
+use sdk::committee_error;
+use types::error::FcliError;
 
 #[tokio::main]
 async fn main() {
+    // A bug must not reach scripts as a panic backtrace, report it like any other error.
+    std::panic::set_hook(Box::new(|info| {
+        eprintln!("{}", format!("Error: internal error: {info}").red());
+        std::process::exit(FcliError::EXIT_OTHER);
+    }));
+
     if let Err(err) = run_command(Cli::parse()).await {
-        eprintln!("{}", format!("Error: {err}").red());
-        std::process::exit(1);
+        eprintln!("{}", format!("Error: {err:#}").red());
+        if let Some(committee_err) = committee_error(&err) {
+            eprintln!("Committee error code: {}", committee_err.code());
+        }
+        if let Some(hint) = hint(&err) {
+            eprintln!("{}", format!("Hint: {hint}").yellow());
+        }
+        std::process::exit(exit_code(&err));
     }
 }
@@ -0,0 +1,269 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use bitcoin::Network;
+use bitcoin_client::{api_client::MempoolClient, ChainSource};
+use clap::Parser;
+use sdk::bundle;
+use storage::Storage;
+use types::{
+    error::FcliError,
+    file::{get_presigned_bundle_path, write_file_atomic},
+    presigned_transactions::PresignedTransactions,
+    register::{CircuitTx, QueryAssertTxReq},
+};
+use wallet::provider::ProviderParams;
+
+use super::params::{connect, Settings};
+
+#[derive(Debug, Parser, Clone)]
+#[clap(
+    name = "Bundle",
+    about = "Export and broadcast the validator's presigned txs without the committee"
+)]
+pub struct Bundle {
+    #[clap(
+        short,
+        long,
+        help = "Network name, can be local or dev",
+        default_value = "local"
+    )]
+    network: Option<String>,
+
+    #[clap(subcommand)]
+    action: Action,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub enum Action {
+    #[clap(
+        name = "export",
+        about = "Export the validator's presigned txs",
+        long_about = "Export the stored stake, assert, challenge and disprove txs and the committee's assert txs of a validator, with the outputs they spend and their witness scripts, as a versioned JSON bundle.\nThe bundle is written after every finished registration too.\n\nExample:\n  fcli bundle -n testnet export -v your_validator_key"
+    )]
+    Export(ExportBundle),
+
+    #[clap(
+        name = "list",
+        about = "List the txs of a bundle",
+        long_about = "List the txs of a bundle with their type, circuit and txid.\n\nExample:\n  fcli bundle list -v your_validator_key"
+    )]
+    List(BundleFile),
+
+    #[clap(
+        name = "broadcast",
+        about = "Broadcast a tx of a bundle through the bitcoin backend",
+        long_about = "Check a tx of a bundle against the relay policy and broadcast it through the bitcoin backend, without the committee.\nSelect the tx by txid, or by type when the bundle holds a single tx of the type.\n\nExample:\n  fcli bundle -n testnet broadcast -v your_validator_key --tx stake_tx"
+    )]
+    Broadcast(BroadcastBundle),
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct BundleFile {
+    #[clap(
+        short,
+        long,
+        help = "Validator key of the bundle in the data directory"
+    )]
+    validator_key: Option<String>,
+
+    #[clap(
+        long,
+        conflicts_with = "validator_key",
+        required_unless_present = "validator_key",
+        help = "Bundle file"
+    )]
+    bundle: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ExportBundle {
+    #[clap(short, long, help = "Validator key of the staker")]
+    validator_key: String,
+
+    #[clap(
+        long,
+        help = "Esplora API url of the bitcoin backend, defaults to mempool.space"
+    )]
+    esplora_url: Option<String>,
+
+    #[clap(
+        short,
+        long,
+        help = "File to write the bundle to, defaults to the data directory"
+    )]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct BroadcastBundle {
+    #[clap(flatten)]
+    file: BundleFile,
+
+    #[clap(long, help = "Txid or type, e.g. stake_tx, of the tx to broadcast")]
+    tx: String,
+
+    #[clap(
+        long,
+        help = "Esplora API url of the bitcoin backend, defaults to mempool.space"
+    )]
+    esplora_url: Option<String>,
+}
+
+impl Bundle {
+    pub async fn run(self, settings: &Settings) -> anyhow::Result<()> {
+        let ctx = if let Some(network) = self.network {
+            match network.as_str() {
+                "local" => ProviderParams::local(),
+                "dev" => ProviderParams::dev(),
+                "testnet" => ProviderParams::testnet(),
+                _ => {
+                    anyhow::bail!(FcliError::InvalidArgument(format!(
+                        "invalid network name {network}"
+                    )))
+                }
+            }
+        } else {
+            ProviderParams::local()
+        };
+
+        match self.action {
+            Action::Export(args) => {
+                let chain = chain(ctx.network, &args.esplora_url)?;
+                let storage = Storage::open(&settings.data_dir)?;
+                let wallet = connect(&ctx, &settings.client_config)?;
+                let assert_txs = match wallet
+                    .get_committee_assert_txs(QueryAssertTxReq::new(&args.validator_key))
+                    .await
+                {
+                    Ok(assert_txs) => assert_txs,
+                    Err(e) => {
+                        println!(
+                            "Warning: failed to query the committee's assert txs, only the stored txs are exported: {:#}",
+                            e
+                        );
+                        vec![]
+                    }
+                };
+
+                let path = args.output.unwrap_or_else(|| {
+                    get_presigned_bundle_path(&settings.data_dir, &args.validator_key)
+                });
+                let bundle = export_bundle(
+                    &chain,
+                    &storage,
+                    ctx.network,
+                    &args.validator_key,
+                    &assert_txs,
+                    &path,
+                )
+                .await?;
+                println!(
+                    "{} presigned txs of validator {} written to {}",
+                    bundle.txs.len(),
+                    args.validator_key,
+                    path.display()
+                );
+            }
+            Action::List(args) => {
+                let bundle = read_bundle(&settings.data_dir, &args)?;
+                println!(
+                    "Presigned txs of validator {} on {}:",
+                    bundle.validator_key, bundle.network
+                );
+                for tx in &bundle.txs {
+                    match &tx.circuit_hash {
+                        Some(circuit_hash) => {
+                            println!("  {} {} circuit {}", tx.tx_type, tx.txid, circuit_hash)
+                        }
+                        None => println!("  {} {}", tx.tx_type, tx.txid),
+                    }
+                }
+            }
+            Action::Broadcast(args) => {
+                let bundle = read_bundle(&settings.data_dir, &args.file)?;
+                if bundle.network != ctx.network {
+                    anyhow::bail!(FcliError::InvalidArgument(format!(
+                        "bundle holds txs of network {}, not {}",
+                        bundle.network, ctx.network
+                    )));
+                }
+                let presigned_tx = match bundle.select(&args.tx).as_slice() {
+                    [tx] => *tx,
+                    [] => anyhow::bail!(FcliError::InvalidArgument(format!(
+                        "bundle holds no tx {}",
+                        args.tx
+                    ))),
+                    txs => anyhow::bail!(FcliError::InvalidArgument(format!(
+                        "bundle holds {} txs of type {}, select one by txid: {}",
+                        txs.len(),
+                        args.tx,
+                        txs.iter()
+                            .map(|tx| tx.txid.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))),
+                };
+
+                let chain = chain(ctx.network, &args.esplora_url)?;
+                if chain.get_tx(&presigned_tx.txid).await.is_ok() {
+                    println!(
+                        "{} {} is already on chain",
+                        presigned_tx.tx_type, presigned_tx.txid
+                    );
+                    return Ok(());
+                }
+                let txid = bundle::broadcast(&chain, &bundle, presigned_tx).await?;
+                println!("{} {} broadcast", presigned_tx.tx_type, txid);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Exports the stored txs of the validator and the committee's `assert_txs` to `path`.
+pub(crate) async fn export_bundle<C: ChainSource>(
+    chain: &C,
+    storage: &Storage,
+    network: Network,
+    validator_key: &str,
+    assert_txs: &[CircuitTx],
+    path: &Path,
+) -> anyhow::Result<PresignedTransactions> {
+    let mut txs = storage
+        .transactions_by_ext_info(validator_key)?
+        .into_iter()
+        .map(|tx| CircuitTx::new("", tx.tx_type, &tx.data))
+        .collect::<Vec<_>>();
+    txs.extend_from_slice(assert_txs);
+    if txs.is_empty() {
+        anyhow::bail!(
+            "no txs of validator {} stored or generated by the committee",
+            validator_key
+        );
+    }
+
+    let bundle = bundle::export(chain, validator_key, network, &txs).await?;
+    write_file_atomic(path, serde_json::to_string_pretty(&bundle)?.as_bytes())?;
+    Ok(bundle)
+}
+
+fn read_bundle(data_dir: &Path, args: &BundleFile) -> anyhow::Result<PresignedTransactions> {
+    let path = match (&args.bundle, &args.validator_key) {
+        (Some(path), _) => path.clone(),
+        (None, Some(validator_key)) => get_presigned_bundle_path(data_dir, validator_key),
+        (None, None) => unreachable!("clap requires the bundle or the validator key"),
+    };
+    let json = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read bundle {}", path.display()))?;
+    serde_json::from_str(&json).map_err(|e| {
+        FcliError::InvalidArgument(format!("invalid bundle {}: {}", path.display(), e)).into()
+    })
+}
+
+fn chain(network: Network, esplora_url: &Option<String>) -> anyhow::Result<MempoolClient> {
+    Ok(match esplora_url {
+        Some(url) => MempoolClient::with_base_url(url),
+        None => MempoolClient::new(network)?,
+    })
+}
@@ -1,25 +1,41 @@
-use std::str::FromStr;
-
-use bitcoin::{
-    bip32::Xpriv,
-    consensus::encode,
-    key::TapTweak,
-    sighash::{self, SighashCache},
-    taproot, Address, KnownHrp, Network, OutPoint, PrivateKey, ScriptBuf, Sequence, TapLeafHash,
-    TapSighashType, Transaction, TxIn, TxOut, Txid, Witness, XOnlyPublicKey,
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
 };
+
+use anyhow::Context;
+use bitcoin::Txid;
 use bitcoin_client::api_client::MempoolClient;
 use clap::Parser;
+use sdk::{committee_error, Auxiliary, ChallengeFlow, DisproveFlow, FundingInput};
+use storage::{circuit::CircuitStore, Storage};
 use types::{
-    challenge::{ChallengeRequest, ChallengeStatus, FinishChallengeRequest},
+    bitvm::LeafScript,
+    challenge::{ChallengeRequest, ChallengeStatus, ProofRes},
     circuit::{CircuitInfo, CircuitType},
-    constants::{CHALLENGE_FEE_AMOUNT, DUST_AMOUNT},
+    committee::CommitteeVersionId,
+    disprove::DisproveRequest,
+    error::{ChallengeError, FcliError},
     file::read_vk_from_path,
+    params::ProtocolParams,
+    transaction::FiammaTransaction,
+};
+use verifier::validate_vk_bytes;
+use wallet::provider::ProviderParams;
+use web3_decl::{error::Web3Error, jsonrpsee::http_client::HttpClient};
+
+use super::{
+    disprove::read_leaf_scripts,
+    dry_run::{DryRun, DryRunParams},
+    params::{check_protocol_params, committee_version, connect, Settings},
+    scripts::verify_assert_commitment,
 };
-use wallet::{provider::ProviderParams, signer::Signer, Wallet};
-use web3_decl::jsonrpsee::http_client::HttpClientBuilder;
 
-use types::constants::CHALLENGE_AMOUNT;
+type Flow<'a> = ChallengeFlow<'a, HttpClient, MempoolClient>;
+type Disprove<'a> = DisproveFlow<'a, HttpClient, MempoolClient>;
 
 #[derive(Debug, Parser, Clone)]
 #[clap(name = "Challenge", about = "CLI for Fiamma validator challenge")]
@@ -38,8 +54,19 @@ pub struct Challenge {
 
 #[derive(Debug, Parser, Clone)]
 pub enum Action {
-    #[clap(name = "start", about = "Start a challenge")]
-    Start(ChallengeProof),
+    #[clap(
+        name = "start",
+        about = "Start a challenge",
+        long_about = "Start a challenge.\nThe proof is verified locally first, and the challenge is refused when the proof is valid.\n\nExample:\n  fcli challenge -n testnet start -p proof_id -v vk.bitvm -c groth16"
+    )]
+    Start(StartChallenge),
+
+    #[clap(
+        name = "check",
+        about = "Verify the challenged proof locally",
+        long_about = "Verify the proof against the verifier key to tell whether it is actually invalid.\nThe proof is fetched from the committee unless --proof-file is given.\n\nExample:\n  fcli challenge -n testnet check -p proof_id -v vk.bitvm -c groth16"
+    )]
+    Check(CheckProof),
 
     #[clap(name = "status", about = "Query the started challenge status")]
     Status(ChallengeProof),
@@ -49,16 +76,57 @@ pub enum Action {
 
     #[clap(name = "finish", about = "Finish the challenge")]
     Finish(FillChallenge),
+
+    #[clap(
+        name = "run",
+        about = "Start, fund and monitor a challenge end-to-end",
+        long_about = "Start the challenge, submit the funded challenge tx once the committee's tx is ready and monitor the challenge until it ends.\nWhen a reward address is given, the disprove request is sent as soon as the disprove tx is ready to handle, for --script-index or else for the first leaf script of the validator succeeding against the assert tx.\n\nExample:\n  fcli challenge -n testnet run -p proof_id -v vk.bitvm -c groth16 -t your_txid -o your_vout -s your_private_key -r your_reward_address --validator-key validator_key"
+    )]
+    Run(RunChallenge),
 }
 
 #[derive(Debug, Parser, Clone)]
 pub struct ChallengeProof {
     #[clap(short, long, help = "Proof id of challenged proof")]
     proof_id: String,
-    #[clap(short, long, help = "circuit's verifier key path")]
-    vk_path: String,
+    #[clap(
+        short,
+        long,
+        help = "circuit's verifier key path or the vk hash of an imported circuit, defaults to the one stored when the challenge started"
+    )]
+    vk_path: Option<String>,
     #[clap(short, long, help = "Circuit type")]
-    circuit_type: String,
+    circuit_type: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct StartChallenge {
+    #[clap(flatten)]
+    proof: ChallengeProof,
+
+    #[clap(
+        long,
+        help = "Proof file in the getProof JSON format, defaults to the proof of the committee"
+    )]
+    proof_file: Option<String>,
+
+    #[clap(long, help = "Challenge the proof even if it verifies locally")]
+    force: bool,
+
+    #[clap(flatten)]
+    dry_run: DryRunParams,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct CheckProof {
+    #[clap(flatten)]
+    proof: ChallengeProof,
+
+    #[clap(
+        long,
+        help = "Proof file in the getProof JSON format, defaults to the proof of the committee"
+    )]
+    proof_file: Option<String>,
 }
 
 #[derive(Debug, Parser, Clone)]
@@ -66,11 +134,15 @@ pub struct FillChallenge {
     #[clap(short, long, help = "Proof id of challenged proof")]
     proof_id: String,
 
-    #[clap(short, long, help = "circuit's verifier key path")]
-    vk_path: String,
+    #[clap(
+        short,
+        long,
+        help = "circuit's verifier key path or the vk hash of an imported circuit, defaults to the one stored when the challenge started"
+    )]
+    vk_path: Option<String>,
 
     #[clap(short, long, help = "Circuit type")]
-    circuit_type: String,
+    circuit_type: Option<String>,
 
     #[clap(short, long, help = "Assert input's transaction id")]
     txid: String,
@@ -80,63 +152,200 @@ pub struct FillChallenge {
 
     #[clap(short = 's', long, help = "Bitcoin private key to sign assert tx")]
     private_key: String,
+
+    #[clap(
+        long,
+        help = "Validator of the proof, its assert tx is checked against the local leaf scripts before funding the challenge"
+    )]
+    validator_key: Option<String>,
+
+    #[clap(flatten)]
+    dry_run: DryRunParams,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct RunChallenge {
+    #[clap(short, long, help = "Proof id of challenged proof")]
+    proof_id: String,
+
+    #[clap(
+        short,
+        long,
+        help = "circuit's verifier key path or the vk hash of an imported circuit, defaults to the one stored when the challenge started"
+    )]
+    vk_path: Option<String>,
+
+    #[clap(short, long, help = "Circuit type")]
+    circuit_type: Option<String>,
+
+    #[clap(short, long, help = "Challenge input's transaction id")]
+    txid: String,
+
+    #[clap(short = 'o', long, help = "Challenge input's vout")]
+    vout: u32,
+
+    #[clap(short = 's', long, help = "Bitcoin private key to sign challenge tx")]
+    private_key: String,
+
+    #[clap(
+        long,
+        help = "Validator of the proof, its assert tx is checked against the local leaf scripts before funding the challenge"
+    )]
+    validator_key: Option<String>,
+
+    #[clap(
+        short,
+        long,
+        help = "Reward address of the disprove, the disprove is requested automatically when given"
+    )]
+    reward_address: Option<String>,
+
+    #[clap(
+        long,
+        help = "Index of the disprove script, discovered from the leaf scripts when omitted"
+    )]
+    script_index: Option<usize>,
+
+    #[clap(
+        long,
+        help = "Path to the leaf scripts file, defaults to the stored leaf scripts of --validator-key"
+    )]
+    leaf_scripts: Option<PathBuf>,
+
+    #[clap(long, help = "Seconds between two polls", default_value_t = 10)]
+    poll_interval: u64,
+
+    #[clap(
+        long,
+        help = "Proof file in the getProof JSON format, defaults to the proof of the committee"
+    )]
+    proof_file: Option<String>,
+
+    #[clap(long, help = "Challenge the proof even if it verifies locally")]
+    force: bool,
 }
 
 impl Challenge {
-    pub async fn run(self) -> anyhow::Result<()> {
+    pub async fn run(self, settings: &Settings) -> anyhow::Result<()> {
+        let network = self.network.clone().unwrap_or_else(|| "local".to_string());
         let ctx = if let Some(network) = self.network {
             match network.as_str() {
                 "local" => ProviderParams::local(),
                 "dev" => ProviderParams::dev(),
                 "testnet" => ProviderParams::testnet(),
                 _ => {
-                    anyhow::bail!("invalid network name")
+                    anyhow::bail!(FcliError::InvalidArgument(format!(
+                        "invalid network name {network}"
+                    )))
                 }
             }
         } else {
             ProviderParams::local()
         };
 
-        let client = HttpClientBuilder::default()
-            .build(ctx.http_endpoint.as_str())
-            .unwrap();
-        let signer = Signer::new();
-        let wallet = Wallet::new(client, signer);
+        let wallet = connect(&ctx, &settings.client_config)?;
+        let storage = Storage::open(&settings.data_dir)?;
+        let params = ProtocolParams::new(ctx.network, committee_version(&wallet).await)?;
+        let chain = MempoolClient::new(ctx.network)?;
+        let flow = ChallengeFlow::new(&wallet, &chain, params);
 
         match self.action {
-            Action::Start(args) => {
-                let vk = read_vk_from_path(&args.vk_path)?;
-                let circuit_type = CircuitType::from_str(&args.circuit_type)?;
-                let circuit_info = CircuitInfo::new(&vk, circuit_type);
-                let request = ChallengeRequest::new(&args.proof_id, &circuit_info.vk_hash);
-
-                wallet
-                    .start_challenge(request)
-                    .await
-                    .expect("Failed to start a challenge");
+            Action::Start(StartChallenge {
+                proof: args,
+                proof_file,
+                force,
+                dry_run,
+            }) => {
+                let challenge = resolve_challenge(
+                    &settings.data_dir,
+                    &storage,
+                    &network,
+                    &args.proof_id,
+                    args.vk_path.as_deref(),
+                    args.circuit_type.as_deref(),
+                    flow.params().committee_version,
+                )?;
+                ensure_proof_is_invalid(&flow, &challenge, proof_file.as_deref(), force).await?;
+                if dry_run.dry_run {
+                    return DryRun::new("startChallenge", &challenge.request)?.emit(&dry_run);
+                }
+                challenge.save(&settings.data_dir, &storage, &network)?;
+
+                let challenge_id = flow.start(challenge.request).await?;
+                storage.set_challenge_id(&network, &args.proof_id, challenge_id)?;
+                storage.record_operation(
+                    &network,
+                    "challenge start",
+                    &args.proof_id,
+                    &format!("challenge id {}", challenge_id),
+                )?;
                 println!(
                     "You have started to challenge a proof with proof_id {:?}\nNow please use `challenge status` to query the status of challenge",
                     args.proof_id
                 );
             }
+            Action::Check(CheckProof {
+                proof: args,
+                proof_file,
+            }) => {
+                let challenge = resolve_challenge(
+                    &settings.data_dir,
+                    &storage,
+                    &network,
+                    &args.proof_id,
+                    args.vk_path.as_deref(),
+                    args.circuit_type.as_deref(),
+                    flow.params().committee_version,
+                )?;
+                let valid = check_proof(&flow, &challenge, proof_file.as_deref()).await?;
+                // Only a proof that can be challenged is kept for the next commands.
+                if !valid {
+                    challenge.save(&settings.data_dir, &storage, &network)?;
+                }
+                storage.record_operation(
+                    &network,
+                    "challenge check",
+                    &args.proof_id,
+                    if valid { "valid" } else { "invalid" },
+                )?;
+                match valid {
+                    true => println!(
+                        "Proof {} is valid, a challenge against it would fail",
+                        args.proof_id
+                    ),
+                    false => println!("Proof {} is invalid, it can be challenged", args.proof_id),
+                }
+            }
             Action::Status(args) => {
-                let vk = read_vk_from_path(&args.vk_path)?;
-                let circuit_type = CircuitType::from_str(&args.circuit_type)?;
-                let circuit_info = CircuitInfo::new(&vk, circuit_type);
-                let request = ChallengeRequest::new(&args.proof_id, &circuit_info.vk_hash);
-                let res = wallet.challenge_status(request).await;
-                println!("{}", res.unwrap());
+                let request = resolve_challenge_request(
+                    &settings.data_dir,
+                    &storage,
+                    &network,
+                    &args.proof_id,
+                    args.vk_path.as_deref(),
+                    args.circuit_type.as_deref(),
+                    flow.params().committee_version,
+                )?;
+                let res = flow.status(request).await?;
+                storage.record_operation(&network, "challenge status", &args.proof_id, &res)?;
+                println!("{}", res);
             }
             Action::Info(args) => {
-                let vk = read_vk_from_path(&args.vk_path)?;
-                let circuit_type = CircuitType::from_str(&args.circuit_type)?;
-                let circuit_info = CircuitInfo::new(&vk, circuit_type);
-                let request = ChallengeRequest::new(&args.proof_id, &circuit_info.vk_hash);
+                let request = resolve_challenge_request(
+                    &settings.data_dir,
+                    &storage,
+                    &network,
+                    &args.proof_id,
+                    args.vk_path.as_deref(),
+                    args.circuit_type.as_deref(),
+                    flow.params().committee_version,
+                )?;
 
                 println!("Starting to poll challenge info...");
                 loop {
-                    match wallet.challenge_info(request.clone()).await {
+                    match flow.info(request.clone()).await {
                         Ok(res) => {
+                            storage.save_challenge_info(&network, &res)?;
                             println!("\nChallenge Status:");
                             println!("Status: {:?}", res.status);
                             if res.status == ChallengeStatus::ChallengeNotExist {
@@ -165,144 +374,549 @@ impl Challenge {
             }
 
             Action::Finish(args) => {
-                let vk = read_vk_from_path(&args.vk_path)?;
-                let circuit_type = CircuitType::from_str(&args.circuit_type)?;
-                let circuit_info = CircuitInfo::new(&vk, circuit_type);
-
-                let request = ChallengeRequest::new(&args.proof_id, &circuit_info.vk_hash);
-                let challenge_tx = wallet
-                    .get_committee_challenge_tx(request)
-                    .await
-                    .expect("failed to get assert tx of proof");
-                let multi_sig_script = wallet
-                    .get_multi_sig_script_of_proof(&args.proof_id)
-                    .await
-                    .expect("failed to query multi sig script of proof");
-                let challenge_tx = fill_challenger_tx(
-                    &challenge_tx,
+                let challenge = resolve_challenge(
+                    &settings.data_dir,
+                    &storage,
+                    &network,
+                    &args.proof_id,
+                    args.vk_path.as_deref(),
+                    args.circuit_type.as_deref(),
+                    flow.params().committee_version,
+                )?;
+                check_protocol_params(&wallet, flow.params(), Some(&challenge.circuit_type)).await;
+                let request = challenge.request.clone();
+                if args.dry_run.dry_run {
+                    check_assert_commitment(
+                        &settings.data_dir,
+                        &flow,
+                        &request,
+                        args.validator_key.as_deref(),
+                    )
+                    .await?;
+                    let key = Auxiliary::from_private_key(&args.private_key, ctx.network)?;
+                    let input = FundingInput::new(&args.txid, args.vout)?;
+                    let (request, challenge_tx) =
+                        flow.finish_request(request, &key, &input).await?;
+                    return DryRun::new("finishChallenge", &request)?
+                        .with_tx("challenge_tx", &challenge_tx)
+                        .emit(&args.dry_run);
+                }
+                challenge.save(&settings.data_dir, &storage, &network)?;
+                let res = finish_challenge(
+                    &settings.data_dir,
+                    &flow,
+                    &storage,
+                    &network,
+                    request,
                     &args.private_key,
                     &args.txid,
                     args.vout,
-                    multi_sig_script,
-                    ctx.network,
-                );
-                let challenge_tx_str = encode::serialize_hex(&challenge_tx);
-                let request = FinishChallengeRequest::new(&args.proof_id, &challenge_tx_str);
-                let res = wallet
-                    .finish_challenge(request)
-                    .await
-                    .expect("failed to finish challenge");
+                    args.validator_key.as_deref(),
+                )
+                .await?;
                 println!("You have success to finish the challenge, please check the challenge transaction ({}) status on bitcoin.", res);
             }
+            Action::Run(args) => {
+                let challenge = resolve_challenge(
+                    &settings.data_dir,
+                    &storage,
+                    &network,
+                    &args.proof_id,
+                    args.vk_path.as_deref(),
+                    args.circuit_type.as_deref(),
+                    flow.params().committee_version,
+                )?;
+                check_protocol_params(&wallet, flow.params(), Some(&challenge.circuit_type)).await;
+                let disprove = DisproveFlow::new(&wallet, &chain, flow.params().clone());
+                run_challenge(
+                    &settings.data_dir,
+                    &flow,
+                    &disprove,
+                    &storage,
+                    &network,
+                    &challenge,
+                    &args,
+                )
+                .await?;
+            }
         }
         Ok(())
     }
 }
 
-fn fill_challenger_tx(
-    raw_tx: &str,
-    challenger_sk: &str,
-    challenger_input_txid: &str,
-    challenger_input_vout: u32,
-    multi_sig_script: ScriptBuf,
-    network: Network,
-) -> Transaction {
-    let secp = bitcoin::secp256k1::Secp256k1::new();
-    let multi_sig_address = Address::p2wsh(&multi_sig_script, KnownHrp::from(network));
-
-    let private_key = if let Ok(pk) = PrivateKey::from_wif(challenger_sk) {
-        pk
-    } else if let Ok(pk) = Xpriv::from_str(challenger_sk) {
-        pk.to_priv()
-    } else {
-        panic!("Invalid private key")
-    };
-    // let challenger_private_key =
-    //     Xpriv::from_str(challenger_sk).expect("failed to recover Xpriv from private key");
-    let sk = private_key.inner;
-    let keypair = bitcoin::secp256k1::Keypair::from_secret_key(&secp, &sk);
-    let (internal_key, _parity) = XOnlyPublicKey::from_keypair(&keypair);
-    // let internal_key = Xpub::from_priv(&secp, &challenger_private_key);
-    let challenger_address = Address::p2tr(&secp, internal_key, None, KnownHrp::from(network));
-
-    // query challenger's pre tx value
-    let bitcoin_rpc_client = MempoolClient::new(network);
-
-    let pre_txid = Txid::from_str(challenger_input_txid).expect("failed to parse tx id");
-    let pre_tx = bitcoin_rpc_client
-        .get_tx(&pre_txid.to_string())
-        .expect("tx_id is not valid");
-    let utxo = pre_tx
-        .tx_out(challenger_input_vout as usize)
-        .expect("Invalid vout");
-    let value = utxo.value;
-
-    let out_point = OutPoint {
-        txid: Txid::from_str(challenger_input_txid).unwrap(),
-        vout: challenger_input_vout,
+/// Starts the challenge, submits the funded challenge tx once the committee's raw tx is ready
+/// and monitors it until the challenge ends, optionally requesting the disprove.
+async fn run_challenge(
+    data_dir: &Path,
+    flow: &Flow<'_>,
+    disprove: &Disprove<'_>,
+    storage: &Storage,
+    network: &str,
+    challenge: &ResolvedChallenge,
+    args: &RunChallenge,
+) -> anyhow::Result<()> {
+    let poll_interval = Duration::from_secs(args.poll_interval);
+    let request = &challenge.request;
+
+    // Read before funding the challenge, a disprove must not fail on missing leaf scripts.
+    let leaves = match (&args.reward_address, args.script_index) {
+        (Some(_), None) => Some(read_leaf_scripts(
+            data_dir,
+            challenge,
+            args.leaf_scripts.as_deref(),
+            args.validator_key.as_deref(),
+        )?),
+        _ => None,
     };
 
-    let input = TxIn {
-        previous_output: out_point,
-        script_sig: ScriptBuf::default(), // For a p2wpkh script_sig is empty.
-        sequence: Sequence::MAX,
-        witness: Witness::default(), // Filled in after signing.
-    };
+    let challenge_id = storage
+        .challenge(network, &args.proof_id)?
+        .and_then(|record| record.challenge_id);
+    if challenge_id.is_none() {
+        ensure_proof_is_invalid(flow, challenge, args.proof_file.as_deref(), args.force).await?;
+    }
+    challenge.save(data_dir, storage, network)?;
+    match challenge_id {
+        Some(challenge_id) => println!("Resuming challenge {}", challenge_id),
+        None => match flow.start(request.clone()).await {
+            Ok(challenge_id) => {
+                storage.set_challenge_id(network, &args.proof_id, challenge_id)?;
+                storage.record_operation(
+                    network,
+                    "challenge start",
+                    &args.proof_id,
+                    &format!("challenge id {}", challenge_id),
+                )?;
+                println!("Challenge {} started", challenge_id);
+            }
+            Err(e)
+                if matches!(
+                    committee_error(&e),
+                    Some(Web3Error::ChallengeNodeError(
+                        ChallengeError::ChallengeExists(_)
+                    ))
+                ) =>
+            {
+                println!(
+                    "Challenge of proof {} already exists, resuming",
+                    args.proof_id
+                )
+            }
+            Err(e) => return Err(e),
+        },
+    }
+
+    println!("Waiting for the committee's challenge tx...");
+    loop {
+        let info = flow.info(request.clone()).await?;
+        storage.save_challenge_info(network, &info)?;
+        match info.status {
+            ChallengeStatus::ChallengeNotExist => {
+                anyhow::bail!(FcliError::Committee(format!(
+                    "challenge of proof {} does not exist",
+                    args.proof_id
+                )))
+            }
+            ChallengeStatus::ChallengeCreated | ChallengeStatus::PartialAssertTxReady => {
+                println!("Status: {}", info.status)
+            }
+            ChallengeStatus::ChallengeTxReadyToSubmit => {
+                match finish_challenge(
+                    data_dir,
+                    flow,
+                    storage,
+                    network,
+                    request.clone(),
+                    &args.private_key,
+                    &args.txid,
+                    args.vout,
+                    args.validator_key.as_deref(),
+                )
+                .await
+                {
+                    Ok(txid) => {
+                        println!("Challenge tx {} submitted", txid);
+                        break;
+                    }
+                    Err(e)
+                        if matches!(
+                            committee_error(&e),
+                            Some(Web3Error::ChallengeNodeError(
+                                ChallengeError::ChallengeRawCommitteeTxNotReady
+                            ))
+                        ) =>
+                    {
+                        println!("Committee's raw challenge tx is not ready yet")
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            _ => {
+                println!("Challenge tx already submitted, status: {}", info.status);
+                break;
+            }
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+
+    let mut last_status = None;
+    let mut disprove_requested = false;
+    loop {
+        let info = flow.info(request.clone()).await?;
+        storage.save_challenge_info(network, &info)?;
+        if last_status.as_ref() != Some(&info.status) {
+            println!("\nStatus: {}", info.status);
+            if let Some(challenge_txid) = &info.challenge_txid {
+                println!("Challenge txid: {}", challenge_txid);
+            }
+            if let Some(assert_txid) = &info.assert_txid {
+                println!("Assert txid: {}", assert_txid);
+            }
+            if let Some(disprove_txid) = &info.disprove_txid {
+                println!("Disprove txid: {}", disprove_txid);
+            }
+            last_status = Some(info.status.clone());
+        }
 
-    let change = TxOut {
-        value: value
-            .checked_sub(CHALLENGE_FEE_AMOUNT.unchecked_add(CHALLENGE_AMOUNT))
-            .expect("not enough btc"),
-        script_pubkey: challenger_address.script_pubkey(),
+        match info.status {
+            ChallengeStatus::DisproveTxReadyToHandle if !disprove_requested => {
+                disprove_requested = true;
+                let script_index = match (args.script_index, &leaves) {
+                    (Some(script_index), _) => Some(script_index),
+                    (None, Some(leaves)) => {
+                        discover_script_index(disprove, request, leaves).await?
+                    }
+                    (None, None) => None,
+                };
+                match (&args.reward_address, script_index) {
+                    (Some(reward_address), Some(script_index)) => {
+                        let request =
+                            DisproveRequest::new(&args.proof_id, script_index, reward_address);
+                        flow.disprove(request).await?;
+                        storage.record_operation(
+                            network,
+                            "disprove",
+                            &args.proof_id,
+                            &format!(
+                                "script index {}, reward address {}",
+                                script_index, reward_address
+                            ),
+                        )?;
+                        println!("Disprove request sent, reward address {}", reward_address);
+                    }
+                    (Some(_), None) => println!(
+                        "No leaf script succeeds against the assert tx of proof {}, there is nothing to disprove",
+                        args.proof_id
+                    ),
+                    (None, _) => println!(
+                        "The disprove tx is ready to handle, use `fcli disprove create_disprove_tx` to claim the reward"
+                    ),
+                }
+            }
+            ChallengeStatus::DisproveTxConfirmed
+            | ChallengeStatus::DisproveTxFailed
+            | ChallengeStatus::ChallengeSucceed
+            | ChallengeStatus::ChallengeFailed => {
+                storage.record_operation(
+                    network,
+                    "challenge run",
+                    &args.proof_id,
+                    &info.status.to_string(),
+                )?;
+                println!("\nChallenge complete");
+                return Ok(());
+            }
+            _ => {}
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/// First leaf script succeeding against the confirmed assert tx of the challenge, `None` when
+/// every leaf script fails and the assert tx is consistent.
+async fn discover_script_index(
+    disprove: &Disprove<'_>,
+    request: &ChallengeRequest,
+    leaves: &[LeafScript],
+) -> anyhow::Result<Option<usize>> {
+    let assert_tx = disprove.confirmed_assert_tx(request.clone()).await?;
+    eprintln!(
+        "Evaluating {} leaf scripts against assert tx {}...",
+        leaves.len(),
+        assert_tx.compute_txid()
+    );
+    let script_index = disprove
+        .find_script_indices(&assert_tx, leaves)?
+        .first()
+        .copied();
+    if let Some(script_index) = script_index {
+        eprintln!("Found disprovable script index {}", script_index);
+    }
+    Ok(script_index)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn finish_challenge(
+    data_dir: &Path,
+    flow: &Flow<'_>,
+    storage: &Storage,
+    network: &str,
+    request: ChallengeRequest,
+    private_key: &str,
+    txid: &str,
+    vout: u32,
+    validator_key: Option<&str>,
+) -> anyhow::Result<Txid> {
+    let proof_id = request.proof_id.clone();
+    check_assert_commitment(data_dir, flow, &request, validator_key).await?;
+
+    let key = Auxiliary::from_private_key(private_key, flow.params().network)?;
+    let input = FundingInput::new(txid, vout)?;
+    let (res, challenge_tx) = flow.finish(request, &key, &input).await?;
+
+    let tx = FiammaTransaction::new_challenge_tx(&challenge_tx, None);
+    storage.save_transaction(tx.with_ext_info(&proof_id))?;
+    storage.record_operation(
+        network,
+        "challenge finish",
+        &proof_id,
+        &format!("challenge tx {}", res),
+    )?;
+
+    Ok(res)
+}
+
+/// Checks that the assert tx of the proof commits to the validator's local leaf scripts, only
+/// warns without a validator key.
+async fn check_assert_commitment(
+    data_dir: &Path,
+    flow: &Flow<'_>,
+    request: &ChallengeRequest,
+    validator_key: Option<&str>,
+) -> anyhow::Result<()> {
+    match validator_key {
+        Some(validator_key) => {
+            let assert_tx = flow.assert_tx_of_proof(request, validator_key).await?;
+            verify_assert_commitment(
+                data_dir,
+                flow.params(),
+                &request.vk_hash,
+                validator_key,
+                &assert_tx,
+            )
+        }
+        None => {
+            eprintln!(
+                "Warning: no --validator-key given, the disprove scripts of proof {} are not checked",
+                request.proof_id
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Verifies the challenged proof against the verify key stored with the challenge, `true`
+/// means the proof is valid.
+async fn check_proof(
+    flow: &Flow<'_>,
+    challenge: &ResolvedChallenge,
+    proof_file: Option<&str>,
+) -> anyhow::Result<bool> {
+    let vk = challenge.vk()?;
+    let proof: ProofRes = match proof_file {
+        Some(path) => serde_json::from_reader(BufReader::new(File::open(path)?))?,
+        None => flow.proof(&challenge.request.proof_id).await?,
     };
+    flow.check_proof(&challenge.request, &vk, &challenge.circuit_type, &proof)
+}
 
-    let mut tx = encode::deserialize_hex::<Transaction>(raw_tx).expect("invalid raw assert tx");
+/// Refuses to challenge a proof that verifies locally unless `force` is set. A proof that
+/// can not be checked only produces a warning.
+async fn ensure_proof_is_invalid(
+    flow: &Flow<'_>,
+    challenge: &ResolvedChallenge,
+    proof_file: Option<&str>,
+    force: bool,
+) -> anyhow::Result<()> {
+    let proof_id = &challenge.request.proof_id;
+    match check_proof(flow, challenge, proof_file).await {
+        Ok(false) => Ok(()),
+        Ok(true) if force => {
+            eprintln!(
+                "Warning: proof {} is valid, challenging it anyway",
+                proof_id
+            );
+            Ok(())
+        }
+        Ok(true) => Err(ChallengeError::ProofIsValid(proof_id.clone()).into()),
+        Err(e) => {
+            eprintln!(
+                "Warning: failed to verify proof {} locally: {:#}",
+                proof_id, e
+            );
+            Ok(())
+        }
+    }
+}
 
-    tx.input.push(input);
-    tx.output.push(change);
+/// Circuit type of the challenge stored for the proof, `None` before the challenge is stored.
+pub(crate) fn challenge_circuit_type(
+    storage: &Storage,
+    network: &str,
+    proof_id: &str,
+) -> anyhow::Result<Option<CircuitType>> {
+    storage
+        .challenge(network, proof_id)?
+        .map(|record| CircuitType::from_str(&record.circuit_type))
+        .transpose()
+        .map_err(Into::into)
+}
 
-    // // Get the sighash to sign.
-    let sighash_type = TapSighashType::All;
-    let mut sighasher = SighashCache::new(&mut tx);
+/// Challenge request resolved from the command line, not stored yet, see
+/// [`resolve_challenge`].
+pub(crate) struct ResolvedChallenge {
+    pub(crate) request: ChallengeRequest,
+    pub(crate) circuit_type: CircuitType,
+    /// Verify key file stored with the challenge.
+    vk_path: Option<String>,
+    /// Verify key read from a `--vk-path` file and that file, imported into the circuit
+    /// store when the challenge is saved.
+    import: Option<(Vec<u8>, String)>,
+}
 
-    let input_txouts = vec![
-        TxOut {
-            value: DUST_AMOUNT,
-            script_pubkey: multi_sig_address.script_pubkey(),
-        },
-        TxOut {
-            value,
-            script_pubkey: challenger_address.script_pubkey(),
-        },
-    ];
-    let sighash = sighasher
-        .taproot_key_spend_signature_hash(
-            1,
-            &sighash::Prevouts::All(input_txouts.as_slice()),
-            sighash_type,
+impl ResolvedChallenge {
+    /// Verify key of the challenged circuit.
+    fn vk(&self) -> anyhow::Result<Vec<u8>> {
+        match (&self.import, &self.vk_path) {
+            (Some((vk, _)), _) => Ok(vk.clone()),
+            (None, Some(vk_path)) => read_vk_from_path(vk_path),
+            (None, None) => anyhow::bail!(
+                "no verifier key stored for proof {}, please pass --vk-path",
+                self.request.proof_id
+            ),
+        }
+    }
+
+    /// Imports the verify key into the circuit store and stores the challenge for the next
+    /// commands on the proof.
+    pub(crate) fn save(
+        &self,
+        data_dir: &Path,
+        storage: &Storage,
+        network: &str,
+    ) -> anyhow::Result<()> {
+        let mut vk_path = self.vk_path.clone();
+        if let Some((vk, source)) = &self.import {
+            let store = CircuitStore::open(data_dir)?;
+            let entry = store.import(vk, self.circuit_type.clone(), source)?;
+            vk_path = Some(store.vk_path(&entry).to_string_lossy().into_owned());
+        }
+        storage.save_challenge(
+            network,
+            &self.request,
+            &self.circuit_type.to_string(),
+            vk_path.as_deref(),
         )
-        .expect("failed to create sighash");
-
-    // Sign the sighash using the secp256k1 library (exported by rust-bitcoin).
-    let keypair = bitcoin::secp256k1::Keypair::from_seckey_slice(&secp, sk.as_ref()).unwrap();
-    let leaf_hash = None::<TapLeafHash>;
-    let keypair = match leaf_hash {
-        None => keypair.tap_tweak(&secp, None).to_inner(),
-        Some(_) => keypair, // no tweak for script spend
-    };
+    }
+}
 
-    let msg = bitcoin::secp256k1::Message::from(sighash);
-    let signature = secp.sign_schnorr(&msg, &keypair);
+/// Resolves the challenge request for `committee_version` from `vk_path` when given, otherwise
+/// from the challenge stored by a previous command for the same proof. `vk_path` is either a
+/// verifier key file, which is imported into the circuit store once the challenge is saved, or
+/// the vk hash of an imported circuit. Without `vk_path`, `circuit_type` must match the type of
+/// the stored challenge. Writes nothing, see [`ResolvedChallenge::save`].
+pub(crate) fn resolve_challenge(
+    data_dir: &Path,
+    storage: &Storage,
+    network: &str,
+    proof_id: &str,
+    vk_path: Option<&str>,
+    circuit_type: Option<&str>,
+    committee_version: CommitteeVersionId,
+) -> anyhow::Result<ResolvedChallenge> {
+    let stored = storage.challenge(network, proof_id)?;
+
+    let Some(vk_path) = vk_path else {
+        let record = stored.ok_or_else(|| {
+            anyhow::anyhow!("no challenge stored for proof {proof_id}, please pass --vk-path")
+        })?;
+        // Without a vk the circuit type cannot select anything, it can only contradict the stored one.
+        let stored_type = CircuitType::from_str(&record.circuit_type)?;
+        if let Some(circuit_type) = circuit_type {
+            let circuit_type = CircuitType::from_str(circuit_type)?;
+            if circuit_type != stored_type {
+                anyhow::bail!(
+                    "proof {proof_id} was challenged as a {} circuit, pass --vk-path to challenge it as {}",
+                    record.circuit_type,
+                    circuit_type
+                );
+            }
+        }
+        return Ok(ResolvedChallenge {
+            request: record.request,
+            circuit_type: stored_type,
+            vk_path: record.vk_path,
+            import: None,
+        });
+    };
 
-    let final_signature = taproot::Signature {
-        signature,
-        sighash_type,
+    let store = CircuitStore::open(data_dir)?;
+    let (vk_hash, circuit_type, stored_vk_path, import) = match store.entry(vk_path)? {
+        Some(entry) if !Path::new(vk_path).exists() => {
+            let stored_vk_path = store.vk_path(&entry).to_string_lossy().into_owned();
+            (
+                entry.vk_hash,
+                entry.circuit_type,
+                Some(stored_vk_path),
+                None,
+            )
+        }
+        _ => {
+            let circuit_type = match (circuit_type, &stored) {
+                (Some(circuit_type), _) => CircuitType::from_str(circuit_type)?,
+                (None, Some(record)) => CircuitType::from_str(&record.circuit_type)?,
+                (None, None) => CircuitType::Groth16,
+            };
+            let vk = read_vk_from_path(vk_path)?;
+            validate_vk_bytes(&vk, &circuit_type)
+                .with_context(|| format!("invalid {} verify key {}", circuit_type, vk_path))?;
+            let vk_hash = CircuitInfo::hash(&vk, circuit_type.clone());
+            (vk_hash, circuit_type, None, Some((vk, vk_path.to_string())))
+        }
     };
 
-    // Update the witness stack.
-    let mut witness = Witness::new();
-    witness.push(final_signature.to_vec());
-    *sighasher.witness_mut(1).unwrap() = witness;
+    // A challenge keeps the committee version it was started against.
+    let committee_version = stored
+        .as_ref()
+        .map_or(committee_version, |record| record.request.committee_version);
+    Ok(ResolvedChallenge {
+        request: ChallengeRequest::new(proof_id, &vk_hash)
+            .with_committee_version(committee_version),
+        circuit_type,
+        vk_path: stored_vk_path,
+        import,
+    })
+}
 
-    sighasher.into_transaction().to_owned()
+/// Resolves the challenge request like [`resolve_challenge`] and saves it.
+pub(crate) fn resolve_challenge_request(
+    data_dir: &Path,
+    storage: &Storage,
+    network: &str,
+    proof_id: &str,
+    vk_path: Option<&str>,
+    circuit_type: Option<&str>,
+    committee_version: CommitteeVersionId,
+) -> anyhow::Result<ChallengeRequest> {
+    let challenge = resolve_challenge(
+        data_dir,
+        storage,
+        network,
+        proof_id,
+        vk_path,
+        circuit_type,
+        committee_version,
+    )?;
+    challenge.save(data_dir, storage, network)?;
+    Ok(challenge.request)
 }
@@ -0,0 +1,161 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use clap::Parser;
+use storage::circuit::{CircuitCheck, CircuitStore};
+use types::{circuit::CircuitType, error::FcliError, file::read_vk_from_path};
+use verifier::{fflonk, groth16, validate_vk_bytes, VkFormat};
+
+use super::Settings;
+
+#[derive(Debug, Parser, Clone)]
+#[clap(
+    name = "Circuit",
+    about = "Manage the verifier keys of the local circuit store"
+)]
+pub struct Circuit {
+    #[clap(subcommand)]
+    action: Action,
+}
+
+#[derive(Debug, Parser, Clone)]
+enum Action {
+    #[clap(
+        name = "import",
+        about = "Import a verifier key into the circuit store",
+        long_about = "Validate a verifier key, convert it into the vk.bitvm encoding and store it under its vk hash.\n\nExample:\n  fcli circuit import -p verification_key.json -f snarkjs -c groth16"
+    )]
+    Import(ImportParams),
+    #[clap(name = "ls", about = "List the stored verifier keys")]
+    Ls,
+    #[clap(
+        name = "rm",
+        about = "Remove a circuit, including its scripts, from the circuit store"
+    )]
+    Rm(VkHashParams),
+    #[clap(
+        name = "verify",
+        about = "Check the stored verifier keys against the index"
+    )]
+    Verify(VerifyParams),
+}
+
+#[derive(Debug, Parser, Clone)]
+struct ImportParams {
+    #[clap(short, long, help = "Verifier key path")]
+    path: String,
+
+    #[clap(
+        short,
+        long,
+        help = "Verifier key format, can be bitvm, snarkjs, gnark or arkworks",
+        default_value = "bitvm"
+    )]
+    format: String,
+
+    #[clap(
+        short,
+        long,
+        help = "Circuit type, can be groth16 or fflonk",
+        default_value = "groth16"
+    )]
+    circuit_type: String,
+}
+
+#[derive(Debug, Parser, Clone)]
+struct VkHashParams {
+    #[clap(help = "Vk hash of the circuit")]
+    vk_hash: String,
+}
+
+#[derive(Debug, Parser, Clone)]
+struct VerifyParams {
+    #[clap(help = "Vk hash of the circuit, all circuits are checked when omitted")]
+    vk_hash: Option<String>,
+}
+
+impl Circuit {
+    pub async fn run(self, settings: &Settings) -> anyhow::Result<()> {
+        let store = CircuitStore::open(&settings.data_dir)?;
+
+        match self.action {
+            Action::Import(args) => {
+                let format = VkFormat::from_str(&args.format)
+                    .map_err(|_| anyhow::anyhow!("invalid verifier key format {}", args.format))?;
+                let circuit_type = CircuitType::from_str(&args.circuit_type)?;
+                let bytes = read_vk_from_path(&args.path)?;
+
+                let vk = match circuit_type {
+                    CircuitType::Groth16 => {
+                        groth16::convert_vk(&bytes, format).map(|vk| groth16::encode_vk(&vk))
+                    }
+                    CircuitType::Fflonk => {
+                        fflonk::convert_vk(&bytes, format).map(|vk| fflonk::encode_vk(&vk))
+                    }
+                }
+                .with_context(|| format!("invalid verifier key {}", args.path))?;
+
+                let entry = store.import(&vk, circuit_type, &args.path)?;
+                println!(
+                    "Imported {} circuit {} from {}",
+                    entry.circuit_type, entry.vk_hash, entry.source
+                );
+            }
+            Action::Ls => {
+                let entries = store.list()?;
+                if entries.is_empty() {
+                    println!("No circuit stored yet");
+                }
+                for entry in entries {
+                    println!(
+                        "{} {} {} bytes, imported from {}",
+                        entry.vk_hash, entry.circuit_type, entry.size, entry.source
+                    );
+                }
+            }
+            Action::Rm(args) => {
+                let entry = store.remove(&args.vk_hash)?;
+                println!("Removed {} circuit {}", entry.circuit_type, entry.vk_hash);
+            }
+            Action::Verify(args) => {
+                let entries = match args.vk_hash {
+                    Some(vk_hash) => vec![store.read_vk(&vk_hash)?.1],
+                    None => store.list()?,
+                };
+
+                let mut failed = 0;
+                for entry in &entries {
+                    let result: Result<(), String> = match store.check(entry)? {
+                        CircuitCheck::Ok => {
+                            let (vk, _) = store.read_vk(&entry.vk_hash)?;
+                            validate_vk_bytes(&vk, &entry.circuit_type).map_err(|e| e.to_string())
+                        }
+                        CircuitCheck::Missing => Err("verifier key file is missing".into()),
+                        CircuitCheck::SizeMismatch { expected, actual } => {
+                            Err(format!("size is {} bytes, expected {}", actual, expected))
+                        }
+                        CircuitCheck::Sha256Mismatch => Err("sha256 does not match".into()),
+                        CircuitCheck::HashMismatch => Err("vk hash does not match".into()),
+                    };
+
+                    match result {
+                        Ok(()) => println!("{} {}: ok", entry.vk_hash, entry.circuit_type),
+                        Err(e) => {
+                            failed += 1;
+                            println!("{} {}: {}", entry.vk_hash, entry.circuit_type, e);
+                        }
+                    }
+                }
+
+                if failed > 0 {
+                    anyhow::bail!(FcliError::Validation(format!(
+                        "{} of {} circuits failed verification",
+                        failed,
+                        entries.len()
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
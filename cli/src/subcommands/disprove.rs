@@ -1,7 +1,31 @@
-use clap::Parser;
-use types::disprove::DisproveRequest;
-use wallet::{provider::ProviderParams, signer::Signer, Wallet};
-use web3_decl::jsonrpsee::http_client::HttpClientBuilder;
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use bitcoin::{consensus::encode, Transaction, XOnlyPublicKey};
+use bitcoin_client::api_client::MempoolClient;
+use clap::{ArgGroup, Parser};
+use sdk::{Auxiliary, DisproveFlow, FundingInput};
+use storage::Storage;
+use types::{
+    bitvm::{LeafScript, NUMS_INTERNAL_KEY},
+    disprove::DisproveRequest,
+    error::FcliError,
+    file::{get_leaf_script_path, read_leaf_scripts_from_path},
+    params::ProtocolParams,
+    transaction::FiammaTransaction,
+};
+use wallet::provider::ProviderParams;
+use web3_decl::jsonrpsee::http_client::HttpClient;
+
+use super::{
+    challenge::{challenge_circuit_type, resolve_challenge, ResolvedChallenge},
+    dry_run::{DryRun, DryRunParams},
+    params::{check_protocol_params, committee_version, connect, Settings},
+};
+
+type Flow<'a> = DisproveFlow<'a, HttpClient, MempoolClient>;
 
 #[derive(Debug, Parser, Clone)]
 #[clap(name = "Disprove", about = "CLI for Fiamma validator disprove")]
@@ -14,6 +38,12 @@ pub struct Disprove {
     )]
     network: Option<String>,
 
+    #[clap(
+        long,
+        help = "Esplora API url of the bitcoin backend, defaults to mempool.space"
+    )]
+    esplora_url: Option<String>,
+
     #[clap(subcommand)]
     action: Action,
 }
@@ -22,53 +52,337 @@ pub struct Disprove {
 pub enum Action {
     #[clap(name = "create_disprove_tx")]
     CreateDisproveTx(DisproveTxParams),
+
+    #[clap(
+        name = "find_script_index",
+        about = "Find the disprove script indices by evaluating the leaf scripts locally",
+        long_about = "Fetch the confirmed assert tx of the challenged proof and evaluate every BitVM2 verifier leaf against its witness.\nThe leaves whose script succeeds are the script indices a disprove request can target.\n\nExample:\n  fcli disprove -n testnet find_script_index -p proof_id -v validator_key"
+    )]
+    FindScriptIndex(FindScriptIndexParams),
+
+    #[clap(
+        name = "build_disprove_tx",
+        about = "Build the disprove tx locally and optionally broadcast it",
+        long_about = "Build the disprove tx spending the assert tx's leaf script output through a leaf whose script succeeds, without the committee.\nConnector C is paid to the reward address, the disprove fee comes from the fee input of the challenger.\nThe tx is broadcast through the bitcoin backend with --broadcast.\n\nExample:\n  fcli disprove -n testnet build_disprove_tx -p proof_id -v validator_key -r your_reward_address -t your_txid -o your_vout --private-key your_private_key --broadcast"
+    )]
+    BuildDisproveTx(BuildDisproveTxParams),
 }
 
 #[derive(Debug, Parser, Clone)]
 pub struct DisproveTxParams {
     #[clap(short, long, help = "Proof id of challenged proof")]
     proof_id: String,
-    #[clap(short, long, help = "Index of the disprove script")]
-    script_index: usize,
+    #[clap(
+        short,
+        long,
+        help = "Index of the disprove script, discovered from the leaf scripts when omitted"
+    )]
+    script_index: Option<usize>,
+    #[clap(short, long, help = "Reward address of the disprove")]
+    reward_address: String,
+
+    #[clap(flatten)]
+    leaf_scripts: LeafScriptsParams,
+
+    #[clap(flatten)]
+    dry_run: DryRunParams,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct BuildDisproveTxParams {
+    #[clap(short, long, help = "Proof id of challenged proof")]
+    proof_id: String,
+    #[clap(
+        short,
+        long,
+        help = "Index of the disprove script, discovered from the leaf scripts when omitted"
+    )]
+    script_index: Option<usize>,
     #[clap(short, long, help = "Reward address of the disprove")]
     reward_address: String,
+    #[clap(
+        long,
+        help = "Internal key of the leaf script output, defaults to the BIP341 NUMS point"
+    )]
+    internal_key: Option<String>,
+    #[clap(short, long, help = "Fee input's transaction id")]
+    txid: String,
+    #[clap(short = 'o', long, help = "Fee input's vout")]
+    vout: u32,
+    #[clap(long, help = "Bitcoin private key to sign the fee input")]
+    private_key: String,
+    #[clap(long, help = "Broadcast the disprove tx to the bitcoin network")]
+    broadcast: bool,
+
+    #[clap(flatten)]
+    leaf_scripts: LeafScriptsParams,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct FindScriptIndexParams {
+    #[clap(short, long, help = "Proof id of challenged proof")]
+    proof_id: String,
+
+    #[clap(flatten)]
+    leaf_scripts: LeafScriptsParams,
+}
+
+#[derive(Debug, Parser, Clone)]
+#[clap(group(ArgGroup::new("leaf_scripts_source").args(["validator_key", "leaf_scripts"])))]
+pub struct LeafScriptsParams {
+    #[clap(
+        short,
+        long,
+        help = "Validator key of the challenged staker, locates the stored leaf scripts"
+    )]
+    validator_key: Option<String>,
+
+    #[clap(short, long, help = "Path to the leaf scripts file")]
+    leaf_scripts: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "circuit's verifier key path or the vk hash of an imported circuit, defaults to the stored challenge"
+    )]
+    vk_path: Option<String>,
+
+    #[clap(short, long, help = "Circuit type")]
+    circuit_type: Option<String>,
 }
 
 impl Disprove {
-    pub async fn run(self) -> anyhow::Result<()> {
+    pub async fn run(self, settings: &Settings) -> anyhow::Result<()> {
+        let network = self.network.clone().unwrap_or_else(|| "local".to_string());
         let ctx = if let Some(network) = self.network {
             match network.as_str() {
                 "local" => ProviderParams::local(),
                 "dev" => ProviderParams::dev(),
                 "testnet" => ProviderParams::testnet(),
                 _ => {
-                    anyhow::bail!("invalid network name")
+                    anyhow::bail!(FcliError::InvalidArgument(format!(
+                        "invalid network name {network}"
+                    )))
                 }
             }
         } else {
             ProviderParams::local()
         };
 
-        let client = HttpClientBuilder::default()
-            .build(ctx.http_endpoint.as_str())
-            .unwrap();
-        let signer = Signer::new();
-        let wallet = Wallet::new(client, signer);
+        let wallet = connect(&ctx, &settings.client_config)?;
+        let storage = Storage::open(&settings.data_dir)?;
+        let params = ProtocolParams::new(ctx.network, committee_version(&wallet).await)?;
+        let chain = match &self.esplora_url {
+            Some(url) => MempoolClient::with_base_url(url),
+            None => MempoolClient::new(ctx.network)?,
+        };
+        let flow = DisproveFlow::new(&wallet, &chain, params);
 
         match self.action {
             Action::CreateDisproveTx(args) => {
+                let script_index = match args.script_index {
+                    Some(script_index) => script_index,
+                    None => {
+                        let indices = find_script_indices(
+                            &settings.data_dir,
+                            &flow,
+                            &storage,
+                            &network,
+                            &args.proof_id,
+                            &args.leaf_scripts,
+                            args.dry_run.dry_run,
+                        )
+                        .await?;
+                        let Some(script_index) = indices.first() else {
+                            anyhow::bail!(FcliError::Validation(format!(
+                                "no leaf script succeeds against the assert tx of proof {}, there is nothing to disprove",
+                                args.proof_id
+                            )))
+                        };
+                        eprintln!("Found disprovable script index {}", script_index);
+                        *script_index
+                    }
+                };
+
                 let request =
-                    DisproveRequest::new(&args.proof_id, args.script_index, &args.reward_address);
-                wallet
-                    .disprove(request)
-                    .await
-                    .expect("Failed to challenge a proof");
+                    DisproveRequest::new(&args.proof_id, script_index, &args.reward_address);
+                if args.dry_run.dry_run {
+                    return DryRun::new("disprove", &request)?.emit(&args.dry_run);
+                }
+                flow.request(request).await?;
+                storage.record_operation(
+                    &network,
+                    "disprove",
+                    &args.proof_id,
+                    &format!(
+                        "script index {}, reward address {}",
+                        script_index, args.reward_address
+                    ),
+                )?;
                 println!(
                     "You have send a disprove request with proof_id {:?}, please wait for the result",
                     args.proof_id
                 );
             }
+            Action::FindScriptIndex(args) => {
+                let indices = find_script_indices(
+                    &settings.data_dir,
+                    &flow,
+                    &storage,
+                    &network,
+                    &args.proof_id,
+                    &args.leaf_scripts,
+                    false,
+                )
+                .await?;
+                if indices.is_empty() {
+                    println!("No leaf script succeeds, the assert tx is consistent");
+                } else {
+                    println!("Disprovable script indices: {:?}", indices);
+                }
+            }
+            Action::BuildDisproveTx(args) => {
+                let (leaves, assert_tx) = load_disprove_context(
+                    &settings.data_dir,
+                    &flow,
+                    &storage,
+                    &network,
+                    &args.proof_id,
+                    &args.leaf_scripts,
+                    false,
+                )
+                .await?;
+                let circuit_type = challenge_circuit_type(&storage, &network, &args.proof_id)?;
+                check_protocol_params(&wallet, flow.params(), circuit_type.as_ref()).await;
+                let script_index = match args.script_index {
+                    Some(script_index) => script_index,
+                    None => *flow.find_script_indices(&assert_tx, &leaves)?
+                        .first()
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "no leaf script succeeds against the assert tx of proof {}, there is nothing to disprove",
+                                args.proof_id
+                            )
+                        })?,
+                };
+                let internal_key = XOnlyPublicKey::from_str(
+                    args.internal_key.as_deref().unwrap_or(NUMS_INTERNAL_KEY),
+                )?;
+                let key = Auxiliary::from_private_key(&args.private_key, ctx.network)?;
+                let input = FundingInput::new(&args.txid, args.vout)?;
+                let disprove_tx = flow
+                    .build_disprove_tx(
+                        &assert_tx,
+                        &leaves,
+                        script_index,
+                        internal_key,
+                        &args.reward_address,
+                        &key,
+                        &input,
+                    )
+                    .await?;
+                let disprove_tx_hex = encode::serialize_hex(&disprove_tx);
+                storage.save_transaction(
+                    FiammaTransaction::new_disprove_tx(&disprove_tx, None)
+                        .with_ext_info(&args.proof_id),
+                )?;
+
+                if args.broadcast {
+                    let txid = flow.broadcast(&disprove_tx).await?;
+                    storage.record_operation(
+                        &network,
+                        "disprove broadcast",
+                        &args.proof_id,
+                        &format!("script index {}, disprove tx {}", script_index, txid),
+                    )?;
+                    println!(
+                        "You have broadcast the disprove tx {}, the reward of {} will be paid to {} once it is confirmed",
+                        txid, disprove_tx.output[0].value, args.reward_address
+                    );
+                } else {
+                    println!(
+                        "Disprove tx of script index {}:\n{}",
+                        script_index, disprove_tx_hex
+                    );
+                }
+            }
         }
         Ok(())
     }
 }
+
+/// Loads the leaf scripts and the confirmed assert tx of the challenged proof. The resolved
+/// challenge is stored unless `dry_run` is set.
+async fn load_disprove_context(
+    data_dir: &Path,
+    flow: &Flow<'_>,
+    storage: &Storage,
+    network: &str,
+    proof_id: &str,
+    args: &LeafScriptsParams,
+    dry_run: bool,
+) -> anyhow::Result<(Vec<LeafScript>, Transaction)> {
+    let challenge = resolve_challenge(
+        data_dir,
+        storage,
+        network,
+        proof_id,
+        args.vk_path.as_deref(),
+        args.circuit_type.as_deref(),
+        flow.params().committee_version,
+    )?;
+    if !dry_run {
+        challenge.save(data_dir, storage, network)?;
+    }
+
+    let leaves = read_leaf_scripts(
+        data_dir,
+        &challenge,
+        args.leaf_scripts.as_deref(),
+        args.validator_key.as_deref(),
+    )?;
+    let assert_tx = flow.confirmed_assert_tx(challenge.request).await?;
+
+    Ok((leaves, assert_tx))
+}
+
+/// Reads the leaf scripts of the challenged circuit from `leaf_scripts`, or else from the
+/// circuit store for `validator_key`.
+pub(crate) fn read_leaf_scripts(
+    data_dir: &Path,
+    challenge: &ResolvedChallenge,
+    leaf_scripts: Option<&Path>,
+    validator_key: Option<&str>,
+) -> anyhow::Result<Vec<LeafScript>> {
+    let leaf_scripts_path = match (leaf_scripts, validator_key) {
+        (Some(path), _) => path.to_path_buf(),
+        (None, Some(validator_key)) => get_leaf_script_path(
+            data_dir,
+            &challenge.request.vk_hash,
+            validator_key,
+            challenge.circuit_type.clone(),
+        ),
+        (None, None) => anyhow::bail!("either --validator-key or --leaf-scripts is required"),
+    };
+    read_leaf_scripts_from_path(&leaf_scripts_path)
+}
+
+/// Evaluates the leaf scripts of the challenged proof against its confirmed assert tx.
+async fn find_script_indices(
+    data_dir: &Path,
+    flow: &Flow<'_>,
+    storage: &Storage,
+    network: &str,
+    proof_id: &str,
+    args: &LeafScriptsParams,
+    dry_run: bool,
+) -> anyhow::Result<Vec<usize>> {
+    let (leaves, assert_tx) =
+        load_disprove_context(data_dir, flow, storage, network, proof_id, args, dry_run).await?;
+
+    eprintln!(
+        "Evaluating {} leaf scripts against assert tx {}...",
+        leaves.len(),
+        assert_tx.compute_txid()
+    );
+    flow.find_script_indices(&assert_tx, &leaves)
+}
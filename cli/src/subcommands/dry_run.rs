@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+use bitcoin::{consensus::encode, Transaction};
+use clap::Parser;
+use serde::Serialize;
+
+#[derive(Debug, Parser, Clone)]
+pub struct DryRunParams {
+    #[clap(
+        long,
+        help = "Build, sign and validate everything without sending it to the committee, print the request and txs instead"
+    )]
+    pub dry_run: bool,
+
+    #[clap(
+        long,
+        requires = "dry_run",
+        help = "Write the dry run request and txs as JSON to this file instead of printing them"
+    )]
+    pub dry_run_file: Option<PathBuf>,
+}
+
+/// What a state-changing command would send to the committee.
+#[derive(Debug, Serialize)]
+pub(crate) struct DryRun {
+    /// Committee RPC method the request would be sent with.
+    method: String,
+    request: serde_json::Value,
+    txs: Vec<DryRunTx>,
+}
+
+#[derive(Debug, Serialize)]
+struct DryRunTx {
+    name: String,
+    txid: String,
+    hex: String,
+}
+
+impl DryRun {
+    pub(crate) fn new<R: Serialize>(method: &str, request: &R) -> anyhow::Result<Self> {
+        Ok(Self {
+            method: method.to_string(),
+            request: serde_json::to_value(request)?,
+            txs: vec![],
+        })
+    }
+
+    pub(crate) fn with_tx(mut self, name: &str, tx: &Transaction) -> Self {
+        self.txs.push(DryRunTx {
+            name: name.to_string(),
+            txid: tx.compute_txid().to_string(),
+            hex: encode::serialize_hex(tx),
+        });
+        self
+    }
+
+    /// Prints the dry run as JSON on stdout, or writes it to the `--dry-run-file`. The note
+    /// that nothing was sent goes to stderr, so stdout stays valid JSON.
+    pub(crate) fn emit(&self, params: &DryRunParams) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        match &params.dry_run_file {
+            Some(path) => {
+                std::fs::write(path, json)?;
+                eprintln!(
+                    "Dry run: {} request written to {}, nothing was sent to the committee",
+                    self.method,
+                    path.display()
+                );
+            }
+            None => {
+                println!("{}", json);
+                eprintln!("Dry run: {} request not sent to the committee", self.method);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{absolute, transaction};
+    use types::disprove::DisproveRequest;
+
+    use super::*;
+
+    #[test]
+    fn test_dry_run_json() {
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        };
+        let request = DisproveRequest::new("proof_id", 7, "reward_address");
+        let dry_run = DryRun::new("disprove", &request)
+            .unwrap()
+            .with_tx("disprove_tx", &tx);
+
+        let json = serde_json::to_value(&dry_run).unwrap();
+        assert_eq!(json["method"], "disprove");
+        assert_eq!(json["request"], serde_json::to_value(&request).unwrap());
+        assert_eq!(json["txs"][0]["txid"], tx.compute_txid().to_string());
+        assert_eq!(json["txs"][0]["hex"], encode::serialize_hex(&tx));
+    }
+}
@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+
+use bitcoin::{consensus::encode, Transaction};
+use bitcoin_client::{api_client::MempoolClient, ChainSource};
+use clap::Parser;
+use sdk::{GraphFormat, ValidatorGraph};
+use storage::Storage;
+use types::{
+    error::FcliError,
+    params::ProtocolParams,
+    register::{CircuitTx, QueryAssertTxReq},
+    transaction::TransactionType,
+};
+use wallet::provider::ProviderParams;
+
+use super::params::{committee_version, connect, Settings};
+
+#[derive(Debug, Parser, Clone)]
+#[clap(
+    name = "Graph",
+    about = "Export the validator's tx graph and its on-chain status",
+    long_about = "Export the stake tx, its connectors and the presigned assert, challenge and disprove txs of a validator as a diagram.\nTxs are colored by whether they are confirmed, in the mempool or not broadcast, outputs by whether they are spent.\nA bold edge leads to the tx spending an output on chain, a dashed one to a presigned tx that did not.\n\nExample:\n  fcli graph -n testnet -v validator_key --format mermaid -o graph.mmd"
+)]
+pub struct Graph {
+    #[clap(
+        short,
+        long,
+        help = "Network name, can be local or dev",
+        default_value = "local"
+    )]
+    network: Option<String>,
+
+    #[clap(
+        long,
+        help = "Esplora API url of the bitcoin backend, defaults to mempool.space"
+    )]
+    esplora_url: Option<String>,
+
+    #[clap(short, long, help = "Validator key of the staker")]
+    validator_key: String,
+
+    #[clap(long, help = "Diagram format, dot or mermaid", default_value = "dot")]
+    format: String,
+
+    #[clap(short, long, help = "File to write the diagram to, defaults to stdout")]
+    output: Option<PathBuf>,
+}
+
+impl Graph {
+    pub async fn run(self, settings: &Settings) -> anyhow::Result<()> {
+        let format = self.format.parse::<GraphFormat>()?;
+        let ctx = if let Some(network) = self.network {
+            match network.as_str() {
+                "local" => ProviderParams::local(),
+                "dev" => ProviderParams::dev(),
+                "testnet" => ProviderParams::testnet(),
+                _ => {
+                    anyhow::bail!(FcliError::InvalidArgument(format!(
+                        "invalid network name {network}"
+                    )))
+                }
+            }
+        } else {
+            ProviderParams::local()
+        };
+
+        let wallet = connect(&ctx, &settings.client_config)?;
+        let storage = Storage::open(&settings.data_dir)?;
+        let params = ProtocolParams::new(ctx.network, committee_version(&wallet).await)?;
+        let chain = match &self.esplora_url {
+            Some(url) => MempoolClient::with_base_url(url),
+            None => MempoolClient::new(ctx.network)?,
+        };
+
+        let mut txs = storage
+            .transactions_by_ext_info(&self.validator_key)?
+            .into_iter()
+            .map(|tx| CircuitTx::new("", tx.tx_type, &tx.data))
+            .collect::<Vec<_>>();
+        match wallet
+            .get_committee_assert_txs(QueryAssertTxReq::new(&self.validator_key))
+            .await
+        {
+            Ok(assert_txs) => txs.extend(assert_txs),
+            // The diagram may go to stdout, keep the warning out of it.
+            Err(e) => eprintln!(
+                "Warning: failed to query the committee's assert txs, only the stored txs are exported: {:#}",
+                e
+            ),
+        }
+        if txs.is_empty() {
+            anyhow::bail!(
+                "no txs of validator {} stored or generated by the committee",
+                self.validator_key
+            );
+        }
+
+        // Without a stored stake tx, e.g. registered from another machine, look up the one
+        // the assert txs spend.
+        if !txs.iter().any(|tx| tx.tx_type == TransactionType::StakeTx) {
+            if let Some(assert_tx) = txs
+                .iter()
+                .find(|tx| tx.tx_type == TransactionType::AssertTx)
+            {
+                let assert_tx = encode::deserialize_hex::<Transaction>(&assert_tx.tx_hex)?;
+                if let Some(input) = assert_tx.input.first() {
+                    let stake_tx = chain.get_tx(&input.previous_output.txid).await?;
+                    txs.push(CircuitTx::new(
+                        "",
+                        TransactionType::StakeTx,
+                        &encode::serialize_hex(&stake_tx),
+                    ));
+                }
+            }
+        }
+
+        let diagram = ValidatorGraph::collect(&chain, &params, &txs)
+            .await?
+            .render(format);
+        match &self.output {
+            Some(path) => {
+                std::fs::write(path, diagram)?;
+                println!(
+                    "Graph of validator {} written to {}",
+                    self.validator_key,
+                    path.display()
+                );
+            }
+            None => print!("{}", diagram),
+        }
+        Ok(())
+    }
+}
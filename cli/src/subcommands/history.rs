@@ -0,0 +1,42 @@
+use clap::Parser;
+use storage::Storage;
+
+use super::Settings;
+
+#[derive(Debug, Parser, Clone)]
+#[clap(
+    name = "History",
+    about = "List the operations recorded in the local state"
+)]
+pub struct History {
+    #[clap(short, long, help = "Only list operations of this network")]
+    network: Option<String>,
+
+    #[clap(
+        short,
+        long,
+        help = "Maximum number of operations to list",
+        default_value_t = 20
+    )]
+    limit: u32,
+}
+
+impl History {
+    pub async fn run(self, settings: &Settings) -> anyhow::Result<()> {
+        let storage = Storage::open(&settings.data_dir)?;
+        let operations = storage.operations(self.network.as_deref(), self.limit)?;
+
+        if operations.is_empty() {
+            println!("No operation recorded yet");
+            return Ok(());
+        }
+
+        for op in operations {
+            println!(
+                "{} [{}] {} {}: {}",
+                op.created_at, op.network, op.command, op.subject, op.detail
+            );
+        }
+        Ok(())
+    }
+}
@@ -4,6 +4,9 @@ pub use signer::Signer;
 mod challenge;
 pub use challenge::Challenge;
 
+mod circuit;
+pub use circuit::Circuit;
+
 mod disprove;
 pub use disprove::Disprove;
 
@@ -12,3 +15,25 @@ pub use tx::Tx;
 
 mod register;
 pub use register::Register;
+
+mod history;
+pub use history::History;
+
+mod graph;
+pub use graph::Graph;
+
+mod bundle;
+pub use bundle::Bundle;
+
+mod dry_run;
+
+mod params;
+pub use params::Settings;
+
+mod scripts;
+
+#[cfg(test)]
+mod test_utils;
+
+mod vk;
+pub use vk::Vk;
@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+use types::{circuit::CircuitType, committee::CommitteeVersionId, params::ProtocolParams};
+use wallet::{config::ClientConfig, provider::ProviderParams, signer::Signer, Wallet};
+use web3_decl::jsonrpsee::http_client::HttpClient;
+
+/// Settings of the global options, resolved once from the command line and passed to the
+/// subcommands.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Directory of the local state and circuit store.
+    pub data_dir: PathBuf,
+    /// Committee client settings of the `--rpc-*` options, the defaults for the unset ones.
+    pub client_config: ClientConfig,
+}
+
+/// Wallet talking to the committee of `ctx`.
+pub(crate) fn connect(
+    ctx: &ProviderParams,
+    client_config: &ClientConfig,
+) -> anyhow::Result<Wallet<HttpClient>> {
+    Ok(Wallet::with_http_client(
+        ctx.http_endpoint.as_str(),
+        Signer::new(),
+        client_config.clone(),
+    )?)
+}
+
+/// Compares the committee's protocol params with the ones fcli builds txs with and warns
+/// about every difference, so a mismatch shows up before the committee rejects a tx. Also
+/// warns when the committee does not support `circuit_type`, the circuit the command operates
+/// on, if any.
+pub(crate) async fn check_protocol_params(
+    wallet: &Wallet<HttpClient>,
+    params: &ProtocolParams,
+    circuit_type: Option<&CircuitType>,
+) {
+    let res = match wallet.get_protocol_params().await {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!(
+                "Warning: failed to query the committee's protocol params, txs are built with the {} defaults: {:#}",
+                params.network, e
+            );
+            return;
+        }
+    };
+
+    for mismatch in params.mismatches(&res.params) {
+        eprintln!("Warning: protocol params mismatch, {}", mismatch);
+    }
+    if let Some(circuit_type) = circuit_type.filter(|c| !res.circuit_types.contains(c)) {
+        eprintln!(
+            "Warning: the committee does not support {} circuits",
+            circuit_type
+        );
+    }
+}
+
+/// Committee version to build txs and requests for, the current version of the committee's
+/// version handshake, or the latest version fcli knows when the handshake fails.
+pub(crate) async fn committee_version(wallet: &Wallet<HttpClient>) -> CommitteeVersionId {
+    match wallet.get_committee_version().await {
+        Ok(res) => res.current.version,
+        Err(e) => {
+            eprintln!(
+                "Warning: failed to query the committee version, assuming {:?}: {:#}",
+                CommitteeVersionId::latest(),
+                e
+            );
+            CommitteeVersionId::latest()
+        }
+    }
+}
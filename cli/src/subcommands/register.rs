@@ -1,20 +1,36 @@
-use bitcoin::{
-    consensus::encode, Address, KnownHrp, OutPoint, PrivateKey, ScriptBuf, Transaction, TxOut,
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
+
+use anyhow::Context;
+use bitcoin::{consensus::encode, ScriptBuf, Transaction, Txid};
+use bitcoin_client::{api_client::MempoolClient, errors::ChainError, ChainSource};
 use clap::Parser;
-use transactions::disprove::DisproveTransaction;
+use sdk::{committee_error, offline, Auxiliary, FundingInput, PresignedTxs, RegistrationFlow};
+use serde::{de::DeserializeOwned, Serialize};
+use storage::Storage;
+use tokio::time::{Duration, Instant};
 use types::{
-    constants::{CONNECTOR_C_INDEX, DUST_AMOUNT},
-    register::{CircuitTx, QueryAssertTxReq},
-    transaction::TransactionType,
-    FinishRegisterRequest, RegisterRequest,
+    bitvm::LeafScript,
+    error::{FcliError, RegisterNodeError},
+    file::get_presigned_bundle_path,
+    offline::{SignedRegistration, SignedStep, UnsignedRegistration, UnsignedStep},
+    params::ProtocolParams,
+    register::CircuitTx,
+    transaction::{FiammaTransaction, TransactionType},
 };
-use wallet::{provider::ProviderParams, signer::Signer, Wallet};
-use web3_decl::jsonrpsee::http_client::HttpClientBuilder;
-
-use crate::subcommands::Tx;
+use wallet::provider::ProviderParams;
+use web3_decl::{error::Web3Error, jsonrpsee::http_client::HttpClient};
 
-use super::tx::CreateTx;
+use super::{
+    bundle::export_bundle,
+    dry_run::{DryRun, DryRunParams},
+    params::{check_protocol_params, committee_version, connect, Settings},
+    scripts::{local_leaf_scripts, verify_assert_txs},
+    tx::CreateTx,
+};
 
 #[derive(Debug, Parser, Clone)]
 #[clap(name = "Register", about = "CLI for Fiamma validator registration")]
@@ -43,9 +59,58 @@ pub enum Action {
     #[clap(
         name = "finish",
         about = "Finish register the validator",
-        long_about = "Finish register the validator.\n\nExample:\n  fcli register -n local start -v your_validator_key -s your_private_key -u your_bitcoin_rpc_user -p your_bitcoin_rpc_password"
+        long_about = "Finish register the validator.\n\nExample:\n  fcli register -n local finish -v your_validator_key -s your_private_key\n\nThe validator key defaults to the last registration started on this network."
     )]
     Finish(FinishRegisterInfo),
+
+    #[clap(
+        name = "run",
+        about = "Register the validator end-to-end",
+        long_about = "Start the registration, wait for the committee's assert txs, finish it and track the stake tx until confirmed.\nRe-running it resumes from the last completed step.\n\nExample:\n  fcli register -n testnet run -v your_validator_key -t your_txid -o your_vout -s your_private_key"
+    )]
+    Run(RunRegisterInfo),
+
+    #[clap(
+        name = "rotate",
+        about = "Re-presign the validator's txs for the next committee version",
+        long_about = "Re-presign the stake, assert and challenge txs for the next committee version while the committee is rotating.\nFinish it with `register finish --rotation` once the committee generated the new assert txs.\n\nExample:\n  fcli register -n testnet rotate -v your_validator_key -t your_txid -o your_vout -s your_private_key"
+    )]
+    Rotate(StartRegisterInfo),
+
+    #[clap(
+        name = "prepare",
+        about = "Prepare the start of an air-gapped registration, without keys",
+        long_about = "Fetch the funding UTXO, the multi sig script and the protocol params into an unsigned bundle, to sign with `register sign` on a machine without network access.\n\nExample:\n  fcli register -n testnet prepare -v your_validator_key --public-key your_public_key -t your_txid -o your_vout --output unsigned.json"
+    )]
+    Prepare(PrepareRegisterInfo),
+
+    #[clap(
+        name = "prepare-finish",
+        about = "Prepare the finish of an air-gapped registration, without keys",
+        long_about = "Fetch the assert txs the committee generated, the multi sig script and the protocol params into an unsigned bundle, to sign with `register sign` on a machine without network access.\n\nExample:\n  fcli register -n testnet prepare-finish -v your_validator_key --output unsigned.json\n\nThe validator key and the public key default to the last registration submitted on this network."
+    )]
+    PrepareFinish(PrepareFinishInfo),
+
+    #[clap(
+        name = "sign",
+        about = "Presign the txs of an unsigned bundle, offline",
+        long_about = "Presign the stake, assert and challenge txs or the disprove txs of a bundle from `register prepare` or `register prepare-finish`.\nNeeds no network access, the network comes with the bundle.\n\nExample:\n  fcli register sign --bundle unsigned.json -s your_private_key --output signed.json"
+    )]
+    Sign(SignRegisterInfo),
+
+    #[clap(
+        name = "submit",
+        about = "Submit a bundle signed with `register sign`",
+        long_about = "Submit the presigned txs of a bundle signed with `register sign` to the committee.\n\nExample:\n  fcli register -n testnet submit --bundle signed.json"
+    )]
+    Submit(SubmitRegisterInfo),
+
+    #[clap(
+        name = "public-key",
+        about = "Print the public key of a private key, offline",
+        long_about = "Print the public key `register prepare` needs, without network access.\n\nExample:\n  fcli register public-key -s your_private_key"
+    )]
+    PublicKey(PublicKeyInfo),
 }
 
 #[derive(Debug, Parser, Clone)]
@@ -55,159 +120,949 @@ pub struct StartRegisterInfo {
 
     #[clap(flatten)]
     pub create_tx: CreateTx,
+
+    #[clap(flatten)]
+    pub dry_run: DryRunParams,
 }
 
 #[derive(Debug, Parser, Clone)]
 pub struct FinishRegisterInfo {
     #[clap(short, long, help = "Fiamma validator key")]
-    pub validator_key: String,
+    pub validator_key: Option<String>,
 
     #[clap(short = 's', long, help = "Bitcoin private key to sign stake tx")]
     pub private_key: String,
+
+    #[clap(
+        long,
+        help = "Finish a registration re-presigned for the next committee version with `register rotate`"
+    )]
+    pub rotation: bool,
+
+    #[clap(flatten)]
+    pub dry_run: DryRunParams,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct RunRegisterInfo {
+    #[clap(short, long, help = "Fiamma validator key")]
+    pub validator_key: String,
+
+    #[clap(flatten)]
+    pub create_tx: CreateTx,
+
+    #[clap(
+        long,
+        help = "Number of circuits the committee must generate assert txs for",
+        default_value_t = 1
+    )]
+    pub min_circuits: usize,
+
+    #[clap(long, help = "Seconds between two polls", default_value_t = 30)]
+    pub poll_interval: u64,
+
+    #[clap(
+        long,
+        help = "Seconds to wait for the assert txs and for the stake tx confirmation before giving up",
+        default_value_t = 86400
+    )]
+    pub timeout: u64,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct PrepareRegisterInfo {
+    #[clap(short, long, help = "Fiamma validator key")]
+    pub validator_key: String,
+
+    #[clap(long, help = "Public key of the bitcoin key signing the txs")]
+    pub public_key: String,
+
+    #[clap(short, long, help = "Stake input's transaction id")]
+    pub txid: String,
+
+    #[clap(short = 'o', long, help = "Stake input's vout")]
+    pub vout: u32,
+
+    #[clap(
+        long,
+        help = "Prepare the txs for the next committee version while it rotates"
+    )]
+    pub rotation: bool,
+
+    #[clap(long, help = "File to write the unsigned bundle to")]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct PrepareFinishInfo {
+    #[clap(short, long, help = "Fiamma validator key")]
+    pub validator_key: Option<String>,
+
+    #[clap(long, help = "Public key of the bitcoin key signing the txs")]
+    pub public_key: Option<String>,
+
+    #[clap(
+        long,
+        help = "Prepare the finish of a registration re-presigned for the next committee version"
+    )]
+    pub rotation: bool,
+
+    #[clap(long, help = "File to write the unsigned bundle to")]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct SignRegisterInfo {
+    #[clap(
+        long,
+        help = "Unsigned bundle from `register prepare` or `register prepare-finish`"
+    )]
+    pub bundle: PathBuf,
+
+    #[clap(short = 's', long, help = "Bitcoin private key to sign the txs")]
+    pub private_key: String,
+
+    #[clap(long, help = "File to write the signed bundle to")]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct SubmitRegisterInfo {
+    #[clap(long, help = "Signed bundle from `register sign`")]
+    pub bundle: PathBuf,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct PublicKeyInfo {
+    #[clap(short = 's', long, help = "Bitcoin private key")]
+    pub private_key: String,
 }
 
 impl Register {
-    pub async fn run(self) -> anyhow::Result<()> {
+    pub async fn run(self, settings: &Settings) -> anyhow::Result<()> {
+        let network = self.network.clone().unwrap_or_else(|| "local".to_string());
         let ctx = if let Some(network) = self.network {
             match network.as_str() {
                 "local" => ProviderParams::local(),
                 "dev" => ProviderParams::dev(),
                 "testnet" => ProviderParams::testnet(),
                 _ => {
-                    anyhow::bail!("invalid network name")
+                    anyhow::bail!(FcliError::InvalidArgument(format!(
+                        "invalid network name {network}"
+                    )))
                 }
             }
         } else {
             ProviderParams::local()
         };
 
-        let client = HttpClientBuilder::default()
-            .build(ctx.http_endpoint.as_str())
-            .unwrap();
-        let signer = Signer::new();
-        let wallet = Wallet::new(client, signer);
+        // The offline steps must not touch the network.
+        let action = match self.action {
+            Action::Sign(args) => return sign_bundle(&settings.data_dir, &args),
+            Action::PublicKey(args) => {
+                let key = Auxiliary::from_private_key(&args.private_key, ctx.network)?;
+                println!("{}", key.pubkey);
+                return Ok(());
+            }
+            action => action,
+        };
+
+        let wallet = connect(&ctx, &settings.client_config)?;
+        let storage = Storage::open(&settings.data_dir)?;
+        let params = ProtocolParams::new(ctx.network, committee_version(&wallet).await)?;
+        let chain = MempoolClient::new(ctx.network)?;
 
-        match self.action {
+        match action {
             Action::Start(args) => {
-                let aux = Tx::parse_private_key(&args.create_tx.private_key, &ctx);
-                let (stake_tx, assert_tx, challenge_tx) =
-                    Tx::create_presign_transactions(&args.create_tx, &ctx, &wallet, &aux).await;
-                let stake_tx_hex = encode::serialize_hex(&stake_tx);
-                let assert_tx_hex = encode::serialize_hex(&assert_tx);
-                let challenge_tx_hex = encode::serialize_hex(&challenge_tx);
-
-                // call register rpc
-                let req = RegisterRequest::new(
-                    &args.validator_key,
-                    &aux.pubkey,
-                    &stake_tx_hex,
-                    &assert_tx_hex,
-                    &challenge_tx_hex,
-                );
+                check_protocol_params(&wallet, &params, None).await;
+                let flow = RegistrationFlow::new(&wallet, &chain, params);
+                let key = args.create_tx.key(ctx.network)?;
+                let multi_sig_script = flow.multi_sig_script(&key).await?;
+                let presign_txs = flow
+                    .create_presign_transactions(
+                        &key,
+                        &args.create_tx.funding_input()?,
+                        &multi_sig_script,
+                    )
+                    .await?;
+                if args.dry_run.dry_run {
+                    return dry_run_start(&flow, &args, &key, &presign_txs).await;
+                }
 
-                let register_id = wallet
-                    .start_register(req)
-                    .await
-                    .expect("Failed to register new node");
+                let register_id = start_register(
+                    &flow,
+                    &storage,
+                    &network,
+                    &args.validator_key,
+                    &key,
+                    &presign_txs,
+                )
+                .await?;
 
                 println!("You have submitted your registration application.\nThe registration number is {}, please wait patiently.", register_id);
             }
             Action::Finish(args) => {
-                let aux = Tx::parse_private_key(&args.private_key, &ctx);
-
-                let multi_sig_script = wallet
-                    .get_multi_sig_script(&aux.pubkey)
-                    .await
-                    .expect("Failed to get multi_sig script");
-                let multi_sig_addr = Address::p2wsh(&multi_sig_script, KnownHrp::from(ctx.network));
-                let script_pubkey = multi_sig_addr.script_pubkey();
-
-                let request = QueryAssertTxReq::new(&args.validator_key);
-                let assert_txs = wallet
-                    .get_committee_assert_txs(request)
-                    .await
-                    .expect("failed to get assert tx of validator");
-
-                let disprove_txs = assert_txs
-                    .iter()
-                    .map(|assert_tx| {
-                        create_disprove_tx(
-                            &aux.private_key,
-                            script_pubkey.clone(),
-                            multi_sig_script.clone(),
-                            assert_tx,
-                        )
-                    })
-                    .collect::<Vec<CircuitTx>>();
+                let key = Auxiliary::from_private_key(&args.private_key, ctx.network)?;
+                let flow = if args.rotation {
+                    RegistrationFlow::for_next_committee(&wallet, &chain, ctx.network).await?
+                } else {
+                    check_protocol_params(&wallet, &params, None).await;
+                    RegistrationFlow::new(&wallet, &chain, params)
+                };
+                let validator_key = match args.validator_key {
+                    Some(validator_key) => validator_key,
+                    None => storage
+                        .latest_registration(&network)?
+                        .map(|record| record.validator_key)
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "no registration stored for network {network}, please pass --validator-key"
+                            )
+                        })?,
+                };
 
-                let request = FinishRegisterRequest::new(&args.validator_key, &disprove_txs);
+                let assert_txs = flow.assert_txs(&validator_key).await?;
+                let multi_sig_script = flow.multi_sig_script(&key).await?;
+                verify_assert_txs(
+                    &settings.data_dir,
+                    flow.params(),
+                    &multi_sig_script,
+                    &validator_key,
+                    &assert_txs,
+                )?;
+                let leaves = local_leaf_scripts(&settings.data_dir, &validator_key, &assert_txs)?;
+                if args.dry_run.dry_run {
+                    let (request, disprove_txs) = flow.finish_request(
+                        &validator_key,
+                        &key,
+                        &multi_sig_script,
+                        &assert_txs,
+                        &leaves,
+                    )?;
+                    let mut dry_run = DryRun::new("finishRegister", &request)?;
+                    for circuit_tx in &disprove_txs {
+                        let disprove_tx =
+                            encode::deserialize_hex::<Transaction>(&circuit_tx.tx_hex)?;
+                        dry_run = dry_run
+                            .with_tx(&format!("disprove_tx {}", circuit_tx.vk_hash), &disprove_tx);
+                    }
+                    return dry_run.emit(&args.dry_run);
+                }
 
-                let register_id = wallet
-                    .finish_register(request)
-                    .await
-                    .expect("Failed to register new node");
+                let register_id = finish_register(
+                    &flow,
+                    &multi_sig_script,
+                    &storage,
+                    &network,
+                    &validator_key,
+                    &key,
+                    &assert_txs,
+                    &leaves,
+                )
+                .await?;
+                save_presigned_bundle(
+                    &settings.data_dir,
+                    &chain,
+                    &storage,
+                    ctx.network,
+                    &validator_key,
+                    &assert_txs,
+                )
+                .await;
 
                 println!("You have finished your registration application.\nThe registration number is {}, please wait patiently.", register_id);
             }
+            Action::Run(args) => {
+                check_protocol_params(&wallet, &params, None).await;
+                let flow = RegistrationFlow::new(&wallet, &chain, params);
+                run_register(&settings.data_dir, &flow, &chain, &storage, &network, &args).await?;
+            }
+            Action::Rotate(args) => {
+                let flow =
+                    RegistrationFlow::for_next_committee(&wallet, &chain, ctx.network).await?;
+                let key = args.create_tx.key(ctx.network)?;
+                let multi_sig_script = flow.multi_sig_script(&key).await?;
+                let presign_txs = flow
+                    .create_presign_transactions(
+                        &key,
+                        &args.create_tx.funding_input()?,
+                        &multi_sig_script,
+                    )
+                    .await?;
+                if args.dry_run.dry_run {
+                    return dry_run_start(&flow, &args, &key, &presign_txs).await;
+                }
+
+                let register_id = start_register(
+                    &flow,
+                    &storage,
+                    &network,
+                    &args.validator_key,
+                    &key,
+                    &presign_txs,
+                )
+                .await?;
+
+                println!(
+                    "Txs re-presigned for committee version {:?}, the registration number is {}.\nFinish it with `register finish --rotation` once the committee generated the new assert txs.",
+                    flow.params().committee_version, register_id
+                );
+            }
+            Action::Prepare(args) => {
+                let flow = if args.rotation {
+                    RegistrationFlow::for_next_committee(&wallet, &chain, ctx.network).await?
+                } else {
+                    check_protocol_params(&wallet, &params, None).await;
+                    RegistrationFlow::new(&wallet, &chain, params)
+                };
+                let input = FundingInput::new(&args.txid, args.vout)?;
+                let bundle = flow
+                    .prepare_start(&args.validator_key, &args.public_key, &input)
+                    .await?;
+                write_bundle(&args.output, &bundle)?;
+
+                println!(
+                    "Unsigned start bundle of validator {} written to {}.\nSign it offline with `register sign`.",
+                    bundle.validator_key,
+                    args.output.display()
+                );
+            }
+            Action::PrepareFinish(args) => {
+                let flow = if args.rotation {
+                    RegistrationFlow::for_next_committee(&wallet, &chain, ctx.network).await?
+                } else {
+                    check_protocol_params(&wallet, &params, None).await;
+                    RegistrationFlow::new(&wallet, &chain, params)
+                };
+                let record = match &args.validator_key {
+                    Some(validator_key) => storage.registration(&network, validator_key)?,
+                    None => storage.latest_registration(&network)?,
+                };
+                let validator_key = args
+                    .validator_key
+                    .or_else(|| record.as_ref().map(|record| record.validator_key.clone()))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "no registration stored for network {network}, please pass --validator-key"
+                        )
+                    })?;
+                let public_key = args
+                    .public_key
+                    .or_else(|| record.map(|record| record.public_key))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "no registration of validator {validator_key} stored, please pass --public-key"
+                        )
+                    })?;
+
+                let bundle = flow.prepare_finish(&validator_key, &public_key).await?;
+                write_bundle(&args.output, &bundle)?;
+
+                println!(
+                    "Unsigned finish bundle of validator {} written to {}.\nSign it offline with `register sign`.",
+                    validator_key,
+                    args.output.display()
+                );
+            }
+            Action::Submit(args) => {
+                let bundle = read_bundle::<SignedRegistration>(&args.bundle)?;
+                let flow = RegistrationFlow::new(&wallet, &chain, params);
+                submit_bundle(
+                    &settings.data_dir,
+                    &flow,
+                    &chain,
+                    &storage,
+                    &network,
+                    &bundle,
+                )
+                .await?;
+            }
+            Action::Sign(_) | Action::PublicKey(_) => unreachable!("offline actions run first"),
         }
         Ok(())
     }
 }
 
-fn create_disprove_tx(
-    private_key: &PrivateKey,
-    script_pubkey: ScriptBuf,
-    multi_sig_script: ScriptBuf,
-    circuit_assert_tx: &CircuitTx,
-) -> CircuitTx {
-    let assert_tx = encode::deserialize_hex::<Transaction>(&circuit_assert_tx.tx_hex)
-        .expect("failed to decode assert tx");
+/// Presigns an unsigned bundle, without network access.
+fn sign_bundle(data_dir: &Path, args: &SignRegisterInfo) -> anyhow::Result<()> {
+    let bundle = read_bundle::<UnsignedRegistration>(&args.bundle)?;
+    let key = Auxiliary::from_private_key(&args.private_key, bundle.params.network)?;
+    if let UnsignedStep::Finish { assert_txs } = &bundle.step {
+        verify_assert_txs(
+            data_dir,
+            &bundle.params,
+            &bundle.multi_sig_script,
+            &bundle.validator_key,
+            assert_txs,
+        )?;
+    }
+
+    let leaves = match &bundle.step {
+        UnsignedStep::Finish { assert_txs } => {
+            local_leaf_scripts(data_dir, &bundle.validator_key, assert_txs)?
+        }
+        UnsignedStep::Start { .. } => HashMap::new(),
+    };
+    if let Some(amounts) = offline::stake_amounts(&bundle, &key)? {
+        println!(
+            "Stake tx locks {} to the multi sig script, pays a fee of {} and returns {} of change",
+            amounts.stake, amounts.fee, amounts.change
+        );
+    }
+    let signed = offline::sign(&bundle, &key, &leaves)?;
+    write_bundle(&args.output, &signed)?;
+
+    match &signed.step {
+        SignedStep::Start { stake_tx, .. } => {
+            let stake_tx = encode::deserialize_hex::<Transaction>(stake_tx)?;
+            println!(
+                "Stake tx {} and its assert and challenge txs presigned",
+                stake_tx.compute_txid()
+            );
+        }
+        SignedStep::Finish { disprove_txs } => {
+            println!("{} disprove txs presigned", disprove_txs.len());
+        }
+    }
+    println!(
+        "Signed bundle written to {}, submit it with `register submit`.",
+        args.output.display()
+    );
+    Ok(())
+}
+
+/// Submits a signed bundle and stores its register id and txs like `register start` and
+/// `register finish` do.
+async fn submit_bundle(
+    data_dir: &Path,
+    flow: &Flow<'_>,
+    chain: &MempoolClient,
+    storage: &Storage,
+    network: &str,
+    bundle: &SignedRegistration,
+) -> anyhow::Result<()> {
+    let register_id = flow.submit(bundle).await?;
+    let rotation = bundle.params.committee_version != flow.params().committee_version;
+    match &bundle.step {
+        SignedStep::Start {
+            stake_tx,
+            assert_tx,
+            challenge_tx,
+        } => {
+            let presign_txs = PresignedTxs {
+                stake_tx: encode::deserialize_hex(stake_tx)?,
+                assert_tx: encode::deserialize_hex(assert_tx)?,
+                challenge_tx: encode::deserialize_hex(challenge_tx)?,
+            };
+            record_start(
+                storage,
+                network,
+                &bundle.validator_key,
+                &bundle.public_key,
+                register_id,
+                &presign_txs,
+                &bundle.params,
+                rotation,
+            )?;
+            println!("You have submitted your registration application.\nThe registration number is {}, please wait patiently.", register_id);
+        }
+        SignedStep::Finish { disprove_txs } => {
+            record_finish(
+                storage,
+                network,
+                &bundle.validator_key,
+                &bundle.public_key,
+                register_id,
+                disprove_txs,
+                rotation,
+            )?;
+            let assert_txs = flow
+                .assert_txs(&bundle.validator_key)
+                .await
+                .unwrap_or_default();
+            save_presigned_bundle(
+                data_dir,
+                chain,
+                storage,
+                bundle.params.network,
+                &bundle.validator_key,
+                &assert_txs,
+            )
+            .await;
+            println!("You have finished your registration application.\nThe registration number is {}, please wait patiently.", register_id);
+        }
+    }
+    Ok(())
+}
+
+fn read_bundle<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read bundle {}", path.display()))?;
+    serde_json::from_str(&json).map_err(|e| {
+        FcliError::InvalidArgument(format!("invalid bundle {}: {}", path.display(), e)).into()
+    })
+}
+
+fn write_bundle<T: Serialize>(path: &Path, bundle: &T) -> anyhow::Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(bundle)?)?;
+    Ok(())
+}
+
+type Flow<'a> = RegistrationFlow<'a, HttpClient, MempoolClient>;
+
+/// Prints the `startRegister` request of the presigned txs instead of submitting it.
+async fn dry_run_start(
+    flow: &Flow<'_>,
+    args: &StartRegisterInfo,
+    key: &Auxiliary,
+    presign_txs: &PresignedTxs,
+) -> anyhow::Result<()> {
+    let request = flow
+        .start_request(&args.validator_key, key, presign_txs)
+        .await?;
+    DryRun::new("startRegister", &request)?
+        .with_tx("stake_tx", &presign_txs.stake_tx)
+        .with_tx("assert_tx", &presign_txs.assert_tx)
+        .with_tx("challenge_tx", &presign_txs.challenge_tx)
+        .emit(&args.dry_run)
+}
+
+/// Drives start -> wait for assert txs -> finish -> stake tx confirmation, resuming from
+/// whatever step a previous run (or the committee) has already completed.
+async fn run_register(
+    data_dir: &Path,
+    flow: &Flow<'_>,
+    chain: &MempoolClient,
+    storage: &Storage,
+    network: &str,
+    args: &RunRegisterInfo,
+) -> anyhow::Result<()> {
+    let validator_key = &args.validator_key;
+    let poll_interval = Duration::from_secs(args.poll_interval);
+    let timeout = Duration::from_secs(args.timeout);
+    let key = args.create_tx.key(flow.params().network)?;
+    let multi_sig_script = flow.multi_sig_script(&key).await?;
+
+    let record = storage.registration(network, validator_key)?;
+    let mut stake_txid = stored_stake_txid(storage, validator_key)?;
+
+    match &record {
+        Some(record) => println!(
+            "Resuming registration {} of validator {}",
+            record.register_id, validator_key
+        ),
+        None => {
+            let presign_txs = flow
+                .create_presign_transactions(
+                    &key,
+                    &args.create_tx.funding_input()?,
+                    &multi_sig_script,
+                )
+                .await?;
+
+            match start_register(flow, storage, network, validator_key, &key, &presign_txs).await {
+                Ok(register_id) => {
+                    stake_txid = Some(presign_txs.stake_tx.compute_txid());
+                    println!(
+                        "Registration {} submitted, waiting for the committee to generate assert txs",
+                        register_id
+                    )
+                }
+                // The stake tx built above was never submitted, only a stored one is tracked.
+                Err(e) if is_validator_key_registered(&e) => println!(
+                    "Validator {} is already registered with the committee, resuming",
+                    validator_key
+                ),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    if !record.as_ref().is_some_and(|record| record.finished) {
+        let assert_txs = wait_for_assert_txs(
+            flow,
+            validator_key,
+            args.min_circuits,
+            poll_interval,
+            timeout,
+        )
+        .await?;
+        verify_assert_txs(
+            data_dir,
+            flow.params(),
+            &multi_sig_script,
+            validator_key,
+            &assert_txs,
+        )?;
+        let leaves = local_leaf_scripts(data_dir, validator_key, &assert_txs)?;
+        let register_id = finish_register(
+            flow,
+            &multi_sig_script,
+            storage,
+            network,
+            validator_key,
+            &key,
+            &assert_txs,
+            &leaves,
+        )
+        .await?;
+        save_presigned_bundle(
+            data_dir,
+            chain,
+            storage,
+            flow.params().network,
+            validator_key,
+            &assert_txs,
+        )
+        .await;
+        println!(
+            "Registration {} finished, {} disprove txs presigned",
+            register_id,
+            assert_txs.len()
+        );
+    } else {
+        println!("Registration already finished");
+    }
+
+    let Some(stake_txid) = stake_txid else {
+        println!(
+            "Stake tx of validator {} is not stored locally, skip tracking it",
+            validator_key
+        );
+        return Ok(());
+    };
+
+    println!("Waiting for stake tx {} to be confirmed...", stake_txid);
+    let deadline = Instant::now() + timeout;
+    loop {
+        match chain.get_tx_status(&stake_txid).await {
+            Ok(status) if status.confirmed => {
+                println!(
+                    "Stake tx {} confirmed at height {}, the registration is complete",
+                    stake_txid,
+                    status.block_height.unwrap_or_default()
+                );
+                storage.record_operation(
+                    network,
+                    "register run",
+                    validator_key,
+                    &format!("stake tx {} confirmed", stake_txid),
+                )?;
+                return Ok(());
+            }
+            Ok(_) => println!("Stake tx is not confirmed yet"),
+            Err(e @ (ChainError::TxNotFound(_) | ChainError::Http(_))) => {
+                println!("Query stake tx status error: {}", e)
+            }
+            Err(e) => return Err(e.into()),
+        }
+        if Instant::now() >= deadline {
+            anyhow::bail!(
+                "stake tx {} is not confirmed after {} seconds, run `register run` again to keep waiting",
+                stake_txid,
+                timeout.as_secs()
+            );
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+async fn start_register<C: ChainSource>(
+    flow: &RegistrationFlow<'_, HttpClient, C>,
+    storage: &Storage,
+    network: &str,
+    validator_key: &str,
+    key: &Auxiliary,
+    presign_txs: &PresignedTxs,
+) -> anyhow::Result<u32> {
+    let register_id = flow.start(validator_key, key, presign_txs).await?;
+    record_start(
+        storage,
+        network,
+        validator_key,
+        &key.pubkey,
+        register_id,
+        presign_txs,
+        flow.params(),
+        flow.is_rotation(),
+    )?;
 
-    let assert_tx_id = assert_tx.compute_txid();
+    Ok(register_id)
+}
 
-    let out_point = vec![OutPoint {
-        txid: assert_tx_id,
-        vout: CONNECTOR_C_INDEX,
-    }];
+#[allow(clippy::too_many_arguments)]
+fn record_start(
+    storage: &Storage,
+    network: &str,
+    validator_key: &str,
+    public_key: &str,
+    register_id: u32,
+    presign_txs: &PresignedTxs,
+    params: &ProtocolParams,
+    rotation: bool,
+) -> anyhow::Result<()> {
+    let PresignedTxs {
+        stake_tx,
+        assert_tx,
+        challenge_tx,
+    } = presign_txs;
+    if rotation {
+        storage.save_rotation(
+            network,
+            validator_key,
+            params.committee_version as u32,
+            public_key,
+            register_id,
+        )?;
+    } else {
+        storage.save_registration(network, validator_key, public_key, register_id)?;
+    }
+    for tx in [
+        FiammaTransaction::new_stake_tx(stake_tx, Some(register_id)),
+        FiammaTransaction::new_assert_tx(assert_tx, Some(register_id)),
+        FiammaTransaction::new_challenge_tx(challenge_tx, Some(register_id)),
+    ] {
+        storage.save_transaction(tx.with_ext_info(validator_key))?;
+    }
+    storage.record_operation(
+        network,
+        "register start",
+        validator_key,
+        &format!(
+            "register id {}, stake tx {}, committee version {:?}",
+            register_id,
+            stake_tx.compute_txid(),
+            params.committee_version
+        ),
+    )?;
+    Ok(())
+}
 
-    let tx_out = vec![TxOut {
-        value: DUST_AMOUNT,
-        script_pubkey,
-    }];
+#[allow(clippy::too_many_arguments)]
+async fn finish_register(
+    flow: &Flow<'_>,
+    multi_sig_script: &ScriptBuf,
+    storage: &Storage,
+    network: &str,
+    validator_key: &str,
+    key: &Auxiliary,
+    assert_txs: &[CircuitTx],
+    leaves: &HashMap<String, Vec<LeafScript>>,
+) -> anyhow::Result<u32> {
+    let (register_id, disprove_txs) = flow
+        .finish(validator_key, key, multi_sig_script, assert_txs, leaves)
+        .await?;
+    record_finish(
+        storage,
+        network,
+        validator_key,
+        &key.pubkey,
+        register_id,
+        &disprove_txs,
+        flow.is_rotation(),
+    )?;
 
-    let input_utxos: Vec<(OutPoint, TxOut)> = out_point.into_iter().zip(tx_out).collect();
+    Ok(register_id)
+}
 
-    let disprove_tx = DisproveTransaction::new(private_key, input_utxos, multi_sig_script);
+fn record_finish(
+    storage: &Storage,
+    network: &str,
+    validator_key: &str,
+    public_key: &str,
+    register_id: u32,
+    disprove_txs: &[CircuitTx],
+    rotation: bool,
+) -> anyhow::Result<()> {
+    for circuit_tx in disprove_txs {
+        let disprove_tx = encode::deserialize_hex::<Transaction>(&circuit_tx.tx_hex)?;
+        let tx = FiammaTransaction::new_disprove_tx(&disprove_tx, Some(register_id));
+        storage.save_transaction(tx.with_ext_info(validator_key))?;
+    }
+    if rotation {
+        storage.finish_rotation(network, validator_key, public_key, register_id)?;
+    } else {
+        storage.finish_registration(network, validator_key, public_key, register_id)?;
+    }
+    storage.record_operation(
+        network,
+        "register finish",
+        validator_key,
+        &format!(
+            "register id {}, {} disprove txs presigned",
+            register_id,
+            disprove_txs.len()
+        ),
+    )?;
+    Ok(())
+}
 
-    let disprove_tx_hex = encode::serialize_hex(&disprove_tx.tx);
+/// Writes the presigned tx bundle of the validator once its registration finished. A failure
+/// only warns, the registration itself succeeded.
+async fn save_presigned_bundle(
+    data_dir: &Path,
+    chain: &MempoolClient,
+    storage: &Storage,
+    network: bitcoin::Network,
+    validator_key: &str,
+    assert_txs: &[CircuitTx],
+) {
+    let path = get_presigned_bundle_path(data_dir, validator_key);
+    match export_bundle(chain, storage, network, validator_key, assert_txs, &path).await {
+        Ok(bundle) => println!(
+            "{} presigned txs written to {}, broadcast them with `fcli bundle broadcast` if the committee is unreachable",
+            bundle.txs.len(),
+            path.display()
+        ),
+        Err(e) => println!(
+            "Warning: failed to write the presigned tx bundle, export it later with `fcli bundle export`: {:#}",
+            e
+        ),
+    }
+}
 
-    CircuitTx {
-        vk_hash: circuit_assert_tx.vk_hash.clone(),
-        tx_type: TransactionType::DisproveTx,
-        tx_hex: disprove_tx_hex,
+/// Polls the committee until it has generated assert txs for at least `min_circuits` circuits,
+/// for at most `timeout`. Only a missing assert tx is waited out, the wallet already retries
+/// transport failures and any other error is returned.
+async fn wait_for_assert_txs<C: ChainSource>(
+    flow: &RegistrationFlow<'_, HttpClient, C>,
+    validator_key: &str,
+    min_circuits: usize,
+    poll_interval: Duration,
+    timeout: Duration,
+) -> anyhow::Result<Vec<CircuitTx>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match flow.assert_txs(validator_key).await {
+            Ok(assert_txs) if assert_txs.len() >= min_circuits.max(1) => return Ok(assert_txs),
+            Ok(assert_txs) => println!(
+                "Committee has generated assert txs for {} of {} circuits",
+                assert_txs.len(),
+                min_circuits
+            ),
+            Err(e)
+                if matches!(
+                    committee_error(&e),
+                    Some(Web3Error::RegisterNodeError(
+                        RegisterNodeError::AssertTxNotFound(_)
+                    ))
+                ) =>
+            {
+                println!("Assert txs are not ready yet: {:#}", e)
+            }
+            Err(e) => return Err(e),
+        }
+        if Instant::now() >= deadline {
+            anyhow::bail!(FcliError::Committee(format!(
+                "committee has not generated the assert txs of validator {} after {} seconds",
+                validator_key,
+                timeout.as_secs()
+            )));
+        }
+        tokio::time::sleep(poll_interval).await;
     }
 }
 
+fn stored_stake_txid(storage: &Storage, validator_key: &str) -> anyhow::Result<Option<Txid>> {
+    storage
+        .transactions_by_ext_info(validator_key)?
+        .into_iter()
+        .rev()
+        .find(|tx| tx.tx_type == TransactionType::StakeTx)
+        .map(|tx| Txid::from_str(&tx.tx_id).map_err(Into::into))
+        .transpose()
+}
+
+fn is_validator_key_registered(err: &anyhow::Error) -> bool {
+    matches!(
+        committee_error(err),
+        Some(Web3Error::RegisterNodeError(
+            RegisterNodeError::ValidatorKeyRegistered(_)
+        ))
+    )
+}
+
 #[cfg(test)]
 mod tests {
+    use bitcoin::Network;
+    use bitcoin_client::simulator::ChainSimulator;
+    use types::committee::CommitteeVersionId;
+
     use super::*;
+    use crate::subcommands::test_utils::{fund, mock_wallet, PRIVATE_KEY, VALIDATOR_KEY};
 
     #[tokio::test]
     async fn test_register() {
-        let create_tx = CreateTx {
-            txid: "d54d867a330aee1500d648792ad0aaee3d9019f806e6ab514e995472e2696e15".to_string(),
-            vout: 0,
-            private_key: "tprv8jzau9CfsdkXMzqWFWSgu7f4z1vRk53yiqYqByfoakSLNFQ4bBuTsrUDLXtKHTPZhp161h49vEJr2zwN92G7ZHLZMFvome2U8GcAqDzVRhW".to_string(),
-        };
-        let register = StartRegisterInfo {
-            validator_key: "fiammavaloper19fldhw0awjv2ag7dz0lr3d4qmnfkxz69vukt7x".to_string(),
-            create_tx,
-        };
-        let register = Register {
-            action: Action::Start(register),
-            network: Some("local".to_string()),
-        };
+        let (wallet, _handle) = mock_wallet().await;
+        let chain = ChainSimulator::new();
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let flow = RegistrationFlow::new(&wallet, &chain, params.clone());
+        let storage = Storage::open_in_memory().unwrap();
+
+        let key = Auxiliary::from_private_key(PRIVATE_KEY, Network::Regtest).unwrap();
+        let input = fund(&chain, &key, &params);
+        let multi_sig_script = flow.multi_sig_script(&key).await.unwrap();
+        let presign_txs = flow
+            .create_presign_transactions(&key, &input, &multi_sig_script)
+            .await
+            .unwrap();
+
+        let register_id =
+            start_register(&flow, &storage, "local", VALIDATOR_KEY, &key, &presign_txs)
+                .await
+                .unwrap();
+        assert_eq!(register_id, 1);
+
+        let record = storage
+            .registration("local", VALIDATOR_KEY)
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.register_id, register_id);
+        assert_eq!(record.public_key, key.pubkey);
+        assert!(!record.finished);
+        assert_eq!(
+            stored_stake_txid(&storage, VALIDATOR_KEY).unwrap(),
+            Some(presign_txs.stake_tx.compute_txid())
+        );
+        assert_eq!(
+            storage
+                .transactions_by_ext_info(VALIDATOR_KEY)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        // The committee rejects a second registration of the validator.
+        let err = start_register(&flow, &storage, "local", VALIDATOR_KEY, &key, &presign_txs)
+            .await
+            .unwrap_err();
+        assert!(is_validator_key_registered(&err));
+
+        // No circuit is registered with the mock committee, waiting for its assert txs times out.
+        let poll_interval = Duration::from_millis(10);
+        let err = wait_for_assert_txs(&flow, VALIDATOR_KEY, 1, poll_interval, Duration::ZERO)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<FcliError>(),
+            Some(FcliError::Committee(_))
+        ));
 
-        let _ = register.run().await;
+        // An unknown validator is not waited out.
+        let err = wait_for_assert_txs(
+            &flow,
+            "fiammavaloper1unknown",
+            1,
+            poll_interval,
+            Duration::from_secs(3600),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            committee_error(&err),
+            Some(Web3Error::RegisterNodeError(
+                RegisterNodeError::RegisterIDNotFound(_)
+            ))
+        ));
     }
 }
@@ -0,0 +1,194 @@
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use bitcoin::{consensus::encode, ScriptBuf, Transaction, XOnlyPublicKey};
+use storage::circuit::CircuitStore;
+use transactions::{
+    bitvm::{commits_to_leaves, connector_c},
+    graph::connector_txout,
+};
+use types::{
+    bitvm::{LeafScript, NUMS_INTERNAL_KEY},
+    circuit::CircuitInfo,
+    error::CircuitError,
+    file::{get_context_script_path, get_leaf_script_path, read_circuit_leaf_scripts},
+    params::ProtocolParams,
+    register::CircuitTx,
+};
+use verifier::validate_vk_bytes;
+
+/// Checks `assert_tx` of circuit `vk_hash` against the circuit store: the stored verify key
+/// must be valid and hash to `vk_hash`, and connector C must be the taproot output committing
+/// to the leaf scripts stored for the circuit. The leaf scripts are not regenerated from the
+/// verify key, the check only ties the committee's commitment to the local files.
+pub(crate) fn verify_assert_commitment(
+    data_dir: &Path,
+    params: &ProtocolParams,
+    vk_hash: &str,
+    validator_key: &str,
+    assert_tx: &Transaction,
+) -> anyhow::Result<()> {
+    let store = CircuitStore::open(data_dir)?;
+    let (vk, entry) = store.read_vk(vk_hash)?;
+    let circuit_type = entry.circuit_type;
+    if CircuitInfo::hash(&vk, circuit_type.clone()) != vk_hash {
+        anyhow::bail!(CircuitError::VerifyKeyMismatch(vk_hash.to_string()));
+    }
+    validate_vk_bytes(&vk, &circuit_type)?;
+
+    let leaves = read_circuit_leaf_scripts(data_dir, vk_hash, validator_key, circuit_type)?;
+    let internal_key = XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY)?;
+    if !commits_to_leaves(params, assert_tx, &leaves, internal_key)? {
+        anyhow::bail!(CircuitError::ScriptCommitmentMismatch(vk_hash.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Runs [`verify_assert_commitment`] on every assert tx the committee generated for the
+/// validator, except those whose connector C is locked to `multi_sig_script` and so commits
+/// to no leaf scripts. A circuit missing from the circuit store fails the check.
+pub(crate) fn verify_assert_txs(
+    data_dir: &Path,
+    params: &ProtocolParams,
+    multi_sig_script: &ScriptBuf,
+    validator_key: &str,
+    assert_txs: &[CircuitTx],
+) -> anyhow::Result<()> {
+    let store = CircuitStore::open(data_dir)?;
+    let multi_sig_connector = connector_txout(params, multi_sig_script);
+    for circuit_tx in assert_txs {
+        let assert_tx = encode::deserialize_hex::<Transaction>(&circuit_tx.tx_hex)?;
+        if connector_c(params, &assert_tx) == Some(&multi_sig_connector) {
+            continue;
+        }
+        if !has_local_scripts(data_dir, &store, &circuit_tx.vk_hash, validator_key)? {
+            anyhow::bail!(CircuitError::ScriptsNotStored(circuit_tx.vk_hash.clone()));
+        }
+        verify_assert_commitment(
+            data_dir,
+            params,
+            &circuit_tx.vk_hash,
+            validator_key,
+            &assert_tx,
+        )?;
+    }
+    Ok(())
+}
+
+/// Leaf scripts of the circuit store for the circuits of `assert_txs`, by verify key hash.
+/// Circuits whose verify key or leaf scripts are not in the store are left out.
+pub(crate) fn local_leaf_scripts(
+    data_dir: &Path,
+    validator_key: &str,
+    assert_txs: &[CircuitTx],
+) -> anyhow::Result<HashMap<String, Vec<LeafScript>>> {
+    let store = CircuitStore::open(data_dir)?;
+    let mut leaves = HashMap::new();
+    for circuit_tx in assert_txs {
+        let vk_hash = &circuit_tx.vk_hash;
+        if !has_local_scripts(data_dir, &store, vk_hash, validator_key)? {
+            continue;
+        }
+        let Some(entry) = store.entry(vk_hash)? else {
+            continue;
+        };
+        let circuit_leaves =
+            read_circuit_leaf_scripts(data_dir, vk_hash, validator_key, entry.circuit_type)?;
+        leaves.insert(vk_hash.clone(), circuit_leaves);
+    }
+    Ok(leaves)
+}
+
+/// Whether the circuit store holds the verify key and the leaf scripts of circuit `vk_hash`.
+fn has_local_scripts(
+    data_dir: &Path,
+    store: &CircuitStore,
+    vk_hash: &str,
+    validator_key: &str,
+) -> anyhow::Result<bool> {
+    let Some(entry) = store.entry(vk_hash)? else {
+        return Ok(false);
+    };
+    let circuit_type = entry.circuit_type.clone();
+    Ok(store.vk_path(&entry).exists()
+        && (get_leaf_script_path(data_dir, vk_hash, validator_key, circuit_type.clone()).exists()
+            || get_context_script_path(data_dir, vk_hash, circuit_type).exists()))
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{absolute, opcodes::OP_TRUE, script::Builder, transaction, Network, TxOut};
+    use transactions::bitvm::{leaf_script_pubkey, leaf_spend_info};
+    use types::{committee::CommitteeVersionId, transaction::TransactionType};
+
+    use super::*;
+
+    fn circuit_tx(params: &ProtocolParams, connector_c: TxOut) -> CircuitTx {
+        let mut output = vec![
+            TxOut {
+                value: params.stake_amount,
+                script_pubkey: ScriptBuf::new(),
+            };
+            params.connector_c_index as usize
+        ];
+        output.push(connector_c);
+        let assert_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![],
+            output,
+        };
+        CircuitTx::new(
+            "vk_hash",
+            TransactionType::AssertTx,
+            &encode::serialize_hex(&assert_tx),
+        )
+    }
+
+    #[test]
+    fn test_verify_assert_txs() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let multi_sig_script = Builder::new().push_opcode(OP_TRUE).into_script();
+
+        // Connector C locked to the multi sig script commits to no leaf scripts.
+        let assert_txs = [circuit_tx(
+            &params,
+            connector_txout(&params, &multi_sig_script),
+        )];
+        verify_assert_txs(
+            data_dir.path(),
+            &params,
+            &multi_sig_script,
+            "validator",
+            &assert_txs,
+        )
+        .unwrap();
+
+        // A leaf commitment needs the circuit in the store.
+        let leaves = vec![LeafScript {
+            script: Builder::new().push_opcode(OP_TRUE).into_script(),
+            witness: vec![],
+        }];
+        let internal_key = XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY).unwrap();
+        let assert_txs = [circuit_tx(
+            &params,
+            TxOut {
+                value: params.dust_amount,
+                script_pubkey: leaf_script_pubkey(&leaf_spend_info(&leaves, internal_key).unwrap()),
+            },
+        )];
+        let err = verify_assert_txs(
+            data_dir.path(),
+            &params,
+            &multi_sig_script,
+            "validator",
+            &assert_txs,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CircuitError>(),
+            Some(CircuitError::ScriptsNotStored(vk_hash)) if vk_hash == "vk_hash"
+        ));
+    }
+}
@@ -1,21 +1,12 @@
-use std::str::FromStr;
-
-use bitcoin::{
-    bip32::Xpriv, consensus::encode, secp256k1, Address, KnownHrp, OutPoint, PrivateKey, PublicKey,
-    ScriptBuf, Transaction, TxOut, Txid, XOnlyPublicKey,
-};
-use bitcoin_client::api_client::MempoolClient;
+use bitcoin::{consensus::encode, Transaction};
+use bitcoin_client::{api_client::MempoolClient, ChainSource};
 use clap::Parser;
-use transactions::{
-    assert::AssertTransaction, challenge::ChallengeTransaction, stake::StakeTransaction,
-    types::P2trUtxo,
-};
-use types::constants::{
-    ASSERT_FEE_AMOUNT, CONNECTOR_A_INDEX, CONNECTOR_B_INDEX, DUST_AMOUNT, STAKE_AMOUNT,
-    STAKE_FEE_AMOUNT, STAKE_VALUE_INDEX,
-};
-use wallet::{provider::ProviderParams, signer::Signer, Wallet};
-use web3_decl::jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use sdk::{Auxiliary, FundingInput, PresignedTxs, RegistrationFlow};
+use types::{error::FcliError, params::ProtocolParams};
+use wallet::provider::ProviderParams;
+use web3_decl::jsonrpsee::http_client::HttpClient;
+
+use super::params::{check_protocol_params, committee_version, connect, Settings};
 
 #[derive(Debug, Parser, Clone)]
 #[clap(
@@ -86,266 +77,168 @@ pub struct Register {
     create_tx: CreateTx,
 }
 
-pub struct Auxiliary {
-    pub private_key: PrivateKey,
-    pub pubkey: String,
-    pub internal_x_only_pubkey: String,
-    pub script_pk: ScriptBuf,
+impl CreateTx {
+    pub fn key(&self, network: bitcoin::Network) -> anyhow::Result<Auxiliary> {
+        Auxiliary::from_private_key(&self.private_key, network)
+    }
+
+    pub fn funding_input(&self) -> anyhow::Result<FundingInput> {
+        FundingInput::new(&self.txid, self.vout)
+    }
+
+    /// Stake tx and the assert and challenge txs spending it, funded by the input.
+    async fn presign_txs<C: ChainSource>(
+        &self,
+        flow: &RegistrationFlow<'_, HttpClient, C>,
+    ) -> anyhow::Result<PresignedTxs> {
+        let key = self.key(flow.params().network)?;
+        let multi_sig_script = flow.multi_sig_script(&key).await?;
+        flow.create_presign_transactions(&key, &self.funding_input()?, &multi_sig_script)
+            .await
+    }
+
+    async fn stake_tx<C: ChainSource>(
+        &self,
+        flow: &RegistrationFlow<'_, HttpClient, C>,
+    ) -> anyhow::Result<Transaction> {
+        let key = self.key(flow.params().network)?;
+        let multi_sig_script = flow.multi_sig_script(&key).await?;
+        flow.create_stake_tx(&key, &self.funding_input()?, &multi_sig_script)
+            .await
+    }
 }
 
 impl Tx {
-    pub async fn run(self) -> anyhow::Result<()> {
+    pub async fn run(self, settings: &Settings) -> anyhow::Result<()> {
         let ctx = if let Some(network) = self.network {
             match network.as_str() {
                 "local" => ProviderParams::local(),
                 "dev" => ProviderParams::dev(),
                 "testnet" => ProviderParams::testnet(),
                 _ => {
-                    anyhow::bail!("invalid network name")
+                    anyhow::bail!(FcliError::InvalidArgument(format!(
+                        "invalid network name {network}"
+                    )))
                 }
             }
         } else {
             ProviderParams::local()
         };
 
-        let client = HttpClientBuilder::default()
-            .build(ctx.http_endpoint.as_str())
-            .unwrap();
-        let signer = Signer::new();
-        let wallet = Wallet::new(client, signer);
+        let wallet = connect(&ctx, &settings.client_config)?;
+        let params = ProtocolParams::new(ctx.network, committee_version(&wallet).await)?;
+
+        let chain = MempoolClient::new(ctx.network)?;
+        let flow = RegistrationFlow::new(&wallet, &chain, params);
 
         match self.action {
             Action::GetStakeAddress(args) => {
-                let script = wallet.get_multi_sig_script(&args.public_key).await.unwrap();
+                let script = flow.multi_sig_script_of(&args.public_key).await?;
                 println!("Multi Sig Script:\n{script}");
             }
             Action::CreateStakeTx(args) => {
-                let aux = Self::parse_private_key(&args.private_key, &ctx);
-                let stake_tx = Self::create_stake_tx(&args, &ctx, &wallet, &aux).await;
-                let stake_tx_hex = encode::serialize_hex(&stake_tx.0);
+                check_protocol_params(&wallet, flow.params(), None).await;
+                let stake_tx = args.stake_tx(&flow).await?;
+                let stake_tx_hex = encode::serialize_hex(&stake_tx);
                 println!("Stake tx:\n{:?}", stake_tx_hex);
             }
             Action::CreateAssertTx(args) => {
-                let aux = Self::parse_private_key(&args.private_key, &ctx);
-                let (_, assert_tx, _) =
-                    Self::create_presign_transactions(&args, &ctx, &wallet, &aux).await;
-                let assert_tx_hex = encode::serialize_hex(&assert_tx);
+                check_protocol_params(&wallet, flow.params(), None).await;
+                let presign_txs = args.presign_txs(&flow).await?;
+                let assert_tx_hex = encode::serialize_hex(&presign_txs.assert_tx);
                 println!("Assert tx:\n{:?}", assert_tx_hex);
             }
         }
         Ok(())
     }
-
-    pub fn parse_private_key(private_key: &str, ctx: &ProviderParams) -> Auxiliary {
-        let secp = secp256k1::Secp256k1::new();
-        let private_key = if let Ok(pk) = PrivateKey::from_wif(private_key) {
-            pk
-        } else if let Ok(pk) = Xpriv::from_str(private_key) {
-            pk.to_priv()
-        } else {
-            panic!("Invalid private key")
-        };
-        // let sk = Xpriv::from_str(private_key).expect("Invalid private key");
-        let pubkey = PublicKey::from_private_key(&secp, &private_key).to_string();
-        // let internal_key = Xpub::from_priv(&secp, &sk);
-        let secret_key = private_key.inner;
-        let keypair = secp256k1::Keypair::from_secret_key(&secp, &secret_key);
-        let (internal_key, _parity) = XOnlyPublicKey::from_keypair(&keypair);
-        let address = Address::p2tr(&secp, internal_key, None, KnownHrp::from(ctx.network));
-        let internal_x_only_pubkey = internal_key.to_string();
-        let script_pk = address.script_pubkey();
-        Auxiliary {
-            private_key,
-            pubkey,
-            internal_x_only_pubkey,
-            script_pk,
-        }
-    }
-
-    async fn create_stake_tx(
-        args: &CreateTx,
-        ctx: &ProviderParams,
-        wallet: &Wallet<HttpClient>,
-        aux: &Auxiliary,
-    ) -> (bitcoin::Transaction, ScriptBuf, ScriptBuf) {
-        let Auxiliary {
-            private_key,
-            pubkey,
-            internal_x_only_pubkey,
-            script_pk,
-        } = aux;
-        let txid = Txid::from_str(&args.txid).expect("Invalid txid");
-        let vout = args.vout;
-
-        let bitcoin_rpc_client = MempoolClient::new(ctx.network);
-        let pre_tx = bitcoin_rpc_client
-            .get_tx(&txid.to_string())
-            .expect("tx_id is not valid");
-        let utxo = pre_tx.tx_out(vout as usize).expect("Invalid vout");
-
-        // generate stake tx
-        let multi_sig_script = wallet
-            .get_multi_sig_script(pubkey)
-            .await
-            .expect("Failed to get multi_sig script");
-        let multi_sig_addr = Address::p2wsh(&multi_sig_script, KnownHrp::from(ctx.network));
-        let script_pubkey = multi_sig_addr.script_pubkey();
-
-        let input_utxo = P2trUtxo {
-            txid: &txid.to_string(),
-            vout,
-            script_pubkey: &utxo.script_pubkey.to_hex_string(),
-            pubkey: &internal_x_only_pubkey.to_string(),
-            amount_in_sats: utxo.value,
-        };
-
-        let spent = TxOut {
-            value: STAKE_AMOUNT.unchecked_add(ASSERT_FEE_AMOUNT),
-            script_pubkey: script_pubkey.clone(),
-        };
-
-        // TODO: add timelock
-        let unstake_timelock = TxOut {
-            value: DUST_AMOUNT,
-            script_pubkey: script_pubkey.clone(),
-        };
-
-        let connector_a = TxOut {
-            value: DUST_AMOUNT,
-            script_pubkey: script_pubkey.clone(),
-        };
-
-        let connector_b = TxOut {
-            value: DUST_AMOUNT,
-            script_pubkey: script_pubkey.clone(),
-        };
-
-        let change = TxOut {
-            value: input_utxo
-                .amount_in_sats
-                .checked_sub(
-                    STAKE_FEE_AMOUNT
-                        .unchecked_add(STAKE_AMOUNT)
-                        .unchecked_add(ASSERT_FEE_AMOUNT)
-                        .unchecked_add(DUST_AMOUNT * 3),
-                )
-                .expect("Not enough btc"),
-            script_pubkey: script_pk.clone(),
-        };
-
-        let stake_tx = StakeTransaction::new(
-            *private_key,
-            input_utxo,
-            vec![spent, unstake_timelock, connector_a, connector_b, change],
-        );
-        let stake_tx = stake_tx.extract_tx();
-        (stake_tx, script_pubkey, multi_sig_script)
-    }
-
-    pub async fn create_presign_transactions(
-        args: &CreateTx,
-        ctx: &ProviderParams,
-        wallet: &Wallet<HttpClient>,
-        aux: &Auxiliary,
-    ) -> (Transaction, Transaction, Transaction) {
-        let (stake_tx, script_pubkey, multi_sig_script) =
-            Self::create_stake_tx(args, ctx, wallet, aux).await;
-
-        let stake_txid = stake_tx.compute_txid();
-
-        let out_point = vec![
-            OutPoint {
-                txid: stake_txid,
-                vout: STAKE_VALUE_INDEX,
-            },
-            OutPoint {
-                txid: stake_txid,
-                vout: CONNECTOR_B_INDEX,
-            },
-        ];
-        let tx_out = vec![
-            TxOut {
-                value: STAKE_AMOUNT.unchecked_add(ASSERT_FEE_AMOUNT),
-                script_pubkey: script_pubkey.clone(),
-            },
-            TxOut {
-                value: DUST_AMOUNT,
-                script_pubkey: script_pubkey.clone(),
-            },
-        ];
-        let input_utxos: Vec<(OutPoint, TxOut)> =
-            out_point.into_iter().zip(tx_out.into_iter()).collect();
-        let assert_tx =
-            AssertTransaction::new(aux.private_key, input_utxos, multi_sig_script.clone());
-
-        let out_point = vec![OutPoint {
-            txid: stake_txid,
-            vout: CONNECTOR_A_INDEX,
-        }];
-
-        let tx_out = vec![TxOut {
-            value: DUST_AMOUNT,
-            script_pubkey: script_pubkey.clone(),
-        }];
-
-        let input_utxos: Vec<(OutPoint, TxOut)> =
-            out_point.into_iter().zip(tx_out.into_iter()).collect();
-
-        let challenge_tx =
-            ChallengeTransaction::new(aux.private_key, input_utxos, multi_sig_script.clone());
-
-        (stake_tx, assert_tx.tx, challenge_tx.tx)
-    }
 }
 
 #[cfg(test)]
 mod tests {
+    use bitcoin::{opcodes::all::OP_CHECKMULTISIG, Network, ScriptBuf};
+    use bitcoin_client::simulator::ChainSimulator;
+    use types::committee::CommitteeVersionId;
+
     use super::*;
+    use crate::subcommands::test_utils::{fund, mock_wallet, PRIVATE_KEY};
 
     #[tokio::test]
     async fn test_create_stake_tx() {
-        let create_stake_tx = CreateTx {
-            txid: "d54d867a330aee1500d648792ad0aaee3d9019f806e6ab514e995472e2696e15".to_string(),
-            vout: 0,
-            private_key: "tprv8jzau9CfsdkXMzqWFWSgu7f4z1vRk53yiqYqByfoakSLNFQ4bBuTsrUDLXtKHTPZhp161h49vEJr2zwN92G7ZHLZMFvome2U8GcAqDzVRhW".to_string(),
-        };
-        let tx = Tx {
-            action: Action::CreateStakeTx(create_stake_tx),
-            network: Some("local".to_string()),
+        let (wallet, _handle) = mock_wallet().await;
+        let chain = ChainSimulator::new();
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let flow = RegistrationFlow::new(&wallet, &chain, params.clone());
+        let key = Auxiliary::from_private_key(PRIVATE_KEY, Network::Regtest).unwrap();
+        let input = fund(&chain, &key, &params);
+        let create_tx = CreateTx {
+            txid: input.txid.to_string(),
+            vout: input.vout,
+            private_key: PRIVATE_KEY.to_string(),
         };
 
-        let _ = tx.run().await;
+        let stake_tx = create_tx.stake_tx(&flow).await.unwrap();
+        assert_eq!(stake_tx.input[0].previous_output.txid, input.txid);
+        let multi_sig_script = wallet.get_multi_sig_script(&key.pubkey).await.unwrap();
+        assert_eq!(
+            stake_tx.output[params.stake_value_index as usize],
+            bitcoin::TxOut {
+                value: params.stake_output_amount(),
+                script_pubkey: ScriptBuf::new_p2wsh(&multi_sig_script.wscript_hash()),
+            }
+        );
+        // The stake tx is standard and fully signed, the simulated chain accepts it.
+        assert_eq!(
+            chain.broadcast(&stake_tx).await.unwrap(),
+            stake_tx.compute_txid()
+        );
     }
 
     #[tokio::test]
     async fn test_create_assert_tx() {
-        let create_assert_tx = CreateTx {
-            txid: "d54d867a330aee1500d648792ad0aaee3d9019f806e6ab514e995472e2696e15".to_string(),
-            vout: 0,
-            private_key: "tprv8jzau9CfsdkXMzqWFWSgu7f4z1vRk53yiqYqByfoakSLNFQ4bBuTsrUDLXtKHTPZhp161h49vEJr2zwN92G7ZHLZMFvome2U8GcAqDzVRhW".to_string(),
-        };
-        let tx = Tx {
-            action: Action::CreateAssertTx(create_assert_tx),
-            network: Some("local".to_string()),
+        let (wallet, _handle) = mock_wallet().await;
+        let chain = ChainSimulator::new();
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let flow = RegistrationFlow::new(&wallet, &chain, params.clone());
+        let key = Auxiliary::from_private_key(PRIVATE_KEY, Network::Regtest).unwrap();
+        let input = fund(&chain, &key, &params);
+        let create_tx = CreateTx {
+            txid: input.txid.to_string(),
+            vout: input.vout,
+            private_key: PRIVATE_KEY.to_string(),
         };
 
-        let _ = tx.run().await;
+        let txs = create_tx.presign_txs(&flow).await.unwrap();
+        let stake_txid = txs.stake_tx.compute_txid();
+        assert_eq!(
+            txs.assert_tx.input[0].previous_output,
+            bitcoin::OutPoint {
+                txid: stake_txid,
+                vout: params.stake_value_index,
+            }
+        );
+        assert_eq!(
+            txs.challenge_tx.input[0].previous_output,
+            bitcoin::OutPoint {
+                txid: stake_txid,
+                vout: params.connector_a_index,
+            }
+        );
     }
 
     #[tokio::test]
     async fn test_get_stake_address() {
-        let get_stake_address = GetStakeAddress {
-            public_key: "02ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f8"
-                .to_string(),
-        };
-
-        let tx = Tx {
-            action: Action::GetStakeAddress(get_stake_address),
-            network: None,
-        };
-
-        let _ = tx.run().await;
+        let (wallet, _handle) = mock_wallet().await;
+        let chain = ChainSimulator::new();
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let flow = RegistrationFlow::new(&wallet, &chain, params);
+        let public_key = "02ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f8";
+
+        let script = flow.multi_sig_script_of(public_key).await.unwrap();
+        let public_key = hex::decode(public_key).unwrap();
+        assert_eq!(script.as_bytes().last(), Some(&OP_CHECKMULTISIG.to_u8()));
+        assert!(script
+            .as_bytes()
+            .windows(public_key.len())
+            .any(|bytes| bytes == public_key));
     }
-
-    // cargo run --bin fcli tx register -t d54d867a330aee1500d648792ad0aaee3d9019f806e6ab514e995472e2696e15 -o 0 -s tprv8jzau9CfsdkXMzqWFWSgu7f4z1vRk53yiqYqByfoakSLNFQ4bBuTsrUDLXtKHTPZhp161h49vEJr2zwN92G7ZHLZMFvome2U8GcAqDzVRhW -u test -p 1234 -v fiammavaloper19fldhw0awjv2ag7dz0lr3d4qmnfkxz69vukt7x
 }
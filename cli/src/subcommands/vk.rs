@@ -0,0 +1,147 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use clap::Parser;
+use types::{circuit::CircuitType, file::read_vk_from_path};
+use verifier::{
+    fflonk::{self, FflonkVerifyingKey},
+    groth16::{self, Groth16VerifyingKey},
+    VkFormat,
+};
+
+#[derive(Debug, Parser, Clone)]
+#[clap(name = "Vk", about = "Inspect and convert verifier keys")]
+pub struct Vk {
+    #[clap(subcommand)]
+    action: Action,
+}
+
+#[derive(Debug, Parser, Clone)]
+enum Action {
+    #[clap(
+        name = "inspect",
+        about = "Validate a verifier key and print its points",
+        long_about = "Validate a verifier key and print its points.\n\nExample:\n  fcli vk inspect -p vk.bitvm"
+    )]
+    Inspect(InspectParams),
+    #[clap(
+        name = "convert",
+        about = "Convert a verifier key into the vk.bitvm encoding",
+        long_about = "Convert a snarkjs, gnark or arkworks verifier key into the vk.bitvm encoding.\n\nExample:\n  fcli vk convert -i verification_key.json -f snarkjs -o vk.bitvm"
+    )]
+    Convert(ConvertParams),
+}
+
+#[derive(Debug, Parser, Clone)]
+struct InspectParams {
+    #[clap(short, long, help = "Verifier key path")]
+    path: String,
+
+    #[clap(
+        short,
+        long,
+        help = "Verifier key format, can be bitvm, snarkjs, gnark or arkworks",
+        default_value = "bitvm"
+    )]
+    format: String,
+
+    #[clap(
+        short,
+        long,
+        help = "Circuit type, can be groth16 or fflonk",
+        default_value = "groth16"
+    )]
+    circuit_type: String,
+}
+
+#[derive(Debug, Parser, Clone)]
+struct ConvertParams {
+    #[clap(short, long, help = "Input verifier key path")]
+    input: String,
+
+    #[clap(
+        short,
+        long,
+        help = "Input verifier key format, can be snarkjs, gnark or arkworks, fflonk keys are only exported by snarkjs"
+    )]
+    format: String,
+
+    #[clap(
+        short,
+        long,
+        help = "Circuit type, can be groth16 or fflonk",
+        default_value = "groth16"
+    )]
+    circuit_type: String,
+
+    #[clap(short, long, help = "Output path of the vk.bitvm verifier key")]
+    output: String,
+}
+
+/// A parsed verifier key of any supported circuit type.
+enum VerifyingKey {
+    Groth16(Groth16VerifyingKey),
+    Fflonk(FflonkVerifyingKey),
+}
+
+impl Vk {
+    pub async fn run(self) -> anyhow::Result<()> {
+        match self.action {
+            Action::Inspect(args) => {
+                let vk = load_vk(&args.path, &args.format, &args.circuit_type)?;
+                print_vk(&vk);
+            }
+            Action::Convert(args) => {
+                let vk = load_vk(&args.input, &args.format, &args.circuit_type)?;
+                let bytes = match &vk {
+                    VerifyingKey::Groth16(vk) => groth16::encode_vk(vk),
+                    VerifyingKey::Fflonk(vk) => fflonk::encode_vk(vk),
+                };
+                std::fs::write(&args.output, bytes)?;
+                println!(
+                    "Converted {} {} verifier key {} into {}",
+                    args.format, args.circuit_type, args.input, args.output
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn load_vk(path: &str, format: &str, circuit_type: &str) -> anyhow::Result<VerifyingKey> {
+    let format = VkFormat::from_str(format)
+        .map_err(|_| anyhow::anyhow!("invalid verifier key format {}", format))?;
+    let circuit_type = CircuitType::from_str(circuit_type)?;
+    let bytes = read_vk_from_path(path)?;
+
+    let vk = match circuit_type {
+        CircuitType::Groth16 => groth16::convert_vk(&bytes, format).map(VerifyingKey::Groth16),
+        CircuitType::Fflonk => fflonk::convert_vk(&bytes, format).map(VerifyingKey::Fflonk),
+    };
+    vk.with_context(|| format!("invalid verifier key {}", path))
+}
+
+fn print_vk(vk: &VerifyingKey) {
+    match vk {
+        VerifyingKey::Groth16(vk) => {
+            println!("alpha_g1: {}", vk.alpha_g1);
+            println!("beta_g2: {}", vk.beta_g2);
+            println!("gamma_g2: {}", vk.gamma_g2);
+            println!("delta_g2: {}", vk.delta_g2);
+            println!(
+                "IC length: {} ({} public inputs)",
+                vk.gamma_abc_g1.len(),
+                vk.gamma_abc_g1.len() - 1
+            );
+        }
+        VerifyingKey::Fflonk(vk) => {
+            println!("public inputs: {}", vk.n_public);
+            println!("domain size: 2^{}", vk.power);
+            println!("k1: {}", vk.k1);
+            println!("k2: {}", vk.k2);
+            println!("w: {}", vk.w);
+            println!("x_2: {}", vk.x_2);
+            println!("c0: {}", vk.c0);
+        }
+    }
+}
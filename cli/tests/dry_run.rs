@@ -0,0 +1,72 @@
+//! Runs dry runs through the fcli binary to check that stdout holds nothing but the JSON.
+
+use bitcoin::Network;
+use mock_committee::MockCommittee;
+use serde_json::json;
+use tokio::process::Command;
+use verifier::{groth16, VkFormat};
+
+/// Groth16 verify key whose points are all the generators, in the `vk.bitvm` encoding.
+fn generator_vk() -> Vec<u8> {
+    let g1 = json!(["1", "2", "1"]);
+    let g2 = json!([
+        [
+            "10857046999023057135944570762232829481370756359578518086990519993285655852781",
+            "11559732032986387107991004021392285783925812861821192530917403151452391805634"
+        ],
+        [
+            "8495653923123431417604973247489272438418190587263600148770280649306958101930",
+            "4082367875863433681332203403145435568316851327593401208105741076214120093531"
+        ],
+        ["1", "0"]
+    ]);
+    let snarkjs = json!({
+        "protocol": "groth16",
+        "curve": "bn128",
+        "nPublic": 1,
+        "vk_alpha_1": g1,
+        "vk_beta_2": g2,
+        "vk_gamma_2": g2,
+        "vk_delta_2": g2,
+        "IC": [g1, g1],
+    });
+    let vk = groth16::convert_vk(snarkjs.to_string().as_bytes(), VkFormat::Snarkjs).unwrap();
+    groth16::encode_vk(&vk)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dry_run_stdout_is_json() {
+    // The `local` network talks to the committee on its fixed endpoint.
+    let (_, _handle) = MockCommittee::new(Network::Regtest)
+        .unwrap()
+        .serve("127.0.0.1:33000".parse().unwrap())
+        .await
+        .expect("the local committee port 33000 is free");
+
+    let data_dir = tempfile::tempdir().unwrap();
+    let vk_path = data_dir.path().join("vk.bitvm");
+    std::fs::write(&vk_path, generator_vk()).unwrap();
+
+    // The committee has no such proof, so the local proof check warns before the dry run.
+    let output = Command::new(env!("CARGO_BIN_EXE_fcli"))
+        .arg("--data-dir")
+        .arg(data_dir.path())
+        .args(["challenge", "start", "--proof-id", "proof", "--dry-run"])
+        .arg("--vk-path")
+        .arg(&vk_path)
+        .output()
+        .await
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("Warning: failed to verify proof proof locally"));
+
+    let dry_run: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap_or_else(|e| {
+        panic!(
+            "stdout is not JSON ({e}): {}",
+            String::from_utf8_lossy(&output.stdout)
+        )
+    });
+    assert_eq!(dry_run["method"], "startChallenge");
+    assert_eq!(dry_run["request"]["proof_id"], "proof");
+}
@@ -0,0 +1,426 @@
+//! In-memory stand-in of the Fiamma committee, serving the `fc` JSON-RPC namespace so fcli
+//! and the sdk flows can run without a live committee.
+//!
+//! The mock checks the stake tx of a registration like the committee does, derives the
+//! multi sig script from fixed committee keys, completes the presigned assert and challenge
+//! txs with the committee's outputs and walks a challenge through its statuses, one status
+//! per status or info query. Proofs cannot be submitted over RPC, seed them with
+//! [`MockCommittee::add_proof`] or a [`Fixtures`] file.
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use bitcoin::{Network, ScriptBuf, Txid};
+use serde::Deserialize;
+use types::{
+    bitvm::LeafScript,
+    challenge::{ChallengeInfoRes, ChallengeRequest, FinishChallengeRequest, ProofRes},
+    circuit::RegisterCircuitRequest,
+    committee::{CommitteeVersionId, CommitteeVersionRes},
+    disprove::DisproveRequest,
+    error::ParamsError,
+    params::{ProtocolParams, ProtocolParamsRes},
+    register::{CircuitTx, QueryAssertTxReq},
+    FinishRegisterRequest, RegisterRequest,
+};
+use web3_decl::{
+    jsonrpsee::{
+        core::{async_trait, RpcResult},
+        server::{Server, ServerHandle},
+    },
+    namespaces::committee::CommitteeNamespaceServer,
+};
+
+mod state;
+pub use state::next_status;
+use state::State;
+
+/// Circuits, leaf scripts and proofs a mock committee starts with.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Fixtures {
+    #[serde(default)]
+    pub circuits: Vec<RegisterCircuitRequest>,
+    #[serde(default)]
+    pub leaf_scripts: Vec<LeafScriptsFixture>,
+    #[serde(default)]
+    pub proofs: Vec<ProofFixture>,
+}
+
+/// Leaf scripts connector C of a circuit's assert txs commits to, in the format of the leaf
+/// script files. Without a validator key they apply to every validator.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LeafScriptsFixture {
+    pub vk_hash: String,
+    #[serde(default)]
+    pub validator_key: Option<String>,
+    pub leaf_scripts: Vec<LeafScript>,
+}
+
+/// A proof and the validator whose registration the proof is challenged against.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProofFixture {
+    pub validator_key: String,
+    pub proof: ProofRes,
+}
+
+#[derive(Clone)]
+pub struct MockCommittee {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockCommittee {
+    /// Committee on `network`, which needs protocol params, i.e. regtest or signet.
+    pub fn new(network: Network) -> Result<Self, ParamsError> {
+        ProtocolParams::new(network, CommitteeVersionId::latest())?;
+        Ok(Self {
+            state: Arc::new(Mutex::new(State::new(network))),
+        })
+    }
+
+    /// Opens a rotation window to the next committee version, validators can then presign
+    /// their txs for both versions.
+    pub fn start_rotation(&self) {
+        self.state().start_rotation();
+    }
+
+    /// Makes `proof` challengeable, against the registration of `validator_key`.
+    pub fn add_proof(&self, validator_key: &str, proof: ProofRes) {
+        self.state().add_proof(validator_key, proof);
+    }
+
+    /// Makes connector C of the assert txs of circuit `vk_hash` commit to `leaf_scripts`, for
+    /// the validator or, without a validator key, for every validator.
+    pub fn add_leaf_scripts(
+        &self,
+        vk_hash: &str,
+        validator_key: Option<&str>,
+        leaf_scripts: Vec<LeafScript>,
+    ) {
+        self.state()
+            .add_leaf_scripts(vk_hash, validator_key, leaf_scripts);
+    }
+
+    pub fn load_fixtures(&self, fixtures: Fixtures) -> anyhow::Result<()> {
+        let mut state = self.state();
+        for circuit in fixtures.circuits {
+            state.register_circuit(circuit)?;
+        }
+        for fixture in fixtures.leaf_scripts {
+            state.add_leaf_scripts(
+                &fixture.vk_hash,
+                fixture.validator_key.as_deref(),
+                fixture.leaf_scripts,
+            );
+        }
+        for fixture in fixtures.proofs {
+            state.add_proof(&fixture.validator_key, fixture.proof);
+        }
+        Ok(())
+    }
+
+    /// Serves the committee namespace on `addr` until the returned handle is stopped or
+    /// dropped, returns the bound address, which differs from `addr` for port 0.
+    pub async fn serve(self, addr: SocketAddr) -> anyhow::Result<(SocketAddr, ServerHandle)> {
+        let server = Server::builder().build(addr).await?;
+        let addr = server.local_addr()?;
+        Ok((addr, server.start(self.into_rpc())))
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // The state stays consistent when a request panics, every update is a single insert.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl CommitteeNamespaceServer for MockCommittee {
+    async fn get_multi_sig_address(&self, register_pk: &str) -> RpcResult<ScriptBuf> {
+        Ok(self.state().get_multi_sig_address(register_pk)?)
+    }
+
+    async fn get_multi_sig_address_of_proof(&self, proof_id: &str) -> RpcResult<ScriptBuf> {
+        Ok(self.state().get_multi_sig_address_of_proof(proof_id)?)
+    }
+
+    async fn get_multi_sig_address_of_version(
+        &self,
+        register_pk: &str,
+        version: CommitteeVersionId,
+    ) -> RpcResult<ScriptBuf> {
+        Ok(self
+            .state()
+            .get_multi_sig_address_of_version(register_pk, version)?)
+    }
+
+    async fn get_committee_version(&self) -> RpcResult<CommitteeVersionRes> {
+        Ok(self.state().get_committee_version())
+    }
+
+    async fn start_register(&self, request: RegisterRequest) -> RpcResult<u32> {
+        Ok(self.state().start_register(request)?)
+    }
+
+    async fn finish_register(&self, request: FinishRegisterRequest) -> RpcResult<u32> {
+        Ok(self.state().finish_register(request)?)
+    }
+
+    async fn start_challenge(&self, request: ChallengeRequest) -> RpcResult<u32> {
+        Ok(self.state().start_challenge(request)?)
+    }
+
+    async fn challenge_status(&self, request: ChallengeRequest) -> RpcResult<String> {
+        Ok(self.state().challenge_status(request))
+    }
+
+    async fn challenge_info(&self, request: ChallengeRequest) -> RpcResult<ChallengeInfoRes> {
+        Ok(self.state().challenge_info(request))
+    }
+
+    async fn get_committee_challenge_tx(&self, request: ChallengeRequest) -> RpcResult<String> {
+        Ok(self.state().get_committee_challenge_tx(request)?)
+    }
+
+    async fn get_committee_assert_txs(
+        &self,
+        request: QueryAssertTxReq,
+    ) -> RpcResult<Vec<CircuitTx>> {
+        Ok(self
+            .state()
+            .get_committee_assert_txs(&request.validator_key)?)
+    }
+
+    async fn get_protocol_params(&self) -> RpcResult<ProtocolParamsRes> {
+        Ok(self.state().get_protocol_params())
+    }
+
+    async fn get_proof(&self, proof_id: &str) -> RpcResult<ProofRes> {
+        Ok(self.state().get_proof(proof_id)?)
+    }
+
+    async fn finish_challenge(&self, request: FinishChallengeRequest) -> RpcResult<Txid> {
+        Ok(self.state().finish_challenge(request)?)
+    }
+
+    async fn disprove(&self, request: DisproveRequest) -> RpcResult<()> {
+        Ok(self.state().disprove(request)?)
+    }
+
+    async fn register_circuit(&self, request: RegisterCircuitRequest) -> RpcResult<u32> {
+        Ok(self.state().register_circuit(request)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin::{
+        absolute::LockTime,
+        consensus::encode,
+        secp256k1::{Secp256k1, SecretKey},
+        transaction::Version,
+        Amount, OutPoint, PublicKey, Transaction, TxIn, TxOut, XOnlyPublicKey,
+    };
+    use transactions::bitvm::{connector_c, leaf_script_pubkey, leaf_spend_info};
+    use types::{
+        bitvm::NUMS_INTERNAL_KEY,
+        challenge::ChallengeStatus,
+        circuit::{CircuitInfo, CircuitType},
+        error::{ChallengeError, RegisterNodeError},
+        params::ProtocolParams,
+        transaction::TransactionType,
+    };
+    use wallet::{config::ClientConfig, signer::Signer, Wallet};
+    use web3_decl::{
+        error::Web3Error,
+        jsonrpsee::{core::ClientError, http_client::HttpClient},
+    };
+
+    use super::*;
+
+    const VALIDATOR_KEY: &str = "fiammavaloper19fldhw0awjv2ag7dz0lr3d4qmnfkxz69vukt7x";
+
+    fn tx(output: Vec<TxOut>) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                ..Default::default()
+            }],
+            output,
+        }
+    }
+
+    fn committee_error(err: &anyhow::Error) -> Option<i32> {
+        err.chain()
+            .find_map(|e| e.downcast_ref::<ClientError>())
+            .and_then(Web3Error::from_client_error)
+            .map(|err| err.code())
+    }
+
+    async fn poll_until(
+        wallet: &Wallet<HttpClient>,
+        request: &ChallengeRequest,
+        status: ChallengeStatus,
+    ) -> ChallengeInfoRes {
+        for _ in 0..16 {
+            let info = wallet.challenge_info(request.clone()).await.unwrap();
+            if info.status == status {
+                return info;
+            }
+        }
+        panic!("challenge never reached {status}");
+    }
+
+    #[tokio::test]
+    async fn test_register_and_challenge() {
+        let committee = MockCommittee::new(Network::Regtest).unwrap();
+        let (addr, _handle) = committee
+            .clone()
+            .serve("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let wallet = Wallet::with_http_client(
+            &format!("http://{addr}"),
+            Signer::new(),
+            ClientConfig::default(),
+        )
+        .unwrap();
+
+        let vk = vec![1; 32];
+        let vk_hash = CircuitInfo::hash(&vk, CircuitType::Groth16);
+        wallet
+            .register_circuit(RegisterCircuitRequest {
+                vk,
+                circuit_type: CircuitType::Groth16,
+            })
+            .await
+            .unwrap();
+        let fixtures = format!(
+            r#"{{"leaf_scripts":[{{"vk_hash":"{vk_hash}","leaf_scripts":[{{"script":"5151","witness":[]}}]}}]}}"#
+        );
+        committee
+            .load_fixtures(serde_json::from_str(&fixtures).unwrap())
+            .unwrap();
+
+        let secp = Secp256k1::new();
+        let public_key =
+            PublicKey::new(SecretKey::from_slice(&[7; 32]).unwrap().public_key(&secp)).to_string();
+        let multi_sig_script = wallet.get_multi_sig_script(&public_key).await.unwrap();
+        let script_pubkey = ScriptBuf::new_p2wsh(&multi_sig_script.wscript_hash());
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let output = |value| TxOut {
+            value,
+            script_pubkey: script_pubkey.clone(),
+        };
+        let register = |stake_tx: &Transaction| {
+            RegisterRequest::new(
+                VALIDATOR_KEY,
+                &public_key,
+                &encode::serialize_hex(stake_tx),
+                &encode::serialize_hex(&tx(vec![])),
+                &encode::serialize_hex(&tx(vec![])),
+            )
+        };
+
+        let mut stake_tx = tx(vec![
+            output(params.stake_output_amount()),
+            output(params.dust_amount),
+            output(params.dust_amount),
+            output(Amount::from_sat(500)),
+        ]);
+        let err = wallet
+            .start_register(register(&stake_tx))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            committee_error(&err),
+            Some(RegisterNodeError::StakeTxInvalidConnectorValue(500).code())
+        );
+        stake_tx.output[3] = output(params.dust_amount);
+        let register_id = wallet.start_register(register(&stake_tx)).await.unwrap();
+
+        let assert_txs = wallet
+            .get_committee_assert_txs(QueryAssertTxReq::new(VALIDATOR_KEY))
+            .await
+            .unwrap();
+        assert_eq!(assert_txs.len(), 1);
+        let assert_tx = encode::deserialize_hex::<Transaction>(&assert_txs[0].tx_hex).unwrap();
+        let leaves = [LeafScript {
+            script: ScriptBuf::from_hex("5151").unwrap(),
+            witness: vec![],
+        }];
+        let internal_key = XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY).unwrap();
+        assert_eq!(
+            connector_c(&params, &assert_tx).unwrap(),
+            &TxOut {
+                value: params.dust_amount,
+                script_pubkey: leaf_script_pubkey(&leaf_spend_info(&leaves, internal_key).unwrap()),
+            }
+        );
+        let disprove_tx = CircuitTx::new(
+            &vk_hash,
+            TransactionType::DisproveTx,
+            &encode::serialize_hex(&tx(vec![])),
+        );
+        let finished = wallet
+            .finish_register(FinishRegisterRequest::new(VALIDATOR_KEY, &[disprove_tx]))
+            .await
+            .unwrap();
+        assert_eq!(finished, register_id);
+
+        committee.add_proof(
+            VALIDATOR_KEY,
+            ProofRes {
+                proof_id: "proof".to_string(),
+                vk_hash: vk_hash.clone(),
+                circuit_type: CircuitType::Groth16,
+                proof: String::new(),
+                public_inputs: vec![],
+            },
+        );
+        let request = ChallengeRequest::new("proof", &vk_hash);
+        wallet.start_challenge(request.clone()).await.unwrap();
+        let err = wallet
+            .get_committee_challenge_tx(request.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            committee_error(&err),
+            Some(ChallengeError::ChallengeRawCommitteeTxNotReady.code())
+        );
+
+        poll_until(&wallet, &request, ChallengeStatus::ChallengeTxReadyToSubmit).await;
+        let status = wallet.challenge_status(request.clone()).await.unwrap();
+        assert_eq!(
+            status,
+            ChallengeStatus::ChallengeTxReadyToSubmit.to_string()
+        );
+        let raw_tx = wallet
+            .get_committee_challenge_tx(request.clone())
+            .await
+            .unwrap();
+        let txid = wallet
+            .finish_challenge(FinishChallengeRequest::new("proof", &raw_tx))
+            .await
+            .unwrap();
+
+        let info = poll_until(&wallet, &request, ChallengeStatus::DisproveTxReadyToHandle).await;
+        assert_eq!(info.challenge_txid, Some(txid.to_string()));
+        assert_eq!(info.assert_txid, Some(assert_tx.compute_txid().to_string()));
+        assert_eq!(info.disprove_txid, None);
+
+        wallet
+            .disprove(DisproveRequest::new("proof", 0, "reward_address"))
+            .await
+            .unwrap();
+        let info = poll_until(&wallet, &request, ChallengeStatus::ChallengeSucceed).await;
+        assert_eq!(
+            info.disprove_txid,
+            Some(tx(vec![]).compute_txid().to_string())
+        );
+    }
+}
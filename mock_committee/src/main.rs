@@ -0,0 +1,54 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use bitcoin::Network;
+use clap::Parser;
+use mock_committee::{Fixtures, MockCommittee};
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "mock-committee",
+    about = "In-memory Fiamma committee for local development and tests"
+)]
+struct Args {
+    #[clap(
+        long,
+        env = "MOCK_COMMITTEE_ADDR",
+        default_value = "127.0.0.1:33000",
+        help = "Address to serve the committee JSON-RPC on, the local network of fcli uses 127.0.0.1:33000"
+    )]
+    addr: SocketAddr,
+    #[clap(
+        long,
+        default_value = "regtest",
+        help = "Bitcoin network of the protocol params, regtest or signet"
+    )]
+    network: Network,
+    #[clap(long, help = "JSON file of the circuits and proofs to start with")]
+    fixtures: Option<PathBuf>,
+    #[clap(long, help = "Open a rotation window to the next committee version")]
+    rotation: bool,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let committee = MockCommittee::new(args.network)?;
+    if let Some(path) = &args.fixtures {
+        let fixtures: Fixtures = serde_json::from_slice(&std::fs::read(path)?)?;
+        committee.load_fixtures(fixtures)?;
+    }
+    if args.rotation {
+        committee.start_rotation();
+    }
+
+    let (addr, handle) = committee.serve(args.addr).await?;
+    println!("Mock committee listening on http://{}", addr);
+    tokio::select! {
+        _ = handle.clone().stopped() => {}
+        _ = tokio::signal::ctrl_c() => {
+            handle.stop()?;
+        }
+    }
+    Ok(())
+}
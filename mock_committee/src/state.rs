@@ -0,0 +1,594 @@
+use std::{collections::HashMap, str::FromStr};
+
+use bitcoin::{
+    consensus::encode,
+    opcodes::all::OP_CHECKMULTISIG,
+    script::Builder,
+    secp256k1::{Secp256k1, SecretKey},
+    Network, PublicKey, ScriptBuf, Transaction, TxOut, Txid, XOnlyPublicKey,
+};
+use transactions::bitvm::{leaf_script_pubkey, leaf_spend_info};
+use types::{
+    bitvm::{LeafScript, NUMS_INTERNAL_KEY},
+    challenge::{
+        ChallengeInfoRes, ChallengeRequest, ChallengeStatus, FinishChallengeRequest, ProofRes,
+    },
+    circuit::{CircuitInfo, CircuitType, RegisterCircuitRequest},
+    committee::{CommitteeKeySet, CommitteeVersionId, CommitteeVersionRes},
+    disprove::DisproveRequest,
+    error::{ChallengeError, CircuitError, DisproveError, RegisterNodeError},
+    params::{ProtocolParams, ProtocolParamsRes},
+    register::CircuitTx,
+    FinishRegisterRequest, RegisterRequest,
+};
+use web3_decl::error::Web3Error;
+
+/// Statuses a challenge goes through, in order. Every status or info query moves a challenge
+/// one status forward, except at the statuses waiting for the challenger, see [`next_status`].
+const PROGRESSION: &[ChallengeStatus] = &[
+    ChallengeStatus::ChallengeCreated,
+    ChallengeStatus::PartialAssertTxReady,
+    ChallengeStatus::ChallengeTxReadyToSubmit,
+    ChallengeStatus::ChallengeTxSubmitted,
+    ChallengeStatus::ChallengeTxConfirmed,
+    ChallengeStatus::AssertTxReadyToSubmit,
+    ChallengeStatus::AssertTxSubmitted,
+    ChallengeStatus::AssertTxConfirmed,
+    ChallengeStatus::DisproveTxReadyToHandle,
+    ChallengeStatus::DisproveTxHandling,
+    ChallengeStatus::DisproveTxReadyToSubmit,
+    ChallengeStatus::DisproveTxSubmitted,
+    ChallengeStatus::DisproveTxConfirmed,
+    ChallengeStatus::ChallengeSucceed,
+];
+
+/// Status a challenge in `status` moves to on the next query, `None` while it waits for the
+/// challenger's `finishChallenge` or `disprove` call, or once it is over.
+pub fn next_status(status: &ChallengeStatus) -> Option<ChallengeStatus> {
+    match status {
+        ChallengeStatus::ChallengeTxReadyToSubmit | ChallengeStatus::DisproveTxReadyToHandle => {
+            None
+        }
+        _ => PROGRESSION.get(step(status)? + 1).cloned(),
+    }
+}
+
+fn step(status: &ChallengeStatus) -> Option<usize> {
+    PROGRESSION.iter().position(|s| s == status)
+}
+
+fn reached(status: &ChallengeStatus, milestone: ChallengeStatus) -> bool {
+    step(status) >= step(&milestone)
+}
+
+struct Registration {
+    id: u32,
+    public_key: PublicKey,
+    version: CommitteeVersionId,
+    assert_tx: Transaction,
+    challenge_tx: Transaction,
+    /// Set by `finishRegister`.
+    disprove_txs: Option<Vec<CircuitTx>>,
+}
+
+struct Circuit {
+    vk_hash: String,
+}
+
+struct Challenge {
+    validator_key: String,
+    vk_hash: String,
+    status: ChallengeStatus,
+    challenge_txid: Option<Txid>,
+}
+
+pub(crate) struct State {
+    network: Network,
+    rotating: bool,
+    registrations: HashMap<String, Registration>,
+    /// Circuits in registration order, the circuit id is the index plus 1.
+    circuits: Vec<Circuit>,
+    /// Proofs by proof id, with the validator key of the registration that proved them.
+    proofs: HashMap<String, (String, ProofRes)>,
+    /// Leaf scripts by vk hash and validator key, the scripts shared by every validator under
+    /// no validator key.
+    leaf_scripts: HashMap<(String, Option<String>), Vec<LeafScript>>,
+    challenges: HashMap<String, Challenge>,
+}
+
+impl State {
+    pub(crate) fn new(network: Network) -> Self {
+        Self {
+            network,
+            rotating: false,
+            registrations: HashMap::new(),
+            circuits: vec![],
+            proofs: HashMap::new(),
+            leaf_scripts: HashMap::new(),
+            challenges: HashMap::new(),
+        }
+    }
+
+    /// Protocol params of `version`, `MockCommittee::new` only accepts networks having them.
+    fn params(&self, version: CommitteeVersionId) -> ProtocolParams {
+        ProtocolParams::new(self.network, version).expect("network has protocol params")
+    }
+
+    pub(crate) fn start_rotation(&mut self) {
+        self.rotating = true;
+    }
+
+    pub(crate) fn add_proof(&mut self, validator_key: &str, proof: ProofRes) {
+        self.proofs
+            .insert(proof.proof_id.clone(), (validator_key.to_string(), proof));
+    }
+
+    pub(crate) fn add_leaf_scripts(
+        &mut self,
+        vk_hash: &str,
+        validator_key: Option<&str>,
+        leaf_scripts: Vec<LeafScript>,
+    ) {
+        self.leaf_scripts.insert(
+            (vk_hash.to_string(), validator_key.map(str::to_string)),
+            leaf_scripts,
+        );
+    }
+
+    /// Leaf scripts of the circuit `vk_hash` for the validator, its own ones first.
+    fn leaf_scripts(&self, vk_hash: &str, validator_key: &str) -> Option<&[LeafScript]> {
+        self.leaf_scripts
+            .get(&(vk_hash.to_string(), Some(validator_key.to_string())))
+            .or_else(|| self.leaf_scripts.get(&(vk_hash.to_string(), None)))
+            .map(Vec::as_slice)
+    }
+
+    /// Committee keys of `version`, derived from fixed secret keys so the multi sig scripts
+    /// stay the same across restarts.
+    fn committee_keys(version: CommitteeVersionId) -> Vec<PublicKey> {
+        let secp = Secp256k1::new();
+        (1..=3u8)
+            .map(|i| {
+                let secret_key = SecretKey::from_slice(&[(version as u8) * 16 + i; 32])
+                    .expect("fixed committee secret keys are valid");
+                PublicKey::new(secret_key.public_key(&secp))
+            })
+            .collect()
+    }
+
+    fn key_set(version: CommitteeVersionId) -> CommitteeKeySet {
+        CommitteeKeySet {
+            version,
+            public_keys: Self::committee_keys(version)
+                .iter()
+                .map(PublicKey::to_string)
+                .collect(),
+        }
+    }
+
+    fn check_version(&self, version: CommitteeVersionId) -> Result<(), Web3Error> {
+        if version == CommitteeVersionId::latest()
+            || (self.rotating && version == CommitteeVersionId::next())
+        {
+            return Ok(());
+        }
+        Err(
+            RegisterNodeError::NoCommitteeRotation(format!("{:?}", CommitteeVersionId::latest()))
+                .into(),
+        )
+    }
+
+    /// N of n multi sig script of the committee of `version` and the register.
+    fn multi_sig_script(version: CommitteeVersionId, register_pk: &PublicKey) -> ScriptBuf {
+        let mut keys = Self::committee_keys(version);
+        keys.push(*register_pk);
+
+        let mut builder = Builder::new().push_int(keys.len() as i64);
+        for key in &keys {
+            builder = builder.push_key(key);
+        }
+        builder
+            .push_int(keys.len() as i64)
+            .push_opcode(OP_CHECKMULTISIG)
+            .into_script()
+    }
+
+    fn registration(&self, validator_key: &str) -> Result<&Registration, Web3Error> {
+        self.registrations
+            .get(validator_key)
+            .ok_or_else(|| RegisterNodeError::RegisterIDNotFound(validator_key.to_string()).into())
+    }
+
+    fn proof(&self, proof_id: &str) -> Result<&(String, ProofRes), Web3Error> {
+        self.proofs
+            .get(proof_id)
+            .ok_or_else(|| DisproveError::ProofIDNotFound(proof_id.to_string()).into())
+    }
+
+    fn circuit(&self, vk_hash: &str) -> Option<&Circuit> {
+        self.circuits
+            .iter()
+            .find(|circuit| circuit.vk_hash == vk_hash)
+    }
+
+    pub(crate) fn get_multi_sig_address(&self, register_pk: &str) -> Result<ScriptBuf, Web3Error> {
+        self.get_multi_sig_address_of_version(register_pk, CommitteeVersionId::latest())
+    }
+
+    pub(crate) fn get_multi_sig_address_of_version(
+        &self,
+        register_pk: &str,
+        version: CommitteeVersionId,
+    ) -> Result<ScriptBuf, Web3Error> {
+        self.check_version(version)?;
+        let register_pk = parse_public_key(register_pk)?;
+        Ok(Self::multi_sig_script(version, &register_pk))
+    }
+
+    pub(crate) fn get_multi_sig_address_of_proof(
+        &self,
+        proof_id: &str,
+    ) -> Result<ScriptBuf, Web3Error> {
+        let (validator_key, _) = self.proof(proof_id)?;
+        let registration = self
+            .registrations
+            .get(validator_key)
+            .ok_or_else(|| DisproveError::RegisterIDNotFound(proof_id.to_string()))?;
+        Ok(Self::multi_sig_script(
+            registration.version,
+            &registration.public_key,
+        ))
+    }
+
+    pub(crate) fn get_committee_version(&self) -> CommitteeVersionRes {
+        CommitteeVersionRes {
+            current: Self::key_set(CommitteeVersionId::latest()),
+            next: self
+                .rotating
+                .then(|| Self::key_set(CommitteeVersionId::next())),
+        }
+    }
+
+    pub(crate) fn get_protocol_params(&self) -> ProtocolParamsRes {
+        ProtocolParamsRes {
+            params: self.params(CommitteeVersionId::latest()),
+            circuit_types: vec![CircuitType::Groth16, CircuitType::Fflonk],
+        }
+    }
+
+    /// Checks the stake tx like the committee does and stores the presigned txs. A validator
+    /// registered for the current committee may register again for the next one during a
+    /// rotation, which replaces its txs.
+    pub(crate) fn start_register(&mut self, request: RegisterRequest) -> Result<u32, Web3Error> {
+        let validator_key = request.validator_key;
+        if let Some(registration) = self.registrations.get(&validator_key) {
+            if registration.version == request.committee_version {
+                return Err(RegisterNodeError::ValidatorKeyRegistered(validator_key).into());
+            }
+        }
+        self.check_version(request.committee_version)?;
+        let public_key = parse_public_key(&request.public_key)?;
+
+        let params = self.params(request.committee_version);
+        let multi_sig_script = Self::multi_sig_script(request.committee_version, &public_key);
+        let script_pubkey = ScriptBuf::new_p2wsh(&multi_sig_script.wscript_hash());
+
+        let stake_tx = decode_tx(&request.stake_tx)
+            .ok_or_else(|| RegisterNodeError::StakeTxMissing(validator_key.clone()))?;
+        let output = |index: u32| {
+            stake_tx
+                .output
+                .get(index as usize)
+                .ok_or(RegisterNodeError::StakeTxMissingOutput)
+        };
+        let stake = output(params.stake_value_index)?;
+        if stake.value != params.stake_output_amount() {
+            return Err(RegisterNodeError::StakeTxInvalidStakeValue(stake.value.to_sat()).into());
+        }
+        for index in [
+            params.stake_timelock_index,
+            params.connector_a_index,
+            params.connector_b_index,
+        ] {
+            let connector = output(index)?;
+            if connector.value != params.dust_amount {
+                return Err(RegisterNodeError::StakeTxInvalidConnectorValue(
+                    connector.value.to_sat(),
+                )
+                .into());
+            }
+        }
+        for index in [
+            params.stake_value_index,
+            params.stake_timelock_index,
+            params.connector_a_index,
+            params.connector_b_index,
+        ] {
+            let script = &output(index)?.script_pubkey;
+            if *script != script_pubkey {
+                return Err(
+                    RegisterNodeError::StakeTxInvalidScriptPubkey(script.to_hex_string()).into(),
+                );
+            }
+        }
+
+        let assert_tx = decode_tx(&request.assert_tx)
+            .ok_or_else(|| RegisterNodeError::AssertTxNotFound(validator_key.clone()))?;
+        if assert_tx.input.is_empty() {
+            return Err(RegisterNodeError::AssertTxMissingInput.into());
+        }
+        let challenge_tx = decode_tx(&request.challenge_tx)
+            .filter(|tx| !tx.input.is_empty())
+            .ok_or(RegisterNodeError::ChallengeTxMissingInput)?;
+
+        let id = match self.registrations.get(&validator_key) {
+            Some(registration) => registration.id,
+            None => self.registrations.len() as u32 + 1,
+        };
+        self.registrations.insert(
+            validator_key,
+            Registration {
+                id,
+                public_key,
+                version: request.committee_version,
+                assert_tx,
+                challenge_tx,
+                disprove_txs: None,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Assert tx of the registration for `circuit`: the presigned assert tx with the outputs
+    /// the committee adds, the stake back to the committee and connector C. Connector C
+    /// commits to the circuit's leaf scripts when the mock has any, to the multi sig script
+    /// otherwise.
+    fn assert_tx(
+        &self,
+        validator_key: &str,
+        registration: &Registration,
+        circuit: &Circuit,
+    ) -> Transaction {
+        let params = self.params(registration.version);
+        let multi_sig_script =
+            Self::multi_sig_script(registration.version, &registration.public_key);
+        let multi_sig_pubkey = ScriptBuf::new_p2wsh(&multi_sig_script.wscript_hash());
+
+        let connector_c = self
+            .leaf_scripts(&circuit.vk_hash, validator_key)
+            .zip(XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY).ok())
+            .and_then(|(leaves, internal_key)| leaf_spend_info(leaves, internal_key).ok())
+            .map(|spend_info| leaf_script_pubkey(&spend_info))
+            .unwrap_or_else(|| multi_sig_pubkey.clone());
+
+        let mut tx = registration.assert_tx.clone();
+        tx.output = vec![TxOut {
+            value: params.stake_amount,
+            script_pubkey: multi_sig_pubkey,
+        }];
+        // The stake output and connector C are the only outputs, the params place connector
+        // C before or after the stake.
+        tx.output.insert(
+            params.connector_c_index as usize,
+            TxOut {
+                value: params.dust_amount,
+                script_pubkey: connector_c,
+            },
+        );
+        tx
+    }
+
+    pub(crate) fn get_committee_assert_txs(
+        &self,
+        validator_key: &str,
+    ) -> Result<Vec<CircuitTx>, Web3Error> {
+        let registration = self.registration(validator_key)?;
+        Ok(self
+            .circuits
+            .iter()
+            .map(|circuit| {
+                let assert_tx = self.assert_tx(validator_key, registration, circuit);
+                CircuitTx::new(
+                    &circuit.vk_hash,
+                    types::transaction::TransactionType::AssertTx,
+                    &encode::serialize_hex(&assert_tx),
+                )
+            })
+            .collect())
+    }
+
+    pub(crate) fn finish_register(
+        &mut self,
+        request: FinishRegisterRequest,
+    ) -> Result<u32, Web3Error> {
+        let circuits = self.circuits.len() as u32;
+        let registration = self
+            .registrations
+            .get_mut(&request.validator_key)
+            .ok_or(RegisterNodeError::RegisterIDNotFound(request.validator_key))?;
+        let disprove_txs = request.disprove_txs.len() as u32;
+        if disprove_txs != circuits {
+            return Err(RegisterNodeError::InvalidDisproveTxCount(circuits, disprove_txs).into());
+        }
+
+        registration.disprove_txs = Some(request.disprove_txs);
+        Ok(registration.id)
+    }
+
+    pub(crate) fn register_circuit(
+        &mut self,
+        request: RegisterCircuitRequest,
+    ) -> Result<u32, Web3Error> {
+        let vk_hash = CircuitInfo::hash(&request.vk, request.circuit_type.clone());
+        if request.vk.is_empty() {
+            return Err(CircuitError::CircuitInvalid(vk_hash).into());
+        }
+        if self.circuit(&vk_hash).is_some() {
+            return Err(CircuitError::CircuitAlreadyExists(vk_hash).into());
+        }
+
+        self.circuits.push(Circuit { vk_hash });
+        Ok(self.circuits.len() as u32)
+    }
+
+    pub(crate) fn get_proof(&self, proof_id: &str) -> Result<ProofRes, Web3Error> {
+        Ok(self.proof(proof_id)?.1.clone())
+    }
+
+    pub(crate) fn start_challenge(&mut self, request: ChallengeRequest) -> Result<u32, Web3Error> {
+        let proof_id = request.proof_id;
+        if self.challenges.contains_key(&proof_id) {
+            return Err(ChallengeError::ChallengeExists(proof_id).into());
+        }
+        let (validator_key, proof) = self.proof(&proof_id)?;
+        if proof.vk_hash != request.vk_hash {
+            return Err(ChallengeError::ProofCircuitMismatch(proof_id, request.vk_hash).into());
+        }
+        if self.circuit(&request.vk_hash).is_none() {
+            return Err(ChallengeError::CircuitNotRegistered(request.vk_hash).into());
+        }
+        if self
+            .registrations
+            .get(validator_key)
+            .and_then(|registration| registration.disprove_txs.as_ref())
+            .is_none()
+        {
+            return Err(ChallengeError::RegisterIDNotFound(validator_key.clone()).into());
+        }
+
+        let id = self.challenges.len() as u32 + 1;
+        let challenge = Challenge {
+            validator_key: validator_key.clone(),
+            vk_hash: request.vk_hash,
+            status: ChallengeStatus::ChallengeCreated,
+            challenge_txid: None,
+        };
+        self.challenges.insert(proof_id, challenge);
+        Ok(id)
+    }
+
+    /// Moves the challenge of the proof one status forward, see [`next_status`].
+    fn advance(&mut self, proof_id: &str) -> Option<&Challenge> {
+        let challenge = self.challenges.get_mut(proof_id)?;
+        if let Some(status) = next_status(&challenge.status) {
+            challenge.status = status;
+        }
+        Some(challenge)
+    }
+
+    pub(crate) fn challenge_status(&mut self, request: ChallengeRequest) -> String {
+        match self.advance(&request.proof_id) {
+            Some(challenge) => challenge.status.to_string(),
+            None => ChallengeStatus::ChallengeNotExist.to_string(),
+        }
+    }
+
+    pub(crate) fn challenge_info(&mut self, request: ChallengeRequest) -> ChallengeInfoRes {
+        let proof_id = request.proof_id;
+        if self.advance(&proof_id).is_none() {
+            return ChallengeInfoRes {
+                proof_id,
+                status: ChallengeStatus::ChallengeNotExist,
+                challenge_txid: None,
+                assert_txid: None,
+                disprove_txid: None,
+            };
+        }
+        let challenge = &self.challenges[&proof_id];
+
+        let registration = &self.registrations[&challenge.validator_key];
+        let assert_txid = reached(&challenge.status, ChallengeStatus::AssertTxSubmitted)
+            .then(|| self.circuit(&challenge.vk_hash))
+            .flatten()
+            .map(|circuit| {
+                self.assert_tx(&challenge.validator_key, registration, circuit)
+                    .compute_txid()
+            });
+        let disprove_txid = reached(&challenge.status, ChallengeStatus::DisproveTxSubmitted)
+            .then_some(registration.disprove_txs.as_ref())
+            .flatten()
+            .and_then(|txs| txs.iter().find(|tx| tx.vk_hash == challenge.vk_hash))
+            .and_then(|tx| encode::deserialize_hex::<Transaction>(&tx.tx_hex).ok())
+            .map(|tx| tx.compute_txid());
+
+        ChallengeInfoRes {
+            proof_id,
+            status: challenge.status.clone(),
+            challenge_txid: challenge.challenge_txid.map(|txid| txid.to_string()),
+            assert_txid: assert_txid.map(|txid| txid.to_string()),
+            disprove_txid: disprove_txid.map(|txid| txid.to_string()),
+        }
+    }
+
+    fn ready_challenge(&self, proof_id: &str) -> Result<&Challenge, Web3Error> {
+        let challenge = self
+            .challenges
+            .get(proof_id)
+            .ok_or_else(|| ChallengeError::ChallengeIDNotFound(proof_id.to_string()))?;
+        if challenge.status != ChallengeStatus::ChallengeTxReadyToSubmit {
+            return Err(ChallengeError::ChallengeRawCommitteeTxNotReady.into());
+        }
+        Ok(challenge)
+    }
+
+    /// The presigned challenge tx of the challenged registration with the committee's output,
+    /// the challenge amount and connector A locked to the multi sig script.
+    pub(crate) fn get_committee_challenge_tx(
+        &self,
+        request: ChallengeRequest,
+    ) -> Result<String, Web3Error> {
+        let challenge = self.ready_challenge(&request.proof_id)?;
+        let registration = &self.registrations[&challenge.validator_key];
+        let params = self.params(registration.version);
+        let multi_sig_script =
+            Self::multi_sig_script(registration.version, &registration.public_key);
+
+        let mut tx = registration.challenge_tx.clone();
+        tx.output = vec![TxOut {
+            value: params.challenge_amount.unchecked_add(params.dust_amount),
+            script_pubkey: ScriptBuf::new_p2wsh(&multi_sig_script.wscript_hash()),
+        }];
+        Ok(encode::serialize_hex(&tx))
+    }
+
+    pub(crate) fn finish_challenge(
+        &mut self,
+        request: FinishChallengeRequest,
+    ) -> Result<Txid, Web3Error> {
+        self.ready_challenge(&request.proof_id)?;
+        let challenge_tx = encode::deserialize_hex::<Transaction>(&request.filled_challenge_tx)
+            .map_err(|e| ChallengeError::ChallengeTxDeserializeError(e.to_string()))?;
+
+        let txid = challenge_tx.compute_txid();
+        let challenge = self
+            .challenges
+            .get_mut(&request.proof_id)
+            .expect("checked by ready_challenge");
+        challenge.challenge_txid = Some(txid);
+        challenge.status = ChallengeStatus::ChallengeTxSubmitted;
+        Ok(txid)
+    }
+
+    pub(crate) fn disprove(&mut self, request: DisproveRequest) -> Result<(), Web3Error> {
+        let challenge = self
+            .challenges
+            .get_mut(&request.proof_id)
+            .ok_or_else(|| DisproveError::ChallengeIDNotFound(request.proof_id.clone()))?;
+        if challenge.status != ChallengeStatus::DisproveTxReadyToHandle {
+            return Err(DisproveError::AssertTxIsNotConfirmed(request.proof_id).into());
+        }
+
+        challenge.status = ChallengeStatus::DisproveTxHandling;
+        Ok(())
+    }
+}
+
+fn parse_public_key(public_key: &str) -> Result<PublicKey, Web3Error> {
+    PublicKey::from_str(public_key)
+        .map_err(|_| Web3Error::InvalidRegisterPublicKey(public_key.to_string()))
+}
+
+/// Txs of the register requests are the hex of the consensus encoding as bytes.
+fn decode_tx(bytes: &[u8]) -> Option<Transaction> {
+    encode::deserialize_hex(std::str::from_utf8(bytes).ok()?).ok()
+}
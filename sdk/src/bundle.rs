@@ -0,0 +1,176 @@
+//! Bundle of a validator's presigned txs, so any of them can be broadcast through the
+//! validator's own bitcoin backend when the committee is unreachable.
+
+use std::collections::{HashMap, HashSet};
+
+use bitcoin::{consensus::encode, Network, OutPoint, ScriptBuf, Transaction, Txid};
+use bitcoin_client::ChainSource;
+use transactions::policy::{self, PolicyScope};
+use types::{
+    error::FcliError,
+    presigned_transactions::{
+        PresignedInput, PresignedTransactions, PresignedTx, PRESIGNED_BUNDLE_VERSION,
+    },
+    register::CircuitTx,
+};
+
+use crate::{lookup_prevout, lookup_prevouts};
+
+/// Bundles `txs`, the outputs they spend are looked up among them first and then on `chain`.
+/// A tx listed twice is bundled once.
+pub async fn export<C: ChainSource>(
+    chain: &C,
+    validator_key: &str,
+    network: Network,
+    txs: &[CircuitTx],
+) -> anyhow::Result<PresignedTransactions> {
+    let mut seen = HashSet::new();
+    let mut decoded = vec![];
+    for circuit_tx in txs {
+        let tx = encode::deserialize_hex::<Transaction>(&circuit_tx.tx_hex)?;
+        if seen.insert(tx.compute_txid()) {
+            decoded.push((circuit_tx, tx));
+        }
+    }
+    let refs = decoded.iter().map(|(_, tx)| tx).collect::<Vec<_>>();
+
+    let prevouts = lookup_prevouts(chain, &refs, &refs).await;
+
+    let txs = decoded
+        .iter()
+        .map(|(circuit_tx, tx)| {
+            let inputs = tx
+                .input
+                .iter()
+                .map(|input| {
+                    let prevout = prevouts.get(&input.previous_output).cloned();
+                    // The witness script is the last item of a p2wsh witness, a presigned
+                    // input the committee still has to complete may not carry it yet.
+                    let witness_script = prevout
+                        .as_ref()
+                        .filter(|prevout| prevout.script_pubkey.is_p2wsh())
+                        .and_then(|_| input.witness.last())
+                        .map(|script| ScriptBuf::from_bytes(script.to_vec()));
+                    PresignedInput {
+                        outpoint: input.previous_output,
+                        prevout,
+                        witness_script,
+                    }
+                })
+                .collect();
+
+            PresignedTx {
+                tx_type: circuit_tx.tx_type.clone(),
+                circuit_hash: Some(circuit_tx.vk_hash.clone()).filter(|hash| !hash.is_empty()),
+                txid: tx.compute_txid(),
+                tx_hex: circuit_tx.tx_hex.clone(),
+                inputs,
+            }
+        })
+        .collect();
+
+    Ok(PresignedTransactions::new(validator_key, network, txs))
+}
+
+/// Checks a tx of the bundle against the relay policy and broadcasts it on `chain`. The
+/// outputs it spends are looked up in the bundle first and then on `chain`.
+pub async fn broadcast<C: ChainSource>(
+    chain: &C,
+    bundle: &PresignedTransactions,
+    presigned_tx: &PresignedTx,
+) -> anyhow::Result<Txid> {
+    if bundle.version != PRESIGNED_BUNDLE_VERSION {
+        anyhow::bail!(FcliError::InvalidArgument(format!(
+            "presigned bundle version {} is not supported, expected version {}",
+            bundle.version, PRESIGNED_BUNDLE_VERSION
+        )));
+    }
+
+    let tx = encode::deserialize_hex::<Transaction>(&presigned_tx.tx_hex)?;
+    let mut prevouts = HashMap::new();
+    for input in &tx.input {
+        let outpoint = input.previous_output;
+        let prevout = match bundle.prevout(&outpoint) {
+            Some(prevout) => Some(prevout),
+            None => lookup_prevout(chain, &[], &outpoint).await,
+        };
+        if let Some(prevout) = prevout {
+            prevouts.insert(outpoint, prevout);
+        }
+    }
+    policy::ensure(&tx, PolicyScope::Complete, |outpoint: &OutPoint| {
+        prevouts.get(outpoint).cloned()
+    })?;
+    Ok(chain.broadcast(&tx).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{Amount, TxOut};
+    use bitcoin_client::simulator::ChainSimulator;
+    use transactions::graph::{GraphSpec, TxGraph};
+    use types::{
+        committee::CommitteeVersionId, params::ProtocolParams, transaction::TransactionType,
+    };
+
+    use super::*;
+    use crate::Auxiliary;
+
+    #[tokio::test]
+    async fn test_export_and_broadcast() {
+        let chain = ChainSimulator::new();
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let key = Auxiliary::from_private_key("tprv8jzau9CfsdkXMzqWFWSgu7f4z1vRk53yiqYqByfoakSLNFQ4bBuTsrUDLXtKHTPZhp161h49vEJr2zwN92G7ZHLZMFvome2U8GcAqDzVRhW", Network::Regtest).unwrap();
+        let amount = params.stake_input_amount() + Amount::from_sat(50_000);
+        let funding = chain.faucet(key.script_pk.clone(), amount);
+        let multi_sig_script = ScriptBuf::from_bytes(vec![0x51]);
+        let graph = TxGraph::new(GraphSpec {
+            operator_key: key.private_key,
+            multi_sig_script,
+            params,
+            funding: (
+                funding,
+                TxOut {
+                    value: amount,
+                    script_pubkey: key.script_pk.clone(),
+                },
+            ),
+            change_script_pubkey: key.script_pk.clone(),
+        })
+        .unwrap();
+
+        let stake_tx = CircuitTx::new(
+            "",
+            TransactionType::StakeTx,
+            &encode::serialize_hex(&graph.stake_tx),
+        );
+        let assert_tx = CircuitTx::new(
+            "vk_hash",
+            TransactionType::AssertTx,
+            &encode::serialize_hex(&graph.assert_tx),
+        );
+        let txs = [stake_tx.clone(), assert_tx, stake_tx];
+        let bundle = export(&chain, "validator_key", Network::Regtest, &txs)
+            .await
+            .unwrap();
+        assert_eq!(bundle.txs.len(), 2);
+        assert_eq!(bundle.txs[1].circuit_hash.as_deref(), Some("vk_hash"));
+        // The assert tx spends the stake tx, its prevouts come from the bundle.
+        let stake_txid = graph.stake_txid();
+        assert_eq!(
+            bundle.txs[1].inputs[0].prevout,
+            Some(graph.stake_output(graph.params.stake_value_index).1)
+        );
+        assert_eq!(bundle.select("stake_tx").len(), 1);
+        assert_eq!(bundle.select(&stake_txid.to_string()).len(), 1);
+
+        assert_eq!(
+            broadcast(&chain, &bundle, &bundle.txs[0]).await.unwrap(),
+            stake_txid
+        );
+        assert!(chain.get_tx(&stake_txid).await.is_ok());
+
+        // The presigned assert tx misses the outputs the committee adds.
+        assert!(broadcast(&chain, &bundle, &bundle.txs[1]).await.is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use bitcoin::{
+    consensus::encode, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+};
+use bitcoin_client::ChainSource;
+use transactions::{
+    graph::connector_txout,
+    policy::{self, PolicyScope},
+};
+use types::{
+    challenge::{ChallengeInfoRes, ChallengeRequest, FinishChallengeRequest, ProofRes},
+    circuit::CircuitType,
+    disprove::DisproveRequest,
+    error::{ChallengeError, FcliError},
+    params::ProtocolParams,
+    register::QueryAssertTxReq,
+};
+use wallet::Wallet;
+use web3_decl::namespaces::committee::CommitteeNamespaceClient;
+
+use crate::{
+    keys::{Auxiliary, FundingInput},
+    lookup_prevouts,
+};
+
+/// Challenges a proof: starts the challenge, then funds and signs the committee's challenge
+/// tx once it is ready to submit.
+pub struct ChallengeFlow<'a, P, C> {
+    wallet: &'a Wallet<P>,
+    chain: &'a C,
+    params: ProtocolParams,
+}
+
+impl<'a, P, C> ChallengeFlow<'a, P, C>
+where
+    P: CommitteeNamespaceClient + Sync,
+    C: ChainSource,
+{
+    pub fn new(wallet: &'a Wallet<P>, chain: &'a C, params: ProtocolParams) -> Self {
+        Self {
+            wallet,
+            chain,
+            params,
+        }
+    }
+
+    pub fn params(&self) -> &ProtocolParams {
+        &self.params
+    }
+
+    /// Proof submitted to the committee.
+    pub async fn proof(&self, proof_id: &str) -> anyhow::Result<ProofRes> {
+        self.wallet.get_proof(proof_id).await
+    }
+
+    /// Verifies `proof` against the verify key `vk` of the challenged circuit, `true` means
+    /// the proof is valid and a challenge against it would fail.
+    pub fn check_proof(
+        &self,
+        request: &ChallengeRequest,
+        vk: &[u8],
+        circuit_type: &CircuitType,
+        proof: &ProofRes,
+    ) -> anyhow::Result<bool> {
+        if proof.vk_hash != request.vk_hash {
+            anyhow::bail!(ChallengeError::ProofCircuitMismatch(
+                request.proof_id.clone(),
+                request.vk_hash.clone()
+            ));
+        }
+
+        Ok(verifier::verify_proof(
+            vk,
+            circuit_type,
+            &proof.proof,
+            &proof.public_inputs,
+        )?)
+    }
+
+    /// Starts the challenge, returns the challenge id.
+    pub async fn start(&self, request: ChallengeRequest) -> anyhow::Result<u32> {
+        self.wallet.start_challenge(request).await
+    }
+
+    pub async fn status(&self, request: ChallengeRequest) -> anyhow::Result<String> {
+        self.wallet.challenge_status(request).await
+    }
+
+    pub async fn info(&self, request: ChallengeRequest) -> anyhow::Result<ChallengeInfoRes> {
+        self.wallet.challenge_info(request).await
+    }
+
+    /// Assert tx the committee generated for the validator and the circuit of the proof.
+    pub async fn assert_tx_of_proof(
+        &self,
+        request: &ChallengeRequest,
+        validator_key: &str,
+    ) -> anyhow::Result<Transaction> {
+        let assert_txs = self
+            .wallet
+            .get_committee_assert_txs(QueryAssertTxReq::new(validator_key))
+            .await?;
+        let circuit_tx = assert_txs
+            .iter()
+            .find(|circuit_tx| circuit_tx.vk_hash == request.vk_hash)
+            .ok_or_else(|| ChallengeError::AssertTxOfProofNotFound(request.proof_id.clone()))?;
+        Ok(encode::deserialize_hex::<Transaction>(&circuit_tx.tx_hex)?)
+    }
+
+    /// Adds the challenger's funding input and change output to the committee's raw challenge
+    /// tx and signs the funding input.
+    pub async fn fill_challenger_tx(
+        &self,
+        raw_tx: &str,
+        key: &Auxiliary,
+        input: &FundingInput,
+        multi_sig_script: &ScriptBuf,
+    ) -> anyhow::Result<Transaction> {
+        let params = &self.params;
+
+        // query challenger's pre tx value
+        let pre_tx = self.chain.get_tx(&input.txid).await?;
+        let value = pre_tx.tx_out(input.vout as usize)?.value;
+
+        let tx_in = TxIn {
+            previous_output: OutPoint {
+                txid: input.txid,
+                vout: input.vout,
+            },
+            script_sig: ScriptBuf::default(), // For a p2tr script_sig is empty.
+            sequence: Sequence::MAX,
+            witness: Witness::default(), // Filled in after signing.
+        };
+
+        let required = params.challenge_fee.unchecked_add(params.challenge_amount);
+        let change = TxOut {
+            value: value
+                .checked_sub(required)
+                .ok_or(FcliError::InsufficientFunds {
+                    available: value,
+                    required,
+                })?,
+            script_pubkey: key.script_pk.clone(),
+        };
+
+        let mut tx = encode::deserialize_hex::<Transaction>(raw_tx)?;
+        tx.input.push(tx_in);
+        tx.output.push(change);
+
+        let input_txouts = vec![
+            connector_txout(params, multi_sig_script),
+            TxOut {
+                value,
+                script_pubkey: key.script_pk.clone(),
+            },
+        ];
+        key.sign_key_spend(&mut tx, 1, &input_txouts)?;
+
+        Ok(tx)
+    }
+
+    /// Funds and signs the committee's challenge tx and builds the `finishChallenge` request
+    /// submitting it.
+    pub async fn finish_request(
+        &self,
+        request: ChallengeRequest,
+        key: &Auxiliary,
+        input: &FundingInput,
+    ) -> anyhow::Result<(FinishChallengeRequest, Transaction)> {
+        let proof_id = request.proof_id.clone();
+        let raw_tx = self.wallet.get_committee_challenge_tx(request).await?;
+        let multi_sig_script = self.wallet.get_multi_sig_script_of_proof(&proof_id).await?;
+        let challenge_tx = self
+            .fill_challenger_tx(&raw_tx, key, input, &multi_sig_script)
+            .await?;
+        let prevouts = lookup_prevouts(self.chain, &[], &[&challenge_tx]).await;
+        policy::ensure(&challenge_tx, PolicyScope::Complete, |outpoint| {
+            prevouts.get(outpoint).cloned()
+        })?;
+
+        let request = FinishChallengeRequest::new(&proof_id, &encode::serialize_hex(&challenge_tx));
+        Ok((request, challenge_tx))
+    }
+
+    /// Funds and signs the committee's challenge tx and submits it, returns the txid the
+    /// committee broadcast and the signed tx.
+    pub async fn finish(
+        &self,
+        request: ChallengeRequest,
+        key: &Auxiliary,
+        input: &FundingInput,
+    ) -> anyhow::Result<(Txid, Transaction)> {
+        let (request, challenge_tx) = self.finish_request(request, key, input).await?;
+        let txid = self.wallet.finish_challenge(request).await?;
+        Ok((txid, challenge_tx))
+    }
+
+    /// Asks the committee to disprove the assert tx of the challenged proof once the disprove
+    /// tx is ready to handle.
+    pub async fn disprove(&self, request: DisproveRequest) -> anyhow::Result<()> {
+        self.wallet.disprove(request).await
+    }
+}
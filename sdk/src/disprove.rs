@@ -0,0 +1,290 @@
+use std::str::FromStr;
+
+use bitcoin::{Address, OutPoint, Transaction, TxOut, Txid, XOnlyPublicKey};
+use bitcoin_client::ChainSource;
+use transactions::{
+    bitvm::find_disprovable_leaves,
+    disprove::DisproveScriptSpend,
+    policy::{self, PolicyScope},
+};
+use types::{
+    bitvm::LeafScript, challenge::ChallengeRequest, disprove::DisproveRequest,
+    error::DisproveError, params::ProtocolParams,
+};
+use wallet::Wallet;
+use web3_decl::namespaces::committee::CommitteeNamespaceClient;
+
+use crate::{
+    keys::{Auxiliary, FundingInput},
+    lookup_prevouts,
+};
+
+/// Disproves the assert tx of a challenged proof, either through the committee or by
+/// spending a succeeding leaf script locally.
+pub struct DisproveFlow<'a, P, C> {
+    wallet: &'a Wallet<P>,
+    chain: &'a C,
+    params: ProtocolParams,
+}
+
+impl<'a, P, C> DisproveFlow<'a, P, C>
+where
+    P: CommitteeNamespaceClient + Sync,
+    C: ChainSource,
+{
+    pub fn new(wallet: &'a Wallet<P>, chain: &'a C, params: ProtocolParams) -> Self {
+        Self {
+            wallet,
+            chain,
+            params,
+        }
+    }
+
+    pub fn params(&self) -> &ProtocolParams {
+        &self.params
+    }
+
+    /// Asks the committee to disprove the assert tx through the leaf `script_index`.
+    pub async fn request(&self, request: DisproveRequest) -> anyhow::Result<()> {
+        self.wallet.disprove(request).await
+    }
+
+    /// Assert tx of the challenged proof, which must be confirmed to be disproved.
+    pub async fn confirmed_assert_tx(
+        &self,
+        request: ChallengeRequest,
+    ) -> anyhow::Result<Transaction> {
+        let proof_id = request.proof_id.clone();
+        let info = self.wallet.challenge_info(request).await?;
+        let assert_txid = info
+            .assert_txid
+            .ok_or_else(|| DisproveError::AssertTxNotFound(proof_id.clone()))?;
+        let assert_txid = Txid::from_str(&assert_txid)?;
+
+        if !self.chain.get_tx_status(&assert_txid).await?.confirmed {
+            anyhow::bail!(DisproveError::AssertTxIsNotConfirmed(proof_id));
+        }
+        Ok(self.chain.get_tx(&assert_txid).await?)
+    }
+
+    /// Indices of the leaf scripts succeeding against the assert tx, the script indices a
+    /// disprove can target.
+    pub fn find_script_indices(
+        &self,
+        assert_tx: &Transaction,
+        leaves: &[LeafScript],
+    ) -> anyhow::Result<Vec<usize>> {
+        find_disprovable_leaves(assert_tx, leaves)
+    }
+
+    /// Builds the disprove tx spending the leaf script output of the assert tx through the
+    /// leaf `script_index` and paying `reward_address`. The challenger's `input` pays the
+    /// disprove fee and is signed with `key`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn build_disprove_tx(
+        &self,
+        assert_tx: &Transaction,
+        leaves: &[LeafScript],
+        script_index: usize,
+        internal_key: XOnlyPublicKey,
+        reward_address: &str,
+        key: &Auxiliary,
+        input: &FundingInput,
+    ) -> anyhow::Result<Transaction> {
+        let reward_address = Address::from_str(reward_address)
+            .map_err(|_| DisproveError::ChallengerAddressParseError(reward_address.to_string()))?
+            .require_network(self.params.network)?;
+
+        let fee_tx = self.chain.get_tx(&input.txid).await?;
+        let fee_prevout = TxOut {
+            value: fee_tx.tx_out(input.vout as usize)?.value,
+            script_pubkey: key.script_pk.clone(),
+        };
+        let fee_outpoint = OutPoint {
+            txid: input.txid,
+            vout: input.vout,
+        };
+
+        let DisproveScriptSpend { mut tx, prevouts } = DisproveScriptSpend::new(
+            &self.params,
+            assert_tx,
+            leaves,
+            script_index,
+            internal_key,
+            reward_address.script_pubkey(),
+            (fee_outpoint, fee_prevout),
+        )?;
+        key.sign_key_spend(&mut tx, 1, &prevouts)?;
+        Ok(tx)
+    }
+
+    /// Broadcasts `tx` once it passes the relay policy checks.
+    pub async fn broadcast(&self, tx: &Transaction) -> anyhow::Result<Txid> {
+        let prevouts = lookup_prevouts(self.chain, &[], &[tx]).await;
+        policy::ensure(tx, PolicyScope::Complete, |outpoint| {
+            prevouts.get(outpoint).cloned()
+        })?;
+        Ok(self.chain.broadcast(tx).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{
+        opcodes::{
+            all::{OP_CHECKSIG, OP_NIP, OP_SIZE},
+            OP_FALSE,
+        },
+        script::Builder,
+        secp256k1::Secp256k1,
+        Amount, CompressedPublicKey, Network, PrivateKey, ScriptBuf, TxOut, Witness,
+    };
+    use bitcoin_client::simulator::ChainSimulator;
+    use transactions::{
+        bitvm::{leaf_script_pubkey, leaf_spend_info},
+        graph::{multi_sig_script_pubkey, GraphSpec, TxGraph},
+    };
+    use types::{
+        bitvm::{WitnessRef, NUMS_INTERNAL_KEY},
+        committee::CommitteeVersionId,
+    };
+
+    use super::*;
+    use crate::test_utils::{mock_wallet, PRIVATE_KEY};
+
+    /// Runs a validator graph through stake, challenge, assert and disprove on the chain
+    /// simulator, which executes the disprove leaf script like a node does.
+    #[tokio::test]
+    async fn test_disprove_on_simulated_chain() {
+        let secp = Secp256k1::new();
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let operator_key = PrivateKey::from_slice(&[7; 32], Network::Regtest).unwrap();
+        let (operator_pubkey, _parity) = operator_key.inner.x_only_public_key(&secp);
+        let script_pubkey = ScriptBuf::new_p2tr(&secp, operator_pubkey, None);
+        // A multi sig script of the validator alone, so the presigned txs are complete.
+        let multi_sig_script = Builder::new()
+            .push_key(&operator_key.public_key(&secp))
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+
+        let chain = ChainSimulator::new();
+        let funding = chain.faucet(
+            script_pubkey.clone(),
+            params.stake_input_amount() + Amount::from_sat(50_000),
+        );
+        let graph = TxGraph::new(GraphSpec {
+            operator_key,
+            multi_sig_script: multi_sig_script.clone(),
+            params: params.clone(),
+            funding: (funding, chain.utxo(&funding).unwrap()),
+            change_script_pubkey: script_pubkey.clone(),
+        })
+        .unwrap();
+        chain.broadcast(&graph.stake_tx).await.unwrap();
+        chain.mine(1);
+
+        // The challenger takes connector A.
+        let mut challenge_tx = graph.challenge_tx.clone();
+        challenge_tx.output.push(TxOut {
+            value: params.dust_amount,
+            script_pubkey: script_pubkey.clone(),
+        });
+        chain.broadcast(&challenge_tx).await.unwrap();
+        chain.mine(1);
+
+        // Leaf 0 never succeeds, leaf 1 succeeds on the witness script the assert tx reveals.
+        let leaves = vec![
+            LeafScript {
+                script: Builder::new().push_opcode(OP_FALSE).into_script(),
+                witness: vec![],
+            },
+            LeafScript {
+                script: Builder::new()
+                    .push_opcode(OP_SIZE)
+                    .push_opcode(OP_NIP)
+                    .into_script(),
+                witness: vec![WitnessRef {
+                    input: 0,
+                    element: 1,
+                }],
+            },
+        ];
+        let internal_key = XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY).unwrap();
+        let mut assert_tx = graph.assert_tx.clone();
+        assert_tx.output = vec![TxOut {
+            value: params.stake_amount,
+            script_pubkey: multi_sig_script_pubkey(&multi_sig_script),
+        }];
+        assert_tx.output.insert(
+            params.connector_c_index as usize,
+            TxOut {
+                value: params.dust_amount,
+                script_pubkey: leaf_script_pubkey(&leaf_spend_info(&leaves, internal_key).unwrap()),
+            },
+        );
+        chain.broadcast(&assert_tx).await.unwrap();
+        chain.mine(1);
+
+        let assert_tx = chain.get_tx(&assert_tx.compute_txid()).await.unwrap();
+        assert_eq!(
+            find_disprovable_leaves(&assert_tx, &leaves).unwrap(),
+            vec![1]
+        );
+
+        // The challenger pays the disprove fee and takes connector C as the reward.
+        let (wallet, _handle) = mock_wallet().await;
+        let flow = DisproveFlow::new(&wallet, &chain, params.clone());
+        let challenger = Auxiliary::from_private_key(PRIVATE_KEY, Network::Regtest).unwrap();
+        let fee_coin = chain.faucet(challenger.script_pk.clone(), Amount::from_sat(10_000));
+        let fee_input = FundingInput {
+            txid: fee_coin.txid,
+            vout: fee_coin.vout,
+        };
+        let reward_key = CompressedPublicKey::from_private_key(&secp, &operator_key).unwrap();
+        let reward_address = Address::p2wpkh(&reward_key, Network::Regtest);
+        assert!(flow
+            .build_disprove_tx(
+                &assert_tx,
+                &leaves,
+                0,
+                internal_key,
+                &reward_address.to_string(),
+                &challenger,
+                &fee_input,
+            )
+            .await
+            .is_err());
+        let disprove_tx = flow
+            .build_disprove_tx(
+                &assert_tx,
+                &leaves,
+                1,
+                internal_key,
+                &reward_address.to_string(),
+                &challenger,
+                &fee_input,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            disprove_tx.output[0],
+            TxOut {
+                value: params.dust_amount,
+                script_pubkey: reward_address.script_pubkey(),
+            }
+        );
+
+        let mut tampered = disprove_tx.clone();
+        let mut witness = tampered.input[0].witness.to_vec();
+        witness[0].clear();
+        tampered.input[0].witness = Witness::from_slice(&witness);
+        assert!(
+            chain.broadcast(&tampered).await.is_err(),
+            "leaf script fails on an empty element"
+        );
+
+        let txid = flow.broadcast(&disprove_tx).await.unwrap();
+        chain.mine(1);
+        assert!(chain.get_tx_status(&txid).await.unwrap().confirmed);
+    }
+}
@@ -0,0 +1,461 @@
+//! The validator's tx graph as it stands on chain, exported as a Graphviz DOT or Mermaid
+//! diagram: which output of the stake and assert txs is spent, and by which tx.
+
+use std::{collections::HashMap, fmt::Write, str::FromStr};
+
+use bitcoin::{consensus::encode, Amount, OutPoint, Transaction, Txid};
+use bitcoin_client::{errors::ChainError, ChainSource};
+use types::{
+    error::FcliError, params::ProtocolParams, register::CircuitTx, transaction::TransactionType,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = FcliError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(Self::Dot),
+            "mermaid" => Ok(Self::Mermaid),
+            _ => Err(FcliError::InvalidArgument(format!(
+                "invalid graph format {s}, expected dot or mermaid"
+            ))),
+        }
+    }
+}
+
+/// Where a tx stands on chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxState {
+    NotBroadcast,
+    Mempool,
+    Confirmed(u32),
+}
+
+/// Where an output stands on chain, `NotCreated` while its tx is not broadcast.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputState {
+    NotCreated,
+    Unspent,
+    Spent(Txid),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphTx {
+    pub txid: Txid,
+    /// `None` for a tx found on chain spending an output of the graph, e.g. the challenge tx
+    /// the challenger completed.
+    pub tx_type: Option<TransactionType>,
+    /// Vk hash of the circuit of an assert or disprove tx.
+    pub circuit: Option<String>,
+    pub state: TxState,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphOutput {
+    pub outpoint: OutPoint,
+    pub name: String,
+    pub value: Amount,
+    pub state: OutputState,
+    /// Txs of the graph spending the output, only one of them can make it on chain.
+    pub spenders: Vec<Txid>,
+}
+
+/// The stake tx, its connectors and the txs spending them, with their on-chain status.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidatorGraph {
+    pub txs: Vec<GraphTx>,
+    pub outputs: Vec<GraphOutput>,
+}
+
+async fn tx_state<C: ChainSource>(chain: &C, txid: &Txid) -> Result<TxState, ChainError> {
+    match chain.get_tx_status(txid).await {
+        Ok(status) if status.confirmed => {
+            Ok(TxState::Confirmed(status.block_height.unwrap_or_default()))
+        }
+        Ok(_) => Ok(TxState::Mempool),
+        Err(ChainError::TxNotFound(_)) => Ok(TxState::NotBroadcast),
+        Err(e) => Err(e),
+    }
+}
+
+/// Name of output `vout` of a tx of type `tx_type` in the protocol's tx layout.
+fn output_name(params: &ProtocolParams, tx_type: Option<&TransactionType>, vout: u32) -> String {
+    let name = match tx_type {
+        Some(TransactionType::StakeTx) => match vout {
+            vout if vout == params.stake_value_index => "stake",
+            vout if vout == params.stake_timelock_index => "timelock",
+            vout if vout == params.connector_a_index => "connector A",
+            vout if vout == params.connector_b_index => "connector B",
+            _ => "change",
+        },
+        Some(TransactionType::AssertTx) if vout == params.connector_c_index => "connector C",
+        _ => return format!("output {vout}"),
+    };
+    name.to_string()
+}
+
+fn short_txid(txid: &Txid) -> String {
+    format!("{}…", &txid.to_string()[..8])
+}
+
+impl GraphTx {
+    fn label(&self, graph: &ValidatorGraph) -> String {
+        let name = match &self.tx_type {
+            Some(tx_type) => tx_type.to_string().replace('_', " "),
+            // Name a completed tx after the presigned tx spending the same output.
+            None => graph
+                .outputs
+                .iter()
+                .filter(|output| output.state == OutputState::Spent(self.txid))
+                .flat_map(|output| &output.spenders)
+                .find_map(|spender| graph.tx(spender)?.tx_type.clone())
+                .map(|tx_type| format!("completed {}", tx_type.to_string().replace('_', " ")))
+                .unwrap_or_else(|| "spending tx".to_string()),
+        };
+        let circuit = match &self.circuit {
+            Some(vk_hash) => format!(" ({})", &vk_hash[..vk_hash.len().min(8)]),
+            None => String::new(),
+        };
+        let state = match self.state {
+            TxState::NotBroadcast => "not broadcast".to_string(),
+            TxState::Mempool => "in mempool".to_string(),
+            TxState::Confirmed(height) => format!("confirmed at {height}"),
+        };
+        format!("{name}{circuit}\\n{}\\n{state}", short_txid(&self.txid))
+    }
+}
+
+impl GraphOutput {
+    fn label(&self) -> String {
+        let state = match self.state {
+            OutputState::NotCreated => "not created".to_string(),
+            OutputState::Unspent => "unspent".to_string(),
+            OutputState::Spent(txid) => format!("spent by {}", short_txid(&txid)),
+        };
+        format!("{}\\n{} sats\\n{state}", self.name, self.value.to_sat())
+    }
+
+    /// Spenders to draw an edge to: the presigned ones, and the tx spending it on chain.
+    fn edges(&self) -> Vec<(Txid, bool)> {
+        let spent_by = match self.state {
+            OutputState::Spent(txid) => Some(txid),
+            _ => None,
+        };
+        let mut edges = self
+            .spenders
+            .iter()
+            .map(|txid| (*txid, Some(*txid) == spent_by))
+            .collect::<Vec<_>>();
+        if let Some(txid) = spent_by.filter(|txid| !self.spenders.contains(txid)) {
+            edges.push((txid, true));
+        }
+        edges
+    }
+}
+
+impl ValidatorGraph {
+    /// Collects the graph of `txs`, the validator's stored txs and the committee's assert txs,
+    /// and looks up the status of every tx and output on `chain`. The graph holds every
+    /// output of the stake txs, connector C of the assert txs and every output a tx of the
+    /// graph spends.
+    pub async fn collect<C: ChainSource>(
+        chain: &C,
+        params: &ProtocolParams,
+        txs: &[CircuitTx],
+    ) -> anyhow::Result<Self> {
+        let mut graph = Self::default();
+        let mut raw_txs = HashMap::new();
+        for circuit_tx in txs {
+            let tx = encode::deserialize_hex::<Transaction>(&circuit_tx.tx_hex)?;
+            let txid = tx.compute_txid();
+            if raw_txs.insert(txid, tx).is_some() {
+                continue;
+            }
+            graph.txs.push(GraphTx {
+                txid,
+                tx_type: Some(circuit_tx.tx_type.clone()),
+                circuit: Some(circuit_tx.vk_hash.clone()).filter(|vk_hash| !vk_hash.is_empty()),
+                state: tx_state(chain, &txid).await?,
+            });
+        }
+
+        let mut outpoints = vec![];
+        for graph_tx in &graph.txs {
+            let tx = &raw_txs[&graph_tx.txid];
+            for vout in 0..tx.output.len() as u32 {
+                if graph_tx.tx_type == Some(TransactionType::StakeTx)
+                    || (graph_tx.tx_type == Some(TransactionType::AssertTx)
+                        && vout == params.connector_c_index)
+                {
+                    outpoints.push(OutPoint {
+                        txid: graph_tx.txid,
+                        vout,
+                    });
+                }
+            }
+            outpoints.extend(tx.input.iter().map(|input| input.previous_output));
+        }
+
+        for outpoint in outpoints {
+            let Some(creator) = graph.tx(&outpoint.txid) else {
+                continue;
+            };
+            let Some(txout) = raw_txs[&outpoint.txid].output.get(outpoint.vout as usize) else {
+                continue;
+            };
+            if graph.output(&outpoint).is_some() {
+                continue;
+            }
+
+            let state = match creator.state {
+                TxState::NotBroadcast => OutputState::NotCreated,
+                _ => match chain.get_outspend(&outpoint).await? {
+                    Some(txid) => OutputState::Spent(txid),
+                    None => OutputState::Unspent,
+                },
+            };
+            let spenders = graph
+                .txs
+                .iter()
+                .filter(|graph_tx| {
+                    raw_txs[&graph_tx.txid]
+                        .input
+                        .iter()
+                        .any(|input| input.previous_output == outpoint)
+                })
+                .map(|graph_tx| graph_tx.txid)
+                .collect();
+            graph.outputs.push(GraphOutput {
+                outpoint,
+                name: output_name(params, creator.tx_type.as_ref(), outpoint.vout),
+                value: txout.value,
+                state,
+                spenders,
+            });
+        }
+
+        // A disprove tx belongs to the circuit of the assert tx it spends.
+        for i in 0..graph.txs.len() {
+            if graph.txs[i].circuit.is_none() {
+                graph.txs[i].circuit = raw_txs[&graph.txs[i].txid]
+                    .input
+                    .iter()
+                    .find_map(|input| graph.tx(&input.previous_output.txid)?.circuit.clone());
+            }
+        }
+
+        let spent_by = graph
+            .outputs
+            .iter()
+            .filter_map(|output| match output.state {
+                OutputState::Spent(txid) => Some(txid),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for txid in spent_by {
+            if graph.tx(&txid).is_none() {
+                graph.txs.push(GraphTx {
+                    txid,
+                    tx_type: None,
+                    circuit: None,
+                    state: tx_state(chain, &txid).await?,
+                });
+            }
+        }
+
+        Ok(graph)
+    }
+
+    pub fn tx(&self, txid: &Txid) -> Option<&GraphTx> {
+        self.txs.iter().find(|tx| tx.txid == *txid)
+    }
+
+    pub fn output(&self, outpoint: &OutPoint) -> Option<&GraphOutput> {
+        self.outputs
+            .iter()
+            .find(|output| output.outpoint == *outpoint)
+    }
+
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Mermaid => self.to_mermaid(),
+        }
+    }
+
+    /// Graphviz DOT diagram: txs are boxes colored by their status, outputs are ellipses
+    /// colored by whether they are spent. A solid edge leads to the tx spending the output on
+    /// chain, a dashed one to a presigned tx that did not.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from(
+            "digraph validator {\n  rankdir=LR;\n  node [style=filled, fontname=monospace];\n",
+        );
+        for tx in &self.txs {
+            let color = match tx.state {
+                TxState::NotBroadcast => "lightgrey",
+                TxState::Mempool => "khaki",
+                TxState::Confirmed(_) => "palegreen",
+            };
+            let _ = writeln!(
+                dot,
+                "  \"{}\" [shape=box, label=\"{}\", fillcolor={color}];",
+                tx.txid,
+                tx.label(self)
+            );
+        }
+        for output in &self.outputs {
+            let color = match output.state {
+                OutputState::NotCreated => "white",
+                OutputState::Unspent => "lightblue",
+                OutputState::Spent(_) => "lightcoral",
+            };
+            let _ = writeln!(
+                dot,
+                "  \"{}\" [shape=ellipse, label=\"{}\", fillcolor={color}];",
+                output.outpoint,
+                output.label()
+            );
+            let _ = writeln!(
+                dot,
+                "  \"{}\" -> \"{}\";",
+                output.outpoint.txid, output.outpoint
+            );
+            for (txid, on_chain) in output.edges() {
+                let style = if on_chain { "bold" } else { "dashed" };
+                let _ = writeln!(
+                    dot,
+                    "  \"{}\" -> \"{}\" [style={style}];",
+                    output.outpoint, txid
+                );
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Mermaid flowchart with the same nodes, colors and edges as [`Self::to_dot`].
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+        let output_id = |outpoint: &OutPoint| format!("o{}_{}", outpoint.txid, outpoint.vout);
+        for tx in &self.txs {
+            let class = match tx.state {
+                TxState::NotBroadcast => "notBroadcast",
+                TxState::Mempool => "mempool",
+                TxState::Confirmed(_) => "confirmed",
+            };
+            let _ = writeln!(
+                mermaid,
+                "  t{}[\"{}\"]:::{class}",
+                tx.txid,
+                tx.label(self).replace("\\n", "<br/>")
+            );
+        }
+        for output in &self.outputs {
+            let class = match output.state {
+                OutputState::NotCreated => "notCreated",
+                OutputState::Unspent => "unspent",
+                OutputState::Spent(_) => "spent",
+            };
+            let id = output_id(&output.outpoint);
+            let _ = writeln!(
+                mermaid,
+                "  {id}([\"{}\"]):::{class}",
+                output.label().replace("\\n", "<br/>")
+            );
+            let _ = writeln!(mermaid, "  t{} --> {id}", output.outpoint.txid);
+            for (txid, on_chain) in output.edges() {
+                let arrow = if on_chain { "==>" } else { "-.->" };
+                let _ = writeln!(mermaid, "  {id} {arrow} t{txid}");
+            }
+        }
+        mermaid.push_str(
+            "  classDef notBroadcast fill:#d3d3d3\n  classDef mempool fill:#f0e68c\n  classDef confirmed fill:#98fb98\n  classDef notCreated fill:#ffffff\n  classDef unspent fill:#add8e6\n  classDef spent fill:#f08080\n",
+        );
+        mermaid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{
+        hashes::Hash, key::Secp256k1, opcodes::OP_TRUE, script::Builder, Network, PrivateKey,
+        ScriptBuf,
+    };
+    use bitcoin_client::simulator::ChainSimulator;
+    use transactions::graph::{GraphSpec, TxGraph};
+    use types::committee::CommitteeVersionId;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_collect_graph() {
+        let secp = Secp256k1::new();
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let operator_key = PrivateKey::from_slice(&[7; 32], Network::Regtest).unwrap();
+        let (internal_key, _parity) = operator_key.inner.x_only_public_key(&secp);
+        let script_pubkey = ScriptBuf::new_p2tr(&secp, internal_key, None);
+
+        let chain = ChainSimulator::new();
+        let funding = chain.faucet(script_pubkey.clone(), params.stake_input_amount());
+        let tx_graph = TxGraph::new(GraphSpec {
+            operator_key,
+            multi_sig_script: Builder::new().push_opcode(OP_TRUE).into_script(),
+            params: params.clone(),
+            funding: (funding, chain.utxo(&funding).unwrap()),
+            change_script_pubkey: script_pubkey,
+        })
+        .unwrap();
+        let stake_txid = chain.broadcast(&tx_graph.stake_tx).await.unwrap();
+        chain.mine(1);
+
+        let txs = [
+            (TransactionType::StakeTx, &tx_graph.stake_tx),
+            (TransactionType::AssertTx, &tx_graph.assert_tx),
+            (TransactionType::ChallengeTx, &tx_graph.challenge_tx),
+        ]
+        .map(|(tx_type, tx)| CircuitTx::new("", tx_type, &encode::serialize_hex(tx)));
+        let graph = ValidatorGraph::collect(&chain, &params, &txs)
+            .await
+            .unwrap();
+
+        assert_eq!(graph.txs.len(), 3);
+        assert_eq!(
+            graph.tx(&stake_txid).unwrap().state,
+            TxState::Confirmed(chain.height())
+        );
+        let assert_txid = tx_graph.assert_tx.compute_txid();
+        assert_eq!(graph.tx(&assert_txid).unwrap().state, TxState::NotBroadcast);
+
+        // The five stake tx outputs, the funding output is not part of the graph.
+        assert_eq!(graph.outputs.len(), 5);
+        let stake = graph
+            .output(&OutPoint {
+                txid: stake_txid,
+                vout: params.stake_value_index,
+            })
+            .unwrap();
+        assert_eq!(stake.name, "stake");
+        assert_eq!(stake.state, OutputState::Unspent);
+        assert_eq!(stake.spenders, vec![assert_txid]);
+        assert!(graph.output(&OutPoint::new(Txid::all_zeros(), 0)).is_none());
+
+        let dot = graph.to_dot();
+        assert!(dot.contains(&format!(
+            "\"{}:{}\" -> \"{}\" [style=dashed];",
+            stake_txid, params.stake_value_index, assert_txid
+        )));
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.contains(&format!(
+            "o{}_{} -.-> t{}",
+            stake_txid,
+            params.connector_a_index,
+            tx_graph.challenge_tx.compute_txid()
+        )));
+    }
+}
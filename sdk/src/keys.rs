@@ -0,0 +1,112 @@
+use std::str::FromStr;
+
+use bitcoin::{
+    bip32::Xpriv,
+    key::TapTweak,
+    secp256k1::{self, Keypair, Message},
+    sighash::{Prevouts, SighashCache},
+    taproot, Address, KnownHrp, Network, PrivateKey, PublicKey, ScriptBuf, TapSighashType,
+    Transaction, TxOut, Txid, Witness, XOnlyPublicKey,
+};
+use types::error::FcliError;
+
+/// Bitcoin key signing the stake or challenge txs, with the keys and p2tr script pubkey
+/// derived from it.
+#[derive(Clone, Debug)]
+pub struct Auxiliary {
+    pub private_key: PrivateKey,
+    pub pubkey: String,
+    pub internal_x_only_pubkey: String,
+    /// Key path p2tr script pubkey of the key, receives the change.
+    pub script_pk: ScriptBuf,
+}
+
+impl Auxiliary {
+    /// Parses a WIF or extended private key.
+    pub fn from_private_key(private_key: &str, network: Network) -> anyhow::Result<Self> {
+        let secp = secp256k1::Secp256k1::new();
+        let private_key = if let Ok(pk) = PrivateKey::from_wif(private_key) {
+            pk
+        } else if let Ok(pk) = Xpriv::from_str(private_key) {
+            pk.to_priv()
+        } else {
+            anyhow::bail!(FcliError::Key(
+                "expected a WIF or extended private key".to_string()
+            ))
+        };
+        let pubkey = PublicKey::from_private_key(&secp, &private_key).to_string();
+        let keypair = secp256k1::Keypair::from_secret_key(&secp, &private_key.inner);
+        let (internal_key, _parity) = XOnlyPublicKey::from_keypair(&keypair);
+        let address = Address::p2tr(&secp, internal_key, None, KnownHrp::from(network));
+
+        Ok(Self {
+            private_key,
+            pubkey,
+            internal_x_only_pubkey: internal_key.to_string(),
+            script_pk: address.script_pubkey(),
+        })
+    }
+
+    /// Signs input `index` of `tx` as a key path spend of the key's p2tr output, committing
+    /// to `prevouts`, the outputs every input of `tx` spends.
+    pub fn sign_key_spend(
+        &self,
+        tx: &mut Transaction,
+        index: usize,
+        prevouts: &[TxOut],
+    ) -> anyhow::Result<()> {
+        let secp = secp256k1::Secp256k1::new();
+        let sighash_type = TapSighashType::All;
+        let sighash = SighashCache::new(&*tx).taproot_key_spend_signature_hash(
+            index,
+            &Prevouts::All(prevouts),
+            sighash_type,
+        )?;
+
+        // Key path spend, sign with the tweaked key.
+        let keypair = Keypair::from_secret_key(&secp, &self.private_key.inner)
+            .tap_tweak(&secp, None)
+            .to_inner();
+        let signature = taproot::Signature {
+            signature: secp.sign_schnorr(&Message::from(sighash), &keypair),
+            sighash_type,
+        };
+        tx.input
+            .get_mut(index)
+            .ok_or_else(|| anyhow::anyhow!("tx misses input {}", index))?
+            .witness = Witness::p2tr_key_spend(&signature);
+        Ok(())
+    }
+}
+
+/// UTXO of the staker or challenger funding a stake or challenge tx.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FundingInput {
+    pub txid: Txid,
+    pub vout: u32,
+}
+
+impl FundingInput {
+    pub fn new(txid: &str, vout: u32) -> anyhow::Result<Self> {
+        let txid = Txid::from_str(txid)
+            .map_err(|_| FcliError::InvalidArgument(format!("invalid txid {}", txid)))?;
+        Ok(Self { txid, vout })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_private_key() {
+        let key = Auxiliary::from_private_key("tprv8jzau9CfsdkXMzqWFWSgu7f4z1vRk53yiqYqByfoakSLNFQ4bBuTsrUDLXtKHTPZhp161h49vEJr2zwN92G7ZHLZMFvome2U8GcAqDzVRhW", Network::Regtest).unwrap();
+        let wif = key.private_key.to_wif();
+        let from_wif = Auxiliary::from_private_key(&wif, Network::Regtest).unwrap();
+        assert_eq!(key.pubkey, from_wif.pubkey);
+        assert!(key.script_pk.is_p2tr());
+
+        assert!(Auxiliary::from_private_key("not a key", Network::Regtest).is_err());
+        assert!(FundingInput::new("not a txid", 0).is_err());
+    }
+}
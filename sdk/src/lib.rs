@@ -0,0 +1,73 @@
+//! Registration, challenge and disprove flows of the Fiamma committee, usable without the CLI.
+//!
+//! Every flow borrows a [`wallet::Wallet`] talking to the committee and a
+//! [`bitcoin_client::ChainSource`] for the bitcoin backend, and builds its txs with the
+//! [`types::params::ProtocolParams`] it was created with. The flows keep no local state,
+//! persisting requests and txs is up to the caller.
+
+use std::collections::HashMap;
+
+use bitcoin::{OutPoint, Transaction, TxOut};
+use bitcoin_client::ChainSource;
+use web3_decl::{error::Web3Error, jsonrpsee::core::ClientError};
+
+pub mod bundle;
+pub mod challenge;
+pub mod disprove;
+pub mod graph;
+pub mod keys;
+pub mod offline;
+pub mod registration;
+
+#[cfg(test)]
+mod test_utils;
+
+pub use challenge::ChallengeFlow;
+pub use disprove::DisproveFlow;
+pub use graph::{GraphFormat, ValidatorGraph};
+pub use keys::{Auxiliary, FundingInput};
+pub use registration::{PresignedTxs, RegistrationFlow};
+
+/// Committee error carried by the first JSON-RPC error in the chain of `err`, decoded back
+/// into its typed variant to branch on it.
+pub fn committee_error(err: &anyhow::Error) -> Option<Web3Error> {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<ClientError>())
+        .find_map(Web3Error::from_client_error)
+}
+
+/// Output `outpoint` spends, looked up in `txs` first and then on `chain`, for the policy
+/// checks of txs spending outputs that are not confirmed yet.
+pub(crate) async fn lookup_prevout<C: ChainSource>(
+    chain: &C,
+    txs: &[&Transaction],
+    outpoint: &OutPoint,
+) -> Option<TxOut> {
+    match txs.iter().find(|tx| tx.compute_txid() == outpoint.txid) {
+        Some(tx) => tx.output.get(outpoint.vout as usize).cloned(),
+        None => chain
+            .get_tx(&outpoint.txid)
+            .await
+            .ok()?
+            .output
+            .get(outpoint.vout as usize)
+            .cloned(),
+    }
+}
+
+/// Outputs the inputs of `spending` spend, looked up like [`lookup_prevout`] does. The policy
+/// checks look up prevouts synchronously, so they are fetched up front.
+pub(crate) async fn lookup_prevouts<C: ChainSource>(
+    chain: &C,
+    txs: &[&Transaction],
+    spending: &[&Transaction],
+) -> HashMap<OutPoint, TxOut> {
+    let mut prevouts = HashMap::new();
+    for input in spending.iter().flat_map(|tx| &tx.input) {
+        let outpoint = input.previous_output;
+        if let Some(prevout) = lookup_prevout(chain, txs, &outpoint).await {
+            prevouts.insert(outpoint, prevout);
+        }
+    }
+    prevouts
+}
@@ -0,0 +1,286 @@
+//! Presigning of registration bundles on a machine without network access, see
+//! [`types::offline`]. [`RegistrationFlow::prepare_start`] and
+//! [`RegistrationFlow::prepare_finish`] prepare the bundles online without keys,
+//! [`RegistrationFlow::submit`] submits the signed ones.
+//!
+//! [`RegistrationFlow::prepare_start`]: crate::RegistrationFlow::prepare_start
+//! [`RegistrationFlow::prepare_finish`]: crate::RegistrationFlow::prepare_finish
+//! [`RegistrationFlow::submit`]: crate::RegistrationFlow::submit
+
+use std::collections::HashMap;
+
+use bitcoin::{
+    consensus::encode, script::Instruction, secp256k1::Secp256k1, Amount, OutPoint, TxOut,
+};
+use transactions::graph::{GraphSpec, TxGraph};
+use types::{
+    bitvm::LeafScript,
+    error::BundleError,
+    offline::{
+        SignedRegistration, SignedStep, UnsignedRegistration, UnsignedStep,
+        REGISTRATION_BUNDLE_VERSION,
+    },
+    params::ProtocolParams,
+};
+
+use crate::{
+    keys::Auxiliary,
+    registration::{check_policy, create_disprove_txs, PresignedTxs},
+};
+
+/// Amounts the stake tx of a start bundle moves, for the signer to review before signing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StakeAmounts {
+    /// Locked to the multi sig script, the stake plus the assert fee.
+    pub stake: Amount,
+    pub fee: Amount,
+    /// Paid back to the signing key.
+    pub change: Amount,
+}
+
+/// Amounts the stake tx of the bundle moves, `None` for a finish bundle. Checks the bundle
+/// like [`sign`] does.
+pub fn stake_amounts(
+    bundle: &UnsignedRegistration,
+    key: &Auxiliary,
+) -> anyhow::Result<Option<StakeAmounts>> {
+    check_bundle(bundle, key)?;
+    let UnsignedStep::Start {
+        funding_outpoint,
+        funding_txout,
+    } = &bundle.step
+    else {
+        return Ok(None);
+    };
+
+    let graph = start_graph(bundle, key, funding_outpoint, funding_txout)?;
+    let outputs = &graph.stake_tx.output;
+    let output_value = outputs.iter().map(|txout| txout.value).sum::<Amount>();
+    Ok(Some(StakeAmounts {
+        stake: outputs[bundle.params.stake_value_index as usize].value,
+        fee: funding_txout.value - output_value,
+        // The change is the last output, after the stake and the connectors.
+        change: outputs.last().map_or(Amount::ZERO, |txout| txout.value),
+    }))
+}
+
+/// Presigns the txs of the bundle's step with `key`, which must be the key the bundle was
+/// prepared for. Needs no chain or committee access: the funding UTXO, the multi sig script
+/// and the assert txs all come with the bundle, `leaves` are the local leaf scripts of the
+/// circuits, see [`create_disprove_txs`].
+///
+/// The bundle is not trusted: its params must be the protocol params of its network and
+/// committee version, its multi sig script must include the signing key, and the funding
+/// UTXO must be locked to the signing key.
+pub fn sign(
+    bundle: &UnsignedRegistration,
+    key: &Auxiliary,
+    leaves: &HashMap<String, Vec<LeafScript>>,
+) -> anyhow::Result<SignedRegistration> {
+    check_bundle(bundle, key)?;
+
+    let step = match &bundle.step {
+        UnsignedStep::Start {
+            funding_outpoint,
+            funding_txout,
+        } => {
+            let graph = start_graph(bundle, key, funding_outpoint, funding_txout)?;
+            let txs = PresignedTxs {
+                stake_tx: graph.stake_tx.clone(),
+                assert_tx: graph.assert_tx.clone(),
+                challenge_tx: graph.challenge_tx.clone(),
+            };
+            // The graph knows every output its txs spend.
+            check_policy(&txs, |outpoint| {
+                if outpoint == funding_outpoint {
+                    Some(funding_txout.clone())
+                } else if outpoint.txid == graph.stake_txid() {
+                    txs.stake_tx.output.get(outpoint.vout as usize).cloned()
+                } else {
+                    None
+                }
+            })?;
+
+            SignedStep::Start {
+                stake_tx: encode::serialize_hex(&txs.stake_tx),
+                assert_tx: encode::serialize_hex(&txs.assert_tx),
+                challenge_tx: encode::serialize_hex(&txs.challenge_tx),
+            }
+        }
+        UnsignedStep::Finish { assert_txs } => {
+            if assert_txs.is_empty() {
+                anyhow::bail!(BundleError::NoAssertTxs(bundle.validator_key.clone()));
+            }
+            SignedStep::Finish {
+                disprove_txs: create_disprove_txs(
+                    key,
+                    &bundle.multi_sig_script,
+                    &bundle.params,
+                    assert_txs,
+                    leaves,
+                )?,
+            }
+        }
+    };
+
+    Ok(SignedRegistration {
+        version: REGISTRATION_BUNDLE_VERSION,
+        validator_key: bundle.validator_key.clone(),
+        public_key: bundle.public_key.clone(),
+        params: bundle.params.clone(),
+        step,
+    })
+}
+
+/// Checks the bundle against the signing key and the protocol params rebuilt offline.
+fn check_bundle(bundle: &UnsignedRegistration, key: &Auxiliary) -> anyhow::Result<()> {
+    check_bundle_version(bundle.version)?;
+    if key.pubkey != bundle.public_key {
+        anyhow::bail!(BundleError::KeyMismatch(
+            bundle.public_key.clone(),
+            key.pubkey.clone()
+        ));
+    }
+
+    let params = ProtocolParams::new(bundle.params.network, bundle.params.committee_version)?;
+    let mismatches = params.mismatches(&bundle.params);
+    if !mismatches.is_empty() {
+        anyhow::bail!(BundleError::ParamsMismatch(mismatches.join(", ")));
+    }
+
+    let public_key = key.private_key.public_key(&Secp256k1::new()).to_bytes();
+    let includes_key = bundle
+        .multi_sig_script
+        .instructions()
+        .any(|instruction| {
+            matches!(instruction, Ok(Instruction::PushBytes(bytes)) if bytes.as_bytes() == public_key)
+        });
+    if !includes_key {
+        anyhow::bail!(BundleError::MultiSigMismatch(key.pubkey.clone()));
+    }
+    Ok(())
+}
+
+/// Tx graph of a start bundle, whose funding UTXO must be locked to the signing key.
+fn start_graph(
+    bundle: &UnsignedRegistration,
+    key: &Auxiliary,
+    funding_outpoint: &OutPoint,
+    funding_txout: &TxOut,
+) -> anyhow::Result<TxGraph> {
+    if funding_txout.script_pubkey != key.script_pk {
+        anyhow::bail!(BundleError::FundingMismatch(funding_outpoint.to_string()));
+    }
+
+    TxGraph::new(GraphSpec {
+        operator_key: key.private_key,
+        multi_sig_script: bundle.multi_sig_script.clone(),
+        params: bundle.params.clone(),
+        funding: (*funding_outpoint, funding_txout.clone()),
+        change_script_pubkey: key.script_pk.clone(),
+    })
+}
+
+pub(crate) fn check_bundle_version(version: u32) -> Result<(), BundleError> {
+    if version != REGISTRATION_BUNDLE_VERSION {
+        return Err(BundleError::UnsupportedVersion(
+            version,
+            REGISTRATION_BUNDLE_VERSION,
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{script::Builder, Network};
+    use bitcoin_client::simulator::ChainSimulator;
+    use types::committee::CommitteeVersionId;
+
+    use super::*;
+    use crate::{
+        test_utils::{fund, mock_wallet, PRIVATE_KEY, VALIDATOR_KEY},
+        RegistrationFlow,
+    };
+
+    #[tokio::test]
+    async fn test_offline_registration() {
+        let (wallet, _handle) = mock_wallet().await;
+        let chain = ChainSimulator::new();
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let flow = RegistrationFlow::new(&wallet, &chain, params.clone());
+
+        let key = Auxiliary::from_private_key(PRIVATE_KEY, Network::Regtest).unwrap();
+        let input = fund(&chain, &key, &params);
+
+        let bundle = flow
+            .prepare_start(VALIDATOR_KEY, &key.pubkey, &input)
+            .await
+            .unwrap();
+        assert_eq!(
+            stake_amounts(&bundle, &key).unwrap(),
+            Some(StakeAmounts {
+                stake: params.stake_output_amount(),
+                fee: params.stake_fee,
+                change: Amount::from_sat(50_000),
+            })
+        );
+        let signed = sign(&bundle, &key, &HashMap::new()).unwrap();
+
+        // Signed offline, the txs spend the same outputs as the ones signed online.
+        let multi_sig_script = flow.multi_sig_script(&key).await.unwrap();
+        let txs = flow
+            .create_presign_transactions(&key, &input, &multi_sig_script)
+            .await
+            .unwrap();
+        let SignedStep::Start { stake_tx, .. } = &signed.step else {
+            panic!("expected a signed start step");
+        };
+        let stake_tx = encode::deserialize_hex::<bitcoin::Transaction>(stake_tx).unwrap();
+        assert_eq!(stake_tx.compute_txid(), txs.stake_tx.compute_txid());
+        assert_eq!(flow.submit(&signed).await.unwrap(), 1);
+
+        let secret_key = bitcoin::secp256k1::SecretKey::from_slice(&[1; 32]).unwrap();
+        let other_key = Auxiliary::from_private_key(
+            &bitcoin::PrivateKey::new(secret_key, Network::Regtest).to_wif(),
+            Network::Regtest,
+        )
+        .unwrap();
+        assert!(sign(&bundle, &other_key, &HashMap::new()).is_err());
+
+        let mut outdated = bundle.clone();
+        outdated.version += 1;
+        assert!(sign(&outdated, &key, &HashMap::new()).is_err());
+
+        let bundle_error = |bundle: &UnsignedRegistration| {
+            sign(bundle, &key, &HashMap::new())
+                .unwrap_err()
+                .downcast::<BundleError>()
+                .unwrap()
+        };
+        let mut cheap_stake = bundle.clone();
+        cheap_stake.params.stake_amount = Amount::from_sat(1_000);
+        assert!(matches!(
+            bundle_error(&cheap_stake),
+            BundleError::ParamsMismatch(_)
+        ));
+
+        let mut foreign_multi_sig = bundle.clone();
+        foreign_multi_sig.multi_sig_script = Builder::new()
+            .push_key(&other_key.private_key.public_key(&Secp256k1::new()))
+            .into_script();
+        assert!(matches!(
+            bundle_error(&foreign_multi_sig),
+            BundleError::MultiSigMismatch(_)
+        ));
+
+        let mut foreign_funding = bundle.clone();
+        if let UnsignedStep::Start { funding_txout, .. } = &mut foreign_funding.step {
+            funding_txout.script_pubkey = other_key.script_pk.clone();
+        }
+        assert!(matches!(
+            bundle_error(&foreign_funding),
+            BundleError::FundingMismatch(_)
+        ));
+    }
+}
@@ -0,0 +1,431 @@
+use std::{collections::HashMap, str::FromStr};
+
+use bitcoin::{
+    consensus::encode, OutPoint, PublicKey, ScriptBuf, Transaction, TxOut, XOnlyPublicKey,
+};
+use bitcoin_client::ChainSource;
+use transactions::{
+    graph::{self, GraphSpec, TxGraph},
+    policy::{self, PolicyScope},
+};
+use types::{
+    bitvm::{LeafScript, NUMS_INTERNAL_KEY},
+    error::{BundleError, FcliError, RegisterNodeError},
+    offline::{
+        SignedRegistration, SignedStep, UnsignedRegistration, UnsignedStep,
+        REGISTRATION_BUNDLE_VERSION,
+    },
+    params::ProtocolParams,
+    register::{CircuitTx, QueryAssertTxReq},
+    transaction::TransactionType,
+    FinishRegisterRequest, RegisterRequest,
+};
+use wallet::Wallet;
+use web3_decl::namespaces::committee::CommitteeNamespaceClient;
+
+use crate::{
+    keys::{Auxiliary, FundingInput},
+    lookup_prevouts,
+    offline::check_bundle_version,
+};
+
+/// Stake, assert and challenge txs a validator presigns to register.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PresignedTxs {
+    pub stake_tx: Transaction,
+    pub assert_tx: Transaction,
+    pub challenge_tx: Transaction,
+}
+
+/// Registers a validator with the committee: presigns the stake, assert and challenge txs,
+/// then the disprove txs of the assert txs the committee generated for every circuit.
+pub struct RegistrationFlow<'a, P, C> {
+    wallet: &'a Wallet<P>,
+    chain: &'a C,
+    params: ProtocolParams,
+    rotation: bool,
+}
+
+impl<'a, P, C> RegistrationFlow<'a, P, C>
+where
+    P: CommitteeNamespaceClient + Sync,
+    C: ChainSource,
+{
+    pub fn new(wallet: &'a Wallet<P>, chain: &'a C, params: ProtocolParams) -> Self {
+        Self {
+            wallet,
+            chain,
+            params,
+            rotation: false,
+        }
+    }
+
+    /// Flow presigning for the next committee version while the committee rotates, fails
+    /// when the committee is not rotating.
+    pub async fn for_next_committee(
+        wallet: &'a Wallet<P>,
+        chain: &'a C,
+        network: bitcoin::Network,
+    ) -> anyhow::Result<Self> {
+        let versions = wallet.get_committee_version().await?;
+        let Some(next) = versions.next else {
+            anyhow::bail!(RegisterNodeError::NoCommitteeRotation(format!(
+                "{:?}",
+                versions.current.version
+            )));
+        };
+
+        Ok(Self {
+            wallet,
+            chain,
+            params: ProtocolParams::new(network, next.version)?,
+            rotation: true,
+        })
+    }
+
+    pub fn params(&self) -> &ProtocolParams {
+        &self.params
+    }
+
+    /// Whether the flow presigns for the next committee version.
+    pub fn is_rotation(&self) -> bool {
+        self.rotation
+    }
+
+    /// Multi sig script of the committee and the validator, locking the stake.
+    pub async fn multi_sig_script(&self, key: &Auxiliary) -> anyhow::Result<ScriptBuf> {
+        self.multi_sig_script_of(&key.pubkey).await
+    }
+
+    /// Multi sig script of the committee and the validator's public key.
+    pub async fn multi_sig_script_of(&self, public_key: &str) -> anyhow::Result<ScriptBuf> {
+        if self.rotation {
+            self.wallet
+                .get_multi_sig_script_of_version(public_key, self.params.committee_version)
+                .await
+        } else {
+            self.wallet.get_multi_sig_script(public_key).await
+        }
+    }
+
+    /// Derives the validator's tx graph funded by `input`, the stake is locked to
+    /// `multi_sig_script` and the change goes back to the key.
+    pub async fn graph(
+        &self,
+        key: &Auxiliary,
+        input: &FundingInput,
+        multi_sig_script: &ScriptBuf,
+    ) -> anyhow::Result<TxGraph> {
+        let pre_tx = self.chain.get_tx(&input.txid).await?;
+        let utxo = pre_tx.tx_out(input.vout as usize)?;
+
+        TxGraph::new(GraphSpec {
+            operator_key: key.private_key,
+            multi_sig_script: multi_sig_script.clone(),
+            params: self.params.clone(),
+            funding: (
+                OutPoint {
+                    txid: input.txid,
+                    vout: input.vout,
+                },
+                utxo.clone(),
+            ),
+            change_script_pubkey: key.script_pk.clone(),
+        })
+    }
+
+    /// Builds the stake tx locking the stake to `multi_sig_script`, the change goes back to
+    /// the key.
+    pub async fn create_stake_tx(
+        &self,
+        key: &Auxiliary,
+        input: &FundingInput,
+        multi_sig_script: &ScriptBuf,
+    ) -> anyhow::Result<Transaction> {
+        Ok(self.graph(key, input, multi_sig_script).await?.stake_tx)
+    }
+
+    /// Builds the stake tx and the assert and challenge txs spending it.
+    pub async fn create_presign_transactions(
+        &self,
+        key: &Auxiliary,
+        input: &FundingInput,
+        multi_sig_script: &ScriptBuf,
+    ) -> anyhow::Result<PresignedTxs> {
+        let graph = self.graph(key, input, multi_sig_script).await?;
+        Ok(PresignedTxs {
+            stake_tx: graph.stake_tx,
+            assert_tx: graph.assert_tx,
+            challenge_tx: graph.challenge_tx,
+        })
+    }
+
+    /// Checks the presigned txs against the relay policy: the stake tx is broadcast as is,
+    /// the committee completes the assert and challenge txs.
+    pub async fn check_policy(&self, txs: &PresignedTxs) -> anyhow::Result<()> {
+        let prevouts = lookup_prevouts(
+            self.chain,
+            &[&txs.stake_tx],
+            &[&txs.stake_tx, &txs.assert_tx, &txs.challenge_tx],
+        )
+        .await;
+        check_policy(txs, |outpoint| prevouts.get(outpoint).cloned())
+    }
+
+    /// Checks the presigned txs and builds the `startRegister` request submitting them.
+    pub async fn start_request(
+        &self,
+        validator_key: &str,
+        key: &Auxiliary,
+        txs: &PresignedTxs,
+    ) -> anyhow::Result<RegisterRequest> {
+        self.check_policy(txs).await?;
+        Ok(RegisterRequest::new(
+            validator_key,
+            &key.pubkey,
+            &encode::serialize_hex(&txs.stake_tx),
+            &encode::serialize_hex(&txs.assert_tx),
+            &encode::serialize_hex(&txs.challenge_tx),
+        )
+        .with_committee_version(self.params.committee_version))
+    }
+
+    /// Submits the presigned txs, returns the register id.
+    pub async fn start(
+        &self,
+        validator_key: &str,
+        key: &Auxiliary,
+        txs: &PresignedTxs,
+    ) -> anyhow::Result<u32> {
+        let request = self.start_request(validator_key, key, txs).await?;
+        self.wallet.start_register(request).await
+    }
+
+    /// Assert txs the committee generated for the validator so far, one per circuit.
+    pub async fn assert_txs(&self, validator_key: &str) -> anyhow::Result<Vec<CircuitTx>> {
+        self.wallet
+            .get_committee_assert_txs(QueryAssertTxReq::new(validator_key))
+            .await
+    }
+
+    /// Builds the disprove tx spending connector C of every assert tx, see
+    /// [`create_disprove_txs`] for `leaves`.
+    pub fn create_disprove_txs(
+        &self,
+        key: &Auxiliary,
+        multi_sig_script: &ScriptBuf,
+        assert_txs: &[CircuitTx],
+        leaves: &HashMap<String, Vec<LeafScript>>,
+    ) -> anyhow::Result<Vec<CircuitTx>> {
+        create_disprove_txs(key, multi_sig_script, &self.params, assert_txs, leaves)
+    }
+
+    /// Presigns the disprove txs of the assert txs and builds the `finishRegister` request
+    /// submitting them.
+    pub fn finish_request(
+        &self,
+        validator_key: &str,
+        key: &Auxiliary,
+        multi_sig_script: &ScriptBuf,
+        assert_txs: &[CircuitTx],
+        leaves: &HashMap<String, Vec<LeafScript>>,
+    ) -> anyhow::Result<(FinishRegisterRequest, Vec<CircuitTx>)> {
+        let disprove_txs = self.create_disprove_txs(key, multi_sig_script, assert_txs, leaves)?;
+        let request = FinishRegisterRequest::new(validator_key, &disprove_txs);
+        Ok((request, disprove_txs))
+    }
+
+    /// Presigns and submits the disprove txs of the assert txs, returns the register id and
+    /// the disprove txs.
+    pub async fn finish(
+        &self,
+        validator_key: &str,
+        key: &Auxiliary,
+        multi_sig_script: &ScriptBuf,
+        assert_txs: &[CircuitTx],
+        leaves: &HashMap<String, Vec<LeafScript>>,
+    ) -> anyhow::Result<(u32, Vec<CircuitTx>)> {
+        let (request, disprove_txs) =
+            self.finish_request(validator_key, key, multi_sig_script, assert_txs, leaves)?;
+        let register_id = self.wallet.finish_register(request).await?;
+        Ok((register_id, disprove_txs))
+    }
+
+    /// Bundle of the start step for an offline machine to presign, funded by `input`.
+    pub async fn prepare_start(
+        &self,
+        validator_key: &str,
+        public_key: &str,
+        input: &FundingInput,
+    ) -> anyhow::Result<UnsignedRegistration> {
+        let pre_tx = self.chain.get_tx(&input.txid).await?;
+        let funding_txout = pre_tx.tx_out(input.vout as usize)?.clone();
+        let step = UnsignedStep::Start {
+            funding_outpoint: OutPoint {
+                txid: input.txid,
+                vout: input.vout,
+            },
+            funding_txout,
+        };
+        self.prepare(validator_key, public_key, step).await
+    }
+
+    /// Bundle of the finish step for an offline machine to presign, with the assert txs the
+    /// committee generated so far.
+    pub async fn prepare_finish(
+        &self,
+        validator_key: &str,
+        public_key: &str,
+    ) -> anyhow::Result<UnsignedRegistration> {
+        let assert_txs = self.assert_txs(validator_key).await?;
+        if assert_txs.is_empty() {
+            anyhow::bail!(BundleError::NoAssertTxs(validator_key.to_string()));
+        }
+        let step = UnsignedStep::Finish { assert_txs };
+        self.prepare(validator_key, public_key, step).await
+    }
+
+    async fn prepare(
+        &self,
+        validator_key: &str,
+        public_key: &str,
+        step: UnsignedStep,
+    ) -> anyhow::Result<UnsignedRegistration> {
+        // Normalize the key, the signing key is compared with it.
+        let public_key = PublicKey::from_str(public_key)
+            .map_err(|e| FcliError::Key(format!("invalid public key {public_key}: {e}")))?
+            .to_string();
+        let multi_sig_script = self.multi_sig_script_of(&public_key).await?;
+        Ok(UnsignedRegistration {
+            version: REGISTRATION_BUNDLE_VERSION,
+            validator_key: validator_key.to_string(),
+            public_key,
+            params: self.params.clone(),
+            multi_sig_script,
+            step,
+        })
+    }
+
+    /// Submits a bundle presigned offline, returns the register id. The request is sent for
+    /// the committee version of the bundle.
+    pub async fn submit(&self, bundle: &SignedRegistration) -> anyhow::Result<u32> {
+        check_bundle_version(bundle.version)?;
+        if bundle.params.network != self.params.network {
+            anyhow::bail!(BundleError::NetworkMismatch(
+                bundle.params.network.to_string(),
+                self.params.network.to_string()
+            ));
+        }
+
+        match &bundle.step {
+            SignedStep::Start {
+                stake_tx,
+                assert_tx,
+                challenge_tx,
+            } => {
+                let request = RegisterRequest::new(
+                    &bundle.validator_key,
+                    &bundle.public_key,
+                    stake_tx,
+                    assert_tx,
+                    challenge_tx,
+                )
+                .with_committee_version(bundle.params.committee_version);
+                self.wallet.start_register(request).await
+            }
+            SignedStep::Finish { disprove_txs } => {
+                let request = FinishRegisterRequest::new(&bundle.validator_key, disprove_txs);
+                self.wallet.finish_register(request).await
+            }
+        }
+    }
+}
+
+/// Checks the presigned txs against the relay policy, `spent` looks up the outputs they
+/// spend.
+pub fn check_policy<S>(txs: &PresignedTxs, mut spent: S) -> anyhow::Result<()>
+where
+    S: FnMut(&OutPoint) -> Option<TxOut>,
+{
+    policy::ensure(&txs.stake_tx, PolicyScope::Complete, &mut spent)?;
+    policy::ensure(&txs.assert_tx, PolicyScope::Presigned, &mut spent)?;
+    policy::ensure(&txs.challenge_tx, PolicyScope::Presigned, &mut spent)?;
+    Ok(())
+}
+
+/// Builds the disprove tx spending connector C of every assert tx. `leaves` holds the leaf
+/// scripts of the circuits by verify key hash: connector C of a circuit in it must commit to
+/// them, connector C of the other circuits must be locked to the multi sig script.
+/// Every disprove tx is checked against the relay policy rules of presigned txs.
+pub fn create_disprove_txs(
+    key: &Auxiliary,
+    multi_sig_script: &ScriptBuf,
+    params: &ProtocolParams,
+    assert_txs: &[CircuitTx],
+    leaves: &HashMap<String, Vec<LeafScript>>,
+) -> anyhow::Result<Vec<CircuitTx>> {
+    let internal_key = XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY)?;
+    assert_txs
+        .iter()
+        .map(|circuit_assert_tx| {
+            let assert_tx = encode::deserialize_hex::<Transaction>(&circuit_assert_tx.tx_hex)?;
+            let circuit_leaves = leaves
+                .get(&circuit_assert_tx.vk_hash)
+                .map(|leaves| (leaves.as_slice(), internal_key));
+            let disprove_tx = graph::disprove_tx(
+                &key.private_key,
+                multi_sig_script,
+                params,
+                &assert_tx,
+                circuit_leaves,
+            )?;
+            // Connector C of the assert tx is the only output the disprove tx spends.
+            policy::ensure(&disprove_tx, PolicyScope::Presigned, |outpoint| {
+                (outpoint.txid == assert_tx.compute_txid())
+                    .then(|| assert_tx.output.get(outpoint.vout as usize).cloned())
+                    .flatten()
+            })?;
+
+            Ok(CircuitTx {
+                vk_hash: circuit_assert_tx.vk_hash.clone(),
+                tx_type: TransactionType::DisproveTx,
+                tx_hex: encode::serialize_hex(&disprove_tx),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::Network;
+    use bitcoin_client::simulator::ChainSimulator;
+    use types::committee::CommitteeVersionId;
+
+    use super::*;
+    use crate::test_utils::{fund, mock_wallet, PRIVATE_KEY, VALIDATOR_KEY};
+
+    #[tokio::test]
+    async fn test_stake_tx_on_simulated_chain() {
+        let (wallet, _handle) = mock_wallet().await;
+        let chain = ChainSimulator::new();
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let flow = RegistrationFlow::new(&wallet, &chain, params.clone());
+
+        let key = Auxiliary::from_private_key(PRIVATE_KEY, Network::Regtest).unwrap();
+        let input = fund(&chain, &key, &params);
+
+        let multi_sig_script = flow.multi_sig_script(&key).await.unwrap();
+        let txs = flow
+            .create_presign_transactions(&key, &input, &multi_sig_script)
+            .await
+            .unwrap();
+        let stake_txid = chain.broadcast(&txs.stake_tx).await.unwrap();
+        assert_eq!(txs.assert_tx.input[0].previous_output.txid, stake_txid);
+        assert_eq!(txs.challenge_tx.input[0].previous_output.txid, stake_txid);
+        chain.mine(1);
+        assert!(chain.get_tx_status(&stake_txid).await.unwrap().confirmed);
+
+        let register_id = flow.start(VALIDATOR_KEY, &key, &txs).await.unwrap();
+        assert_eq!(register_id, 1);
+    }
+}
@@ -0,0 +1,47 @@
+//! Mock committee and simulated chain the flow tests run against.
+
+use bitcoin::{Amount, Network};
+use bitcoin_client::simulator::ChainSimulator;
+use mock_committee::MockCommittee;
+use types::params::ProtocolParams;
+use wallet::{config::ClientConfig, signer::Signer, Wallet};
+use web3_decl::jsonrpsee::{http_client::HttpClient, server::ServerHandle};
+
+use crate::{Auxiliary, FundingInput};
+
+pub(crate) const PRIVATE_KEY: &str = "tprv8jzau9CfsdkXMzqWFWSgu7f4z1vRk53yiqYqByfoakSLNFQ4bBuTsrUDLXtKHTPZhp161h49vEJr2zwN92G7ZHLZMFvome2U8GcAqDzVRhW";
+pub(crate) const VALIDATOR_KEY: &str = "fiammavaloper19fldhw0awjv2ag7dz0lr3d4qmnfkxz69vukt7x";
+
+/// Wallet of a regtest mock committee served on a free local port, the committee stops once
+/// the handle is dropped.
+pub(crate) async fn mock_wallet() -> (Wallet<HttpClient>, ServerHandle) {
+    let (addr, handle) = MockCommittee::new(Network::Regtest)
+        .unwrap()
+        .serve("127.0.0.1:0".parse().unwrap())
+        .await
+        .unwrap();
+    let wallet = Wallet::with_http_client(
+        &format!("http://{addr}"),
+        Signer::new(),
+        ClientConfig::default(),
+    )
+    .unwrap();
+    (wallet, handle)
+}
+
+/// Coin of `key` on `chain` covering the stake, the assert fee and the stake tx fee, with
+/// 50 000 sats of change.
+pub(crate) fn fund(
+    chain: &ChainSimulator,
+    key: &Auxiliary,
+    params: &ProtocolParams,
+) -> FundingInput {
+    let funding = chain.faucet(
+        key.script_pk.clone(),
+        params.stake_input_amount() + Amount::from_sat(50_000),
+    );
+    FundingInput {
+        txid: funding.txid,
+        vout: funding.vout,
+    }
+}
@@ -0,0 +1,140 @@
+use anyhow::Context;
+use rusqlite::{params, OptionalExtension, Row};
+use types::challenge::{ChallengeInfoRes, ChallengeRequest};
+
+use crate::Storage;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChallengeRecord {
+    pub network: String,
+    pub request: ChallengeRequest,
+    pub circuit_type: String,
+    pub vk_path: Option<String>,
+    pub challenge_id: Option<u32>,
+    pub created_at: String,
+}
+
+impl ChallengeRecord {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let request: String = row.get(1)?;
+        let request = serde_json::from_str(&request).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e))
+        })?;
+        Ok(Self {
+            network: row.get(0)?,
+            request,
+            circuit_type: row.get(2)?,
+            vk_path: row.get(3)?,
+            challenge_id: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    }
+}
+
+impl Storage {
+    pub fn save_challenge(
+        &self,
+        network: &str,
+        request: &ChallengeRequest,
+        circuit_type: &str,
+        vk_path: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let raw_request = serde_json::to_string(request)?;
+        self.conn
+            .execute(
+                "INSERT INTO challenges (network, proof_id, vk_hash, circuit_type, vk_path, request)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (network, proof_id) DO UPDATE SET
+                    vk_hash = excluded.vk_hash,
+                    circuit_type = excluded.circuit_type,
+                    vk_path = COALESCE(excluded.vk_path, challenges.vk_path),
+                    request = excluded.request",
+                params![
+                    network,
+                    request.proof_id,
+                    request.vk_hash,
+                    circuit_type,
+                    vk_path,
+                    raw_request
+                ],
+            )
+            .context("Failed to save challenge")?;
+        Ok(())
+    }
+
+    pub fn set_challenge_id(
+        &self,
+        network: &str,
+        proof_id: &str,
+        challenge_id: u32,
+    ) -> anyhow::Result<()> {
+        self.conn
+            .execute(
+                "UPDATE challenges SET challenge_id = ?3 WHERE network = ?1 AND proof_id = ?2",
+                params![network, proof_id, challenge_id],
+            )
+            .context("Failed to update challenge")?;
+        Ok(())
+    }
+
+    pub fn challenge(
+        &self,
+        network: &str,
+        proof_id: &str,
+    ) -> anyhow::Result<Option<ChallengeRecord>> {
+        self.conn
+            .query_row(
+                "SELECT network, request, circuit_type, vk_path, challenge_id, created_at
+                 FROM challenges WHERE network = ?1 AND proof_id = ?2",
+                params![network, proof_id],
+                ChallengeRecord::from_row,
+            )
+            .optional()
+            .context("Failed to query challenge")
+    }
+
+    /// Stores a snapshot of the challenge info, skipping it when nothing changed since the last one.
+    pub fn save_challenge_info(
+        &self,
+        network: &str,
+        info: &ChallengeInfoRes,
+    ) -> anyhow::Result<()> {
+        if self
+            .latest_challenge_info(network, &info.proof_id)?
+            .as_ref()
+            == Some(info)
+        {
+            return Ok(());
+        }
+
+        let raw_info = serde_json::to_string(info)?;
+        self.conn
+            .execute(
+                "INSERT INTO challenge_infos (network, proof_id, status, info) VALUES (?1, ?2, ?3, ?4)",
+                params![network, info.proof_id, info.status.to_string(), raw_info],
+            )
+            .context("Failed to save challenge info")?;
+        Ok(())
+    }
+
+    pub fn latest_challenge_info(
+        &self,
+        network: &str,
+        proof_id: &str,
+    ) -> anyhow::Result<Option<ChallengeInfoRes>> {
+        let raw_info: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT info FROM challenge_infos WHERE network = ?1 AND proof_id = ?2
+                 ORDER BY id DESC LIMIT 1",
+                params![network, proof_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query challenge info")?;
+
+        raw_info
+            .map(|info| serde_json::from_str(&info).context("Invalid challenge info in database"))
+            .transpose()
+    }
+}
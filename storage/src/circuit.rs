@@ -0,0 +1,187 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use types::{
+    circuit::{CircuitInfo, CircuitType},
+    error::CircuitError,
+    file::{get_circuit_dir, write_file_atomic, CIRCUITS_DIR_NAME, VK_FILE_NAME},
+};
+
+const INDEX_FILE_NAME: &str = "index.json";
+
+/// Index entry of a verify key stored in the circuit store.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CircuitEntry {
+    pub vk_hash: String,
+    pub circuit_type: CircuitType,
+    /// Size of the verify key file in bytes.
+    pub size: u64,
+    /// Hex SHA-256 of the verify key file.
+    pub sha256: String,
+    /// Where the verify key was imported from.
+    pub source: String,
+}
+
+/// Outcome of checking a stored verify key against its index entry.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CircuitCheck {
+    Ok,
+    Missing,
+    SizeMismatch { expected: u64, actual: u64 },
+    Sha256Mismatch,
+    HashMismatch,
+}
+
+/// Verify keys and BitVM2 scripts of circuits, under `circuits-metadata` in the data
+/// directory and indexed by vk hash in `index.json`.
+pub struct CircuitStore {
+    root: PathBuf,
+}
+
+impl CircuitStore {
+    pub fn open(data_dir: &Path) -> anyhow::Result<Self> {
+        let root = data_dir.join(CIRCUITS_DIR_NAME);
+        fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create circuit store {}", root.display()))?;
+        Ok(Self { root })
+    }
+
+    pub fn vk_path(&self, entry: &CircuitEntry) -> PathBuf {
+        get_circuit_dir(&self.root, &entry.vk_hash, entry.circuit_type.clone()).join(VK_FILE_NAME)
+    }
+
+    pub fn list(&self) -> anyhow::Result<Vec<CircuitEntry>> {
+        let path = self.root.join(INDEX_FILE_NAME);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let data = fs::read(&path)?;
+        serde_json::from_slice(&data)
+            .with_context(|| format!("Failed to parse circuit index {}", path.display()))
+    }
+
+    fn save_index(&self, entries: &[CircuitEntry]) -> anyhow::Result<()> {
+        let data = serde_json::to_vec_pretty(entries)?;
+        write_file_atomic(&self.root.join(INDEX_FILE_NAME), &data)
+    }
+
+    pub fn entry(&self, vk_hash: &str) -> anyhow::Result<Option<CircuitEntry>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .find(|entry| entry.vk_hash == vk_hash))
+    }
+
+    /// Stores the verify key and indexes it, replacing a previous import of the same key.
+    pub fn import(
+        &self,
+        vk: &[u8],
+        circuit_type: CircuitType,
+        source: &str,
+    ) -> anyhow::Result<CircuitEntry> {
+        let entry = CircuitEntry {
+            vk_hash: CircuitInfo::hash(vk, circuit_type.clone()),
+            circuit_type,
+            size: vk.len() as u64,
+            sha256: hex::encode(Sha256::digest(vk)),
+            source: source.to_string(),
+        };
+        write_file_atomic(&self.vk_path(&entry), vk)?;
+
+        let mut entries = self.list()?;
+        entries.retain(|e| e.vk_hash != entry.vk_hash);
+        entries.push(entry.clone());
+        self.save_index(&entries)?;
+
+        Ok(entry)
+    }
+
+    /// Reads the verify key stored under `vk_hash`.
+    pub fn read_vk(&self, vk_hash: &str) -> anyhow::Result<(Vec<u8>, CircuitEntry)> {
+        let entry = self
+            .entry(vk_hash)?
+            .ok_or_else(|| CircuitError::CircuitNotExists(vk_hash.to_string()))?;
+        let vk = fs::read(self.vk_path(&entry))?;
+        Ok((vk, entry))
+    }
+
+    /// Removes the circuit directory, including its scripts, and its index entry.
+    pub fn remove(&self, vk_hash: &str) -> anyhow::Result<CircuitEntry> {
+        let mut entries = self.list()?;
+        let position = entries
+            .iter()
+            .position(|entry| entry.vk_hash == vk_hash)
+            .ok_or_else(|| CircuitError::CircuitNotExists(vk_hash.to_string()))?;
+        let entry = entries.remove(position);
+        self.save_index(&entries)?;
+
+        let dir = get_circuit_dir(&self.root, &entry.vk_hash, entry.circuit_type.clone());
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        Ok(entry)
+    }
+
+    /// Checks that the stored verify key still matches its index entry and vk hash.
+    pub fn check(&self, entry: &CircuitEntry) -> anyhow::Result<CircuitCheck> {
+        let path = self.vk_path(entry);
+        if !path.exists() {
+            return Ok(CircuitCheck::Missing);
+        }
+
+        let vk = fs::read(path)?;
+        if vk.len() as u64 != entry.size {
+            return Ok(CircuitCheck::SizeMismatch {
+                expected: entry.size,
+                actual: vk.len() as u64,
+            });
+        }
+        if hex::encode(Sha256::digest(&vk)) != entry.sha256 {
+            return Ok(CircuitCheck::Sha256Mismatch);
+        }
+        if CircuitInfo::hash(&vk, entry.circuit_type.clone()) != entry.vk_hash {
+            return Ok(CircuitCheck::HashMismatch);
+        }
+        Ok(CircuitCheck::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CircuitStore::open(dir.path()).unwrap();
+
+        let entry = store
+            .import(b"verify key", CircuitType::Groth16, "vk.bitvm")
+            .unwrap();
+        assert_eq!(store.list().unwrap(), vec![entry.clone()]);
+        assert_eq!(store.check(&entry).unwrap(), CircuitCheck::Ok);
+
+        let (vk, stored) = store.read_vk(&entry.vk_hash).unwrap();
+        assert_eq!(vk, b"verify key");
+        assert_eq!(stored, entry);
+
+        // Re-importing the same key keeps a single entry.
+        store
+            .import(b"verify key", CircuitType::Groth16, "other.bitvm")
+            .unwrap();
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        fs::write(store.vk_path(&entry), b"verify kez").unwrap();
+        assert_eq!(store.check(&entry).unwrap(), CircuitCheck::Sha256Mismatch);
+
+        store.remove(&entry.vk_hash).unwrap();
+        assert!(store.list().unwrap().is_empty());
+        assert_eq!(store.check(&entry).unwrap(), CircuitCheck::Missing);
+        assert!(store.remove(&entry.vk_hash).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Context;
+use rusqlite::params;
+
+use crate::Storage;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationRecord {
+    pub id: i64,
+    pub network: String,
+    pub command: String,
+    pub subject: String,
+    pub detail: String,
+    pub created_at: String,
+}
+
+impl Storage {
+    pub fn record_operation(
+        &self,
+        network: &str,
+        command: &str,
+        subject: &str,
+        detail: &str,
+    ) -> anyhow::Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO operations (network, command, subject, detail) VALUES (?1, ?2, ?3, ?4)",
+                params![network, command, subject, detail],
+            )
+            .context("Failed to record operation")?;
+        Ok(())
+    }
+
+    /// Recorded operations, newest first.
+    pub fn operations(
+        &self,
+        network: Option<&str>,
+        limit: u32,
+    ) -> anyhow::Result<Vec<OperationRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, network, command, subject, detail, created_at FROM operations
+             WHERE ?1 IS NULL OR network = ?1 ORDER BY id DESC LIMIT ?2",
+        )?;
+        let operations = stmt
+            .query_map(params![network, limit], |row| {
+                Ok(OperationRecord {
+                    id: row.get(0)?,
+                    network: row.get(1)?,
+                    command: row.get(2)?,
+                    subject: row.get(3)?,
+                    detail: row.get(4)?,
+                    created_at: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to query operations")?;
+        Ok(operations)
+    }
+}
@@ -0,0 +1,249 @@
+use std::path::Path;
+
+use anyhow::Context;
+use rusqlite::Connection;
+
+pub mod challenge;
+pub mod circuit;
+pub mod history;
+pub mod register;
+pub mod transaction;
+
+const DB_FILE_NAME: &str = "fcli.sqlite";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS registrations (
+    network TEXT NOT NULL,
+    validator_key TEXT NOT NULL,
+    public_key TEXT NOT NULL,
+    register_id INTEGER NOT NULL,
+    finished INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (network, validator_key)
+);
+
+CREATE TABLE IF NOT EXISTS rotations (
+    network TEXT NOT NULL,
+    validator_key TEXT NOT NULL,
+    committee_version INTEGER NOT NULL,
+    public_key TEXT NOT NULL,
+    register_id INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (network, validator_key)
+);
+
+CREATE TABLE IF NOT EXISTS challenges (
+    network TEXT NOT NULL,
+    proof_id TEXT NOT NULL,
+    vk_hash TEXT NOT NULL,
+    circuit_type TEXT NOT NULL,
+    vk_path TEXT,
+    challenge_id INTEGER,
+    request TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (network, proof_id)
+);
+
+CREATE TABLE IF NOT EXISTS challenge_infos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    network TEXT NOT NULL,
+    proof_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    info TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS transactions (
+    tx_id BLOB PRIMARY KEY,
+    tx_type INTEGER NOT NULL,
+    data BLOB NOT NULL,
+    ext_info TEXT,
+    register_id INTEGER,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS operations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    network TEXT NOT NULL,
+    command TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    detail TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+";
+
+/// Local persistent state of fcli, kept in a SQLite database under the data directory.
+pub struct Storage {
+    conn: Connection,
+}
+
+impl Storage {
+    pub fn open(data_dir: &Path) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(data_dir)
+            .with_context(|| format!("Failed to create data dir {}", data_dir.display()))?;
+        let conn = Connection::open(data_dir.join(DB_FILE_NAME))
+            .context("Failed to open local state database")?;
+        Self::init(conn)
+    }
+
+    pub fn open_in_memory() -> anyhow::Result<Self> {
+        let conn = Connection::open_in_memory().context("Failed to open in-memory database")?;
+        Self::init(conn)
+    }
+
+    fn init(conn: Connection) -> anyhow::Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .context("Failed to migrate local state database")?;
+        Ok(Self { conn })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use types::{
+        challenge::{ChallengeInfoRes, ChallengeRequest, ChallengeStatus},
+        transaction::{FiammaTransaction, TransactionType},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_registration_roundtrip() {
+        let storage = Storage::open_in_memory().unwrap();
+        storage
+            .save_registration("local", "fiammavaloper1", "02ff", 7)
+            .unwrap();
+        storage
+            .finish_registration("local", "fiammavaloper1", "02ff", 7)
+            .unwrap();
+
+        let record = storage.latest_registration("local").unwrap().unwrap();
+        assert_eq!(record.register_id, 7);
+        assert!(record.finished);
+        assert!(storage.latest_registration("testnet").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_finish_registration_without_start() {
+        let storage = Storage::open_in_memory().unwrap();
+        storage
+            .finish_registration("local", "fiammavaloper1", "02ff", 9)
+            .unwrap();
+
+        let record = storage
+            .registration("local", "fiammavaloper1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.public_key, "02ff");
+        assert_eq!(record.register_id, 9);
+        assert!(record.finished);
+    }
+
+    #[test]
+    fn test_rotation_keeps_live_registration() {
+        let storage = Storage::open_in_memory().unwrap();
+        storage
+            .save_registration("local", "fiammavaloper1", "02ff", 7)
+            .unwrap();
+        storage
+            .finish_registration("local", "fiammavaloper1", "02ff", 7)
+            .unwrap();
+        storage
+            .save_rotation("local", "fiammavaloper1", 1, "02ee", 8)
+            .unwrap();
+
+        let live = storage
+            .registration("local", "fiammavaloper1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(live.register_id, 7);
+        assert!(live.finished);
+        let rotation = storage
+            .rotation("local", "fiammavaloper1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(rotation.committee_version, 1);
+        assert_eq!(rotation.register_id, 8);
+
+        storage
+            .finish_rotation("local", "fiammavaloper1", "02ee", 8)
+            .unwrap();
+        let live = storage
+            .registration("local", "fiammavaloper1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(live.public_key, "02ee");
+        assert_eq!(live.register_id, 8);
+        assert!(live.finished);
+        assert!(storage
+            .rotation("local", "fiammavaloper1")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_challenge_info_snapshots() {
+        let storage = Storage::open_in_memory().unwrap();
+        let request = ChallengeRequest::new("proof", "vk_hash");
+        storage
+            .save_challenge("local", &request, "groth16", Some("vk.bitvm"))
+            .unwrap();
+        storage
+            .save_challenge("local", &request, "groth16", None)
+            .unwrap();
+
+        let record = storage.challenge("local", "proof").unwrap().unwrap();
+        assert_eq!(record.request, request);
+        assert_eq!(record.vk_path.as_deref(), Some("vk.bitvm"));
+
+        let info = ChallengeInfoRes {
+            proof_id: "proof".to_string(),
+            status: ChallengeStatus::ChallengeCreated,
+            challenge_txid: None,
+            assert_txid: None,
+            disprove_txid: None,
+        };
+        storage.save_challenge_info("local", &info).unwrap();
+        storage.save_challenge_info("local", &info).unwrap();
+        assert_eq!(
+            storage.latest_challenge_info("local", "proof").unwrap(),
+            Some(info)
+        );
+    }
+
+    #[test]
+    fn test_transaction_roundtrip() {
+        let storage = Storage::open_in_memory().unwrap();
+        let tx = FiammaTransaction {
+            tx_id: "txid".to_string(),
+            tx_type: TransactionType::AssertTx,
+            data: "0200".to_string(),
+            ext_info: Some("fiammavaloper1".to_string()),
+            register_id: Some(3),
+        };
+        storage.save_transaction(tx).unwrap();
+
+        let tx = storage.transaction("txid").unwrap().unwrap();
+        assert_eq!(tx.tx_type, TransactionType::AssertTx);
+        assert_eq!(tx.register_id, Some(3));
+
+        let tx = FiammaTransaction {
+            tx_id: "challenge".to_string(),
+            tx_type: TransactionType::ChallengeTx,
+            data: "0200".to_string(),
+            ext_info: Some("proof".to_string()),
+            register_id: None,
+        };
+        storage.save_transaction(tx).unwrap();
+        let tx = storage.transaction("challenge").unwrap().unwrap();
+        assert_eq!(tx.register_id, None);
+        assert_eq!(
+            storage
+                .transactions_by_ext_info("fiammavaloper1")
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::Context;
+use rusqlite::{params, OptionalExtension, Row};
+
+use crate::Storage;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistrationRecord {
+    pub network: String,
+    pub validator_key: String,
+    pub public_key: String,
+    pub register_id: u32,
+    pub finished: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl RegistrationRecord {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            network: row.get(0)?,
+            validator_key: row.get(1)?,
+            public_key: row.get(2)?,
+            register_id: row.get(3)?,
+            finished: row.get(4)?,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+        })
+    }
+}
+
+/// Registration re-presigned for the next committee version, kept apart from the live
+/// registration until the rotation finishes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RotationRecord {
+    pub network: String,
+    pub validator_key: String,
+    pub committee_version: u32,
+    pub public_key: String,
+    pub register_id: u32,
+    pub created_at: String,
+}
+
+impl RotationRecord {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            network: row.get(0)?,
+            validator_key: row.get(1)?,
+            committee_version: row.get(2)?,
+            public_key: row.get(3)?,
+            register_id: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    }
+}
+
+const SELECT_REGISTRATION: &str = "SELECT network, validator_key, public_key, register_id, finished, created_at, updated_at FROM registrations";
+
+impl Storage {
+    pub fn save_registration(
+        &self,
+        network: &str,
+        validator_key: &str,
+        public_key: &str,
+        register_id: u32,
+    ) -> anyhow::Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO registrations (network, validator_key, public_key, register_id)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (network, validator_key) DO UPDATE SET
+                    public_key = excluded.public_key,
+                    register_id = excluded.register_id,
+                    finished = 0,
+                    updated_at = CURRENT_TIMESTAMP",
+                params![network, validator_key, public_key, register_id],
+            )
+            .context("Failed to save registration")?;
+        Ok(())
+    }
+
+    /// Marks the registration finished. The row is created when missing, e.g. when the start
+    /// step ran from another machine and the committee reported the key as registered.
+    pub fn finish_registration(
+        &self,
+        network: &str,
+        validator_key: &str,
+        public_key: &str,
+        register_id: u32,
+    ) -> anyhow::Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO registrations (network, validator_key, public_key, register_id, finished)
+                 VALUES (?1, ?2, ?3, ?4, 1)
+                 ON CONFLICT (network, validator_key) DO UPDATE SET
+                    register_id = excluded.register_id,
+                    finished = 1,
+                    updated_at = CURRENT_TIMESTAMP",
+                params![network, validator_key, public_key, register_id],
+            )
+            .context("Failed to update registration")?;
+        Ok(())
+    }
+
+    pub fn registration(
+        &self,
+        network: &str,
+        validator_key: &str,
+    ) -> anyhow::Result<Option<RegistrationRecord>> {
+        self.conn
+            .query_row(
+                &format!("{SELECT_REGISTRATION} WHERE network = ?1 AND validator_key = ?2"),
+                params![network, validator_key],
+                RegistrationRecord::from_row,
+            )
+            .optional()
+            .context("Failed to query registration")
+    }
+
+    /// Stores the registration started by `register rotate`, the live registration stays
+    /// untouched until [`Storage::finish_rotation`].
+    pub fn save_rotation(
+        &self,
+        network: &str,
+        validator_key: &str,
+        committee_version: u32,
+        public_key: &str,
+        register_id: u32,
+    ) -> anyhow::Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO rotations (network, validator_key, committee_version, public_key, register_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (network, validator_key) DO UPDATE SET
+                    committee_version = excluded.committee_version,
+                    public_key = excluded.public_key,
+                    register_id = excluded.register_id,
+                    created_at = CURRENT_TIMESTAMP",
+                params![network, validator_key, committee_version, public_key, register_id],
+            )
+            .context("Failed to save rotation")?;
+        Ok(())
+    }
+
+    pub fn rotation(
+        &self,
+        network: &str,
+        validator_key: &str,
+    ) -> anyhow::Result<Option<RotationRecord>> {
+        self.conn
+            .query_row(
+                "SELECT network, validator_key, committee_version, public_key, register_id, created_at
+                 FROM rotations WHERE network = ?1 AND validator_key = ?2",
+                params![network, validator_key],
+                RotationRecord::from_row,
+            )
+            .optional()
+            .context("Failed to query rotation")
+    }
+
+    /// Swaps the finished rotation in as the live registration and drops the rotation row.
+    pub fn finish_rotation(
+        &self,
+        network: &str,
+        validator_key: &str,
+        public_key: &str,
+        register_id: u32,
+    ) -> anyhow::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO registrations (network, validator_key, public_key, register_id, finished)
+             VALUES (?1, ?2, ?3, ?4, 1)
+             ON CONFLICT (network, validator_key) DO UPDATE SET
+                public_key = excluded.public_key,
+                register_id = excluded.register_id,
+                finished = 1,
+                updated_at = CURRENT_TIMESTAMP",
+            params![network, validator_key, public_key, register_id],
+        )
+        .context("Failed to update registration")?;
+        tx.execute(
+            "DELETE FROM rotations WHERE network = ?1 AND validator_key = ?2",
+            params![network, validator_key],
+        )
+        .context("Failed to delete rotation")?;
+        tx.commit().context("Failed to finish rotation")?;
+        Ok(())
+    }
+
+    /// The most recently started registration on `network`, used when no validator key is given.
+    pub fn latest_registration(&self, network: &str) -> anyhow::Result<Option<RegistrationRecord>> {
+        self.conn
+            .query_row(
+                &format!("{SELECT_REGISTRATION} WHERE network = ?1 ORDER BY updated_at DESC, rowid DESC LIMIT 1"),
+                params![network],
+                RegistrationRecord::from_row,
+            )
+            .optional()
+            .context("Failed to query registration")
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::Context;
+use rusqlite::{params, OptionalExtension, Row};
+use types::{storage_transaction::StorageFiammaTransaction, transaction::FiammaTransaction};
+
+use crate::Storage;
+
+fn storage_tx_from_row(row: &Row) -> rusqlite::Result<StorageFiammaTransaction> {
+    let ext_info: Option<String> = row.get(3)?;
+    Ok(StorageFiammaTransaction {
+        tx_id: row.get(0)?,
+        tx_type: row.get(1)?,
+        data: row.get(2)?,
+        ext_info: ext_info.map(serde_json::Value::String),
+        register_id: row.get(4)?,
+    })
+}
+
+impl Storage {
+    pub fn save_transaction(&self, tx: FiammaTransaction) -> anyhow::Result<()> {
+        let tx = StorageFiammaTransaction::from(tx);
+        let ext_info = tx
+            .ext_info
+            .as_ref()
+            .and_then(|v| v.as_str().map(|s| s.to_string()));
+        self.conn
+            .execute(
+                "INSERT INTO transactions (tx_id, tx_type, data, ext_info, register_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (tx_id) DO UPDATE SET
+                    tx_type = excluded.tx_type,
+                    data = excluded.data,
+                    ext_info = excluded.ext_info,
+                    register_id = excluded.register_id",
+                params![tx.tx_id, tx.tx_type, tx.data, ext_info, tx.register_id],
+            )
+            .context("Failed to save transaction")?;
+        Ok(())
+    }
+
+    pub fn transaction(&self, tx_id: &str) -> anyhow::Result<Option<FiammaTransaction>> {
+        let tx = self
+            .conn
+            .query_row(
+                "SELECT tx_id, tx_type, data, ext_info, register_id FROM transactions WHERE tx_id = ?1",
+                params![tx_id.as_bytes()],
+                storage_tx_from_row,
+            )
+            .optional()
+            .context("Failed to query transaction")?;
+
+        tx.map(|tx| FiammaTransaction::try_from(tx).map_err(Into::into))
+            .transpose()
+    }
+
+    /// Signed transactions whose `ext_info` equals `ext_info`, e.g. all txs of one validator key.
+    pub fn transactions_by_ext_info(
+        &self,
+        ext_info: &str,
+    ) -> anyhow::Result<Vec<FiammaTransaction>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT tx_id, tx_type, data, ext_info, register_id FROM transactions
+             WHERE ext_info = ?1 ORDER BY rowid",
+        )?;
+        let txs = stmt
+            .query_map(params![ext_info], storage_tx_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to query transactions")?;
+
+        txs.into_iter()
+            .map(|tx| FiammaTransaction::try_from(tx).map_err(Into::into))
+            .collect()
+    }
+}
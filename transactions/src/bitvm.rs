@@ -0,0 +1,266 @@
+use anyhow::Context;
+use bitcoin::{
+    absolute,
+    hashes::Hash,
+    secp256k1::Secp256k1,
+    taproot::{TaprootBuilder, TaprootSpendInfo},
+    transaction, ScriptBuf, TapLeafHash, Transaction, TxOut, Witness, XOnlyPublicKey,
+};
+use bitcoin_scriptexec::{Exec, ExecCtx, Options, TxTemplate};
+use types::{
+    bitvm::{LeafScript, WitnessRef},
+    error::DisproveError,
+    params::ProtocolParams,
+};
+
+/// Collects the assert tx witness elements the leaf consumes, in stack order.
+pub fn leaf_witness(assert_tx: &Transaction, leaf: &LeafScript) -> anyhow::Result<Vec<Vec<u8>>> {
+    leaf.witness
+        .iter()
+        .map(|WitnessRef { input, element }| {
+            assert_tx
+                .input
+                .get(*input)
+                .map(|txin| &txin.witness)
+                .and_then(|witness: &Witness| witness.nth(*element))
+                .map(|item| item.to_vec())
+                .with_context(|| {
+                    format!(
+                        "assert tx has no witness element {} in input {}",
+                        element, input
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Executes the leaf script on the assert tx witness, `true` means the script succeeds: the
+/// committed values are inconsistent and the leaf can be spent by a disprove tx.
+pub fn execute_leaf(assert_tx: &Transaction, leaf: &LeafScript) -> anyhow::Result<bool> {
+    let witness = leaf_witness(assert_tx, leaf)?;
+
+    // Chunk scripts only check hash-based commitments, so a placeholder spending tx is enough.
+    let tx_template = TxTemplate {
+        tx: Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        },
+        prevouts: Vec::<TxOut>::new(),
+        input_idx: 0,
+        taproot_annex_scriptleaf: Some((TapLeafHash::all_zeros(), None)),
+    };
+
+    let mut exec = Exec::new(
+        ExecCtx::Tapscript,
+        Options::default(),
+        tx_template,
+        leaf.script.clone(),
+        witness,
+    )
+    .map_err(|e| anyhow::anyhow!("failed to create script executor: {:?}", e))?;
+
+    while exec.exec_next().is_ok() {}
+
+    Ok(exec.result().is_some_and(|res| res.success))
+}
+
+/// Returns the indices of all leaves whose script succeeds against the assert tx, i.e. the
+/// script indices a disprove request can target. A leaf that fails found the committed values
+/// consistent and cannot be spent.
+pub fn find_disprovable_leaves(
+    assert_tx: &Transaction,
+    leaves: &[LeafScript],
+) -> anyhow::Result<Vec<usize>> {
+    let mut indices = vec![];
+    for (index, leaf) in leaves.iter().enumerate() {
+        if execute_leaf(assert_tx, leaf).context(DisproveError::ScriptInvalid(index))? {
+            indices.push(index);
+        }
+    }
+    Ok(indices)
+}
+
+/// Depth of every leaf when `count` leaves are laid out, in order, on a balanced tree.
+fn balanced_depths(count: usize) -> Vec<u8> {
+    if count <= 1 {
+        return vec![0; count];
+    }
+
+    let depth = (usize::BITS - (count - 1).leading_zeros()) as u8;
+    let deepest = 2 * (count - (1 << (depth - 1)));
+    (0..count)
+        .map(|i| if i < deepest { depth } else { depth - 1 })
+        .collect()
+}
+
+/// Builds the taproot tree committing to the leaf scripts, which are placed in order on a
+/// balanced tree under `internal_key`.
+pub fn leaf_spend_info(
+    leaves: &[LeafScript],
+    internal_key: XOnlyPublicKey,
+) -> anyhow::Result<TaprootSpendInfo> {
+    let secp = Secp256k1::verification_only();
+
+    let mut builder = TaprootBuilder::new();
+    for (leaf, depth) in leaves.iter().zip(balanced_depths(leaves.len())) {
+        builder = builder.add_leaf(depth, leaf.script.clone())?;
+    }
+
+    builder
+        .finalize(&secp, internal_key)
+        .map_err(|_| anyhow::anyhow!("taproot tree of {} leaves is incomplete", leaves.len()))
+}
+
+/// Script pubkey of the taproot output committing to the leaf scripts.
+pub fn leaf_script_pubkey(spend_info: &TaprootSpendInfo) -> ScriptBuf {
+    ScriptBuf::new_p2tr_tweaked(spend_info.output_key())
+}
+
+/// Connector C output of the assert tx, the output the disprove tx spends.
+pub fn connector_c<'a>(params: &ProtocolParams, assert_tx: &'a Transaction) -> Option<&'a TxOut> {
+    assert_tx.output.get(params.connector_c_index as usize)
+}
+
+/// Whether the connector C output of the assert tx is the taproot output committing to the
+/// leaf scripts, i.e. the disprove scripts the assert tx can be challenged with.
+pub fn commits_to_leaves(
+    params: &ProtocolParams,
+    assert_tx: &Transaction,
+    leaves: &[LeafScript],
+    internal_key: XOnlyPublicKey,
+) -> anyhow::Result<bool> {
+    let Some(connector_c) = connector_c(params, assert_tx) else {
+        return Ok(false);
+    };
+
+    let spend_info = leaf_spend_info(leaves, internal_key)?;
+    Ok(connector_c.script_pubkey == leaf_script_pubkey(&spend_info))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin::{
+        opcodes::{all::OP_EQUAL, OP_TRUE},
+        script::Builder,
+        taproot::LeafVersion,
+        Amount, Network, TxIn,
+    };
+    use types::{bitvm::NUMS_INTERNAL_KEY, committee::CommitteeVersionId};
+
+    fn leaves(count: i64) -> Vec<LeafScript> {
+        (0..count)
+            .map(|i| LeafScript {
+                script: Builder::new()
+                    .push_int(i)
+                    .push_opcode(OP_TRUE)
+                    .into_script(),
+                witness: vec![],
+            })
+            .collect()
+    }
+
+    use super::*;
+
+    #[test]
+    fn test_leaf_spend_info_is_complete() {
+        let internal_key = XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY).unwrap();
+        for count in 1..=33 {
+            let leaves = leaves(count);
+
+            let spend_info = leaf_spend_info(&leaves, internal_key).unwrap();
+            for leaf in &leaves {
+                let control_block = spend_info
+                    .control_block(&(leaf.script.clone(), LeafVersion::TapScript))
+                    .unwrap();
+                assert!(control_block.verify_taproot_commitment(
+                    &Secp256k1::verification_only(),
+                    spend_info.output_key().to_inner(),
+                    &leaf.script,
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_commits_to_leaves() {
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let internal_key = XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY).unwrap();
+        let leaves = leaves(5);
+        let spend_info = leaf_spend_info(&leaves, internal_key).unwrap();
+
+        let mut assert_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![
+                TxOut {
+                    value: Amount::from_sat(1000),
+                    script_pubkey: ScriptBuf::new(),
+                },
+                TxOut {
+                    value: Amount::from_sat(1000),
+                    script_pubkey: leaf_script_pubkey(&spend_info),
+                },
+            ],
+        };
+        assert!(commits_to_leaves(&params, &assert_tx, &leaves, internal_key).unwrap());
+        assert!(!commits_to_leaves(&params, &assert_tx, &leaves[1..], internal_key).unwrap());
+
+        assert_tx.output.truncate(1);
+        assert!(!commits_to_leaves(&params, &assert_tx, &leaves, internal_key).unwrap());
+    }
+
+    #[test]
+    fn test_find_disprovable_leaves() {
+        let assert_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                witness: Witness::from_slice(&[[7u8]]),
+                ..Default::default()
+            }],
+            output: vec![],
+        };
+        let committed = WitnessRef {
+            input: 0,
+            element: 0,
+        };
+        let leaves = vec![
+            // Succeeds, so the committed values are inconsistent.
+            LeafScript {
+                script: Builder::new().push_opcode(OP_TRUE).into_script(),
+                witness: vec![],
+            },
+            // Leaves false on the stack, the leaf found nothing to disprove.
+            LeafScript {
+                script: Builder::new().push_int(0).into_script(),
+                witness: vec![],
+            },
+            LeafScript {
+                script: Builder::new()
+                    .push_int(7)
+                    .push_opcode(OP_EQUAL)
+                    .into_script(),
+                witness: vec![committed],
+            },
+            LeafScript {
+                script: Builder::new()
+                    .push_int(8)
+                    .push_opcode(OP_EQUAL)
+                    .into_script(),
+                witness: vec![committed],
+            },
+        ];
+
+        assert!(execute_leaf(&assert_tx, &leaves[0]).unwrap());
+        assert!(!execute_leaf(&assert_tx, &leaves[1]).unwrap());
+        assert_eq!(
+            find_disprovable_leaves(&assert_tx, &leaves).unwrap(),
+            vec![0, 2]
+        );
+    }
+}
@@ -1,6 +1,17 @@
-use bitcoin::{OutPoint, PrivateKey, ScriptBuf, Transaction, TxOut};
+use bitcoin::{
+    absolute, taproot::LeafVersion, transaction, OutPoint, PrivateKey, ScriptBuf, Sequence,
+    Transaction, TxIn, TxOut, Witness, XOnlyPublicKey,
+};
+use types::{
+    bitvm::LeafScript,
+    error::{DisproveError, FcliError},
+    params::ProtocolParams,
+};
 
-use crate::create_tx_with_single_signature;
+use crate::{
+    bitvm::{connector_c, execute_leaf, leaf_script_pubkey, leaf_spend_info, leaf_witness},
+    create_tx_with_single_signature,
+};
 
 pub struct DisproveTransaction {
     pub tx: Transaction,
@@ -16,3 +27,279 @@ impl DisproveTransaction {
         Self { tx }
     }
 }
+
+/// Disprove tx spending the assert tx's leaf script output through a leaf whose script
+/// succeeds, built without the committee.
+///
+/// Connector C only holds the connector amount, which is paid out whole as the reward. The
+/// disprove fee comes from a second input of the challenger, left unsigned in `tx`, whose
+/// change returns to the script pubkey of that input.
+pub struct DisproveScriptSpend {
+    pub tx: Transaction,
+    /// The outputs spent by `tx`: connector C of the assert tx, then the fee input.
+    pub prevouts: Vec<TxOut>,
+}
+
+impl DisproveScriptSpend {
+    pub fn new(
+        params: &ProtocolParams,
+        assert_tx: &Transaction,
+        leaves: &[LeafScript],
+        script_index: usize,
+        internal_key: XOnlyPublicKey,
+        reward_script_pubkey: ScriptBuf,
+        fee_input: (OutPoint, TxOut),
+    ) -> anyhow::Result<Self> {
+        let leaf = leaves
+            .get(script_index)
+            .ok_or(DisproveError::WrongScriptInedx(script_index))?;
+
+        let spend_info = leaf_spend_info(leaves, internal_key)?;
+        let script_pubkey = leaf_script_pubkey(&spend_info);
+        let prevout = connector_c(params, assert_tx)
+            .filter(|output| output.script_pubkey == script_pubkey)
+            .ok_or(DisproveError::ConnectorCMismatch)?;
+
+        let reward = TxOut {
+            value: prevout.value,
+            script_pubkey: reward_script_pubkey,
+        };
+        let dust_limit = reward.script_pubkey.minimal_non_dust();
+        if reward.value < dust_limit {
+            anyhow::bail!(DisproveError::RewardBelowDust(
+                reward.value.to_sat(),
+                dust_limit.to_sat()
+            ));
+        }
+
+        let (fee_outpoint, fee_prevout) = fee_input;
+        let change = TxOut {
+            value: fee_prevout.value.checked_sub(params.disprove_fee).ok_or(
+                FcliError::InsufficientFunds {
+                    available: fee_prevout.value,
+                    required: params.disprove_fee,
+                },
+            )?,
+            script_pubkey: fee_prevout.script_pubkey.clone(),
+        };
+
+        if !execute_leaf(assert_tx, leaf)? {
+            anyhow::bail!(DisproveError::ScriptInvalid(script_index));
+        }
+        let control_block = spend_info
+            .control_block(&(leaf.script.clone(), LeafVersion::TapScript))
+            .ok_or(DisproveError::FailedToCreateControl)?;
+
+        let mut witness = Witness::new();
+        for item in leaf_witness(assert_tx, leaf)? {
+            witness.push(item);
+        }
+        witness.push(leaf.script.as_bytes());
+        witness.push(control_block.serialize());
+
+        let mut output = vec![reward];
+        // A dust change is left to the fee.
+        if change.value >= change.script_pubkey.minimal_non_dust() {
+            output.push(change);
+        }
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![
+                TxIn {
+                    previous_output: OutPoint {
+                        txid: assert_tx.compute_txid(),
+                        vout: params.connector_c_index,
+                    },
+                    script_sig: ScriptBuf::default(),
+                    sequence: Sequence::MAX,
+                    witness,
+                },
+                TxIn {
+                    previous_output: fee_outpoint,
+                    script_sig: ScriptBuf::default(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::default(), // Signed by the challenger.
+                },
+            ],
+            output,
+        };
+
+        Ok(Self {
+            tx,
+            prevouts: vec![prevout.clone(), fee_prevout],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin::{
+        hashes::Hash, opcodes::OP_TRUE, script::Builder, secp256k1::Secp256k1, Amount,
+        CompressedPublicKey, Network, Txid,
+    };
+    use types::{bitvm::NUMS_INTERNAL_KEY, committee::CommitteeVersionId};
+
+    use super::*;
+
+    fn assert_tx(params: &ProtocolParams, connector_c: TxOut) -> Transaction {
+        let mut output = vec![
+            TxOut {
+                value: params.dust_amount,
+                script_pubkey: ScriptBuf::new(),
+            };
+            params.connector_c_index as usize
+        ];
+        output.push(connector_c);
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![],
+            output,
+        }
+    }
+
+    fn fee_input(value: Amount) -> (OutPoint, TxOut) {
+        let key = PrivateKey::from_slice(&[9; 32], Network::Regtest).unwrap();
+        let pubkey = CompressedPublicKey::from_private_key(&Secp256k1::new(), &key).unwrap();
+        (
+            OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 0,
+            },
+            TxOut {
+                value,
+                script_pubkey: ScriptBuf::new_p2wpkh(&pubkey.wpubkey_hash()),
+            },
+        )
+    }
+
+    #[test]
+    fn test_disprove_pays_reward_address() {
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let internal_key = XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY).unwrap();
+        let leaves = vec![LeafScript {
+            script: Builder::new().push_opcode(OP_TRUE).into_script(),
+            witness: vec![],
+        }];
+        let connector_c = TxOut {
+            value: params.dust_amount,
+            script_pubkey: leaf_script_pubkey(&leaf_spend_info(&leaves, internal_key).unwrap()),
+        };
+        let assert_tx = assert_tx(&params, connector_c.clone());
+        let (_, reward) = fee_input(Amount::ZERO);
+        let fee_input = fee_input(Amount::from_sat(10_000));
+
+        let disprove = DisproveScriptSpend::new(
+            &params,
+            &assert_tx,
+            &leaves,
+            0,
+            internal_key,
+            reward.script_pubkey.clone(),
+            fee_input.clone(),
+        )
+        .unwrap();
+        assert_eq!(disprove.prevouts, vec![connector_c, fee_input.1.clone()]);
+        assert_eq!(disprove.tx.input[1].previous_output, fee_input.0);
+        // Connector C is paid out whole, the fee input pays the disprove fee.
+        assert_eq!(
+            disprove.tx.output,
+            vec![
+                TxOut {
+                    value: params.dust_amount,
+                    script_pubkey: reward.script_pubkey.clone(),
+                },
+                TxOut {
+                    value: Amount::from_sat(10_000) - params.disprove_fee,
+                    script_pubkey: fee_input.1.script_pubkey.clone(),
+                },
+            ]
+        );
+
+        let err = DisproveScriptSpend::new(
+            &params,
+            &assert_tx,
+            &leaves,
+            0,
+            internal_key,
+            reward.script_pubkey,
+            (
+                fee_input.0,
+                TxOut {
+                    value: params.disprove_fee - Amount::from_sat(1),
+                    ..fee_input.1
+                },
+            ),
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<FcliError>(),
+            Some(FcliError::InsufficientFunds { .. })
+        ));
+    }
+
+    #[test]
+    fn test_disprove_spends_indexed_connector_c() {
+        let params = ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap();
+        let internal_key = XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY).unwrap();
+        let leaves = vec![LeafScript {
+            script: Builder::new().push_opcode(OP_TRUE).into_script(),
+            witness: vec![],
+        }];
+        let script_pubkey = leaf_script_pubkey(&leaf_spend_info(&leaves, internal_key).unwrap());
+        let (_, reward) = fee_input(Amount::ZERO);
+        let fee_input = fee_input(Amount::from_sat(10_000));
+
+        // The leaf commitment at another output than connector C is not spendable.
+        let mut misplaced = assert_tx(
+            &params,
+            TxOut {
+                value: params.dust_amount,
+                script_pubkey: ScriptBuf::new(),
+            },
+        );
+        misplaced.output[0].script_pubkey = script_pubkey.clone();
+        let err = DisproveScriptSpend::new(
+            &params,
+            &misplaced,
+            &leaves,
+            0,
+            internal_key,
+            reward.script_pubkey.clone(),
+            fee_input.clone(),
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<DisproveError>(),
+            Some(DisproveError::ConnectorCMismatch)
+        ));
+
+        let dusty = assert_tx(
+            &params,
+            TxOut {
+                value: Amount::from_sat(1),
+                script_pubkey,
+            },
+        );
+        let err = DisproveScriptSpend::new(
+            &params,
+            &dusty,
+            &leaves,
+            0,
+            internal_key,
+            reward.script_pubkey,
+            fee_input,
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<DisproveError>(),
+            Some(DisproveError::RewardBelowDust(1, _))
+        ));
+    }
+}
@@ -0,0 +1,532 @@
+use std::collections::HashMap;
+
+use bitcoin::{
+    absolute,
+    secp256k1::{Keypair, Secp256k1},
+    transaction, Amount, OutPoint, PrivateKey, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid,
+    Witness, XOnlyPublicKey,
+};
+use types::{
+    bitvm::LeafScript,
+    error::{FcliError, GraphError},
+    params::ProtocolParams,
+};
+
+use crate::{
+    assert::AssertTransaction,
+    bitvm::{connector_c, leaf_script_pubkey, leaf_spend_info},
+    challenge::ChallengeTransaction,
+    disprove::DisproveTransaction,
+    stake::StakeTransaction,
+    types::P2trUtxo,
+};
+
+/// Everything a validator's tx graph is derived from.
+#[derive(Clone, Debug)]
+pub struct GraphSpec {
+    /// Key of the validator, owns the funding UTXO and presigns every tx of the graph.
+    pub operator_key: PrivateKey,
+    /// Multi sig script of the committee and the validator, locking the stake.
+    pub multi_sig_script: ScriptBuf,
+    pub params: ProtocolParams,
+    /// Key path p2tr UTXO of the operator key funding the stake tx.
+    pub funding: (OutPoint, TxOut),
+    /// Receives the change of the stake tx.
+    pub change_script_pubkey: ScriptBuf,
+}
+
+/// The validator's presigned BitVM2 txs, wired from one [`GraphSpec`]: the stake tx, the
+/// assert tx spending the stake and connector B, and the challenge tx spending connector A.
+/// The committee completes the assert tx once per circuit, the disprove txs spend connector C
+/// of those, see [`disprove_tx`].
+#[derive(Clone, Debug)]
+pub struct TxGraph {
+    pub params: ProtocolParams,
+    pub multi_sig_script: ScriptBuf,
+    pub stake_tx: Transaction,
+    pub assert_tx: Transaction,
+    pub challenge_tx: Transaction,
+    operator_key: PrivateKey,
+    /// Outputs spent inside the graph: the funding UTXO and the stake tx outputs.
+    prevouts: HashMap<OutPoint, TxOut>,
+}
+
+/// P2wsh script pubkey of the multi sig script, locking the stake and the connectors.
+pub fn multi_sig_script_pubkey(multi_sig_script: &ScriptBuf) -> ScriptBuf {
+    ScriptBuf::new_p2wsh(&multi_sig_script.wscript_hash())
+}
+
+/// A connector output, the dust amount locked to the multi sig script.
+pub fn connector_txout(params: &ProtocolParams, multi_sig_script: &ScriptBuf) -> TxOut {
+    TxOut {
+        value: params.dust_amount,
+        script_pubkey: multi_sig_script_pubkey(multi_sig_script),
+    }
+}
+
+impl TxGraph {
+    pub fn new(spec: GraphSpec) -> anyhow::Result<Self> {
+        let GraphSpec {
+            operator_key,
+            multi_sig_script,
+            params,
+            funding: (funding_outpoint, funding_txout),
+            change_script_pubkey,
+        } = spec;
+
+        let required = params.stake_input_amount();
+        let change =
+            funding_txout
+                .value
+                .checked_sub(required)
+                .ok_or(FcliError::InsufficientFunds {
+                    available: funding_txout.value,
+                    required,
+                })?;
+
+        // Place the stake and connector outputs at their indices of the params, which must
+        // fill the first outputs of the stake tx.
+        let stake = TxOut {
+            value: params.stake_output_amount(),
+            script_pubkey: multi_sig_script_pubkey(&multi_sig_script),
+        };
+        let connector = connector_txout(&params, &multi_sig_script);
+        let layout = [
+            (params.stake_value_index, stake),
+            (params.stake_timelock_index, connector.clone()),
+            (params.connector_a_index, connector.clone()),
+            (params.connector_b_index, connector),
+        ];
+        let mut outputs = vec![None; layout.len()];
+        for (index, output) in layout {
+            let slot = outputs
+                .get_mut(index as usize)
+                .filter(|slot| slot.is_none())
+                .ok_or_else(|| GraphError::InvalidLayout(format!("output index {}", index)))?;
+            *slot = Some(output);
+        }
+        let mut outputs = outputs.into_iter().flatten().collect::<Vec<_>>();
+        outputs.push(TxOut {
+            value: change,
+            script_pubkey: change_script_pubkey,
+        });
+
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, &operator_key.inner);
+        let (internal_key, _parity) = XOnlyPublicKey::from_keypair(&keypair);
+        let input_utxo = P2trUtxo {
+            txid: &funding_outpoint.txid.to_string(),
+            vout: funding_outpoint.vout,
+            script_pubkey: &funding_txout.script_pubkey.to_hex_string(),
+            pubkey: &internal_key.to_string(),
+            amount_in_sats: funding_txout.value,
+        };
+        let stake_tx = StakeTransaction::new(operator_key, input_utxo, outputs)?.extract_tx();
+
+        let stake_txid = stake_tx.compute_txid();
+        let mut prevouts = HashMap::from([(funding_outpoint, funding_txout)]);
+        for (vout, output) in stake_tx.output.iter().enumerate() {
+            let outpoint = OutPoint {
+                txid: stake_txid,
+                vout: vout as u32,
+            };
+            prevouts.insert(outpoint, output.clone());
+        }
+        let stake_output = |vout: u32| {
+            let outpoint = OutPoint {
+                txid: stake_txid,
+                vout,
+            };
+            (outpoint, prevouts[&outpoint].clone())
+        };
+
+        let assert_tx = AssertTransaction::new(
+            operator_key,
+            vec![
+                stake_output(params.stake_value_index),
+                stake_output(params.connector_b_index),
+            ],
+            multi_sig_script.clone(),
+        )
+        .tx;
+        let challenge_tx = ChallengeTransaction::new(
+            operator_key,
+            vec![stake_output(params.connector_a_index)],
+            multi_sig_script.clone(),
+        )
+        .tx;
+
+        let graph = Self {
+            params,
+            multi_sig_script,
+            stake_tx,
+            assert_tx,
+            challenge_tx,
+            operator_key,
+            prevouts,
+        };
+        graph.validate()?;
+        Ok(graph)
+    }
+
+    pub fn stake_txid(&self) -> Txid {
+        self.stake_tx.compute_txid()
+    }
+
+    /// Output `vout` of the stake tx.
+    pub fn stake_output(&self, vout: u32) -> (OutPoint, TxOut) {
+        let outpoint = OutPoint {
+            txid: self.stake_txid(),
+            vout,
+        };
+        (outpoint, self.prevouts[&outpoint].clone())
+    }
+
+    /// Outputs spent by the inputs of `tx`, in input order. Fails for an input spending an
+    /// output outside the graph.
+    pub fn prevouts(&self, tx: &Transaction) -> Result<Vec<TxOut>, GraphError> {
+        tx.input
+            .iter()
+            .map(|input| {
+                self.prevouts
+                    .get(&input.previous_output)
+                    .cloned()
+                    .ok_or_else(|| GraphError::UnknownPrevout(input.previous_output.to_string()))
+            })
+            .collect()
+    }
+
+    /// Checks that `tx` turns its graph inputs into `outputs` plus exactly `fee`.
+    fn check_balance(
+        &self,
+        tx: &Transaction,
+        outputs: Amount,
+        fee: Amount,
+    ) -> Result<(), GraphError> {
+        let inputs = self
+            .prevouts(tx)?
+            .iter()
+            .map(|txout| txout.value)
+            .sum::<Amount>();
+        if outputs.checked_add(fee) != Some(inputs) {
+            return Err(GraphError::Unbalanced(
+                tx.compute_txid(),
+                inputs.to_sat(),
+                outputs.to_sat(),
+                fee.to_sat(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the amounts at every edge: the stake tx pays the stake fee, the assert tx inputs
+    /// cover the stake and connector C the committee adds plus the assert fee, and the
+    /// challenge tx spends one connector.
+    fn validate(&self) -> Result<(), GraphError> {
+        let params = &self.params;
+        let stake_outputs = self.stake_tx.output.iter().map(|txout| txout.value).sum();
+        self.check_balance(&self.stake_tx, stake_outputs, params.stake_fee)?;
+
+        let assert_outputs = params.stake_amount.unchecked_add(params.dust_amount);
+        self.check_balance(&self.assert_tx, assert_outputs, params.assert_fee)?;
+
+        self.check_balance(&self.challenge_tx, params.dust_amount, Amount::ZERO)
+    }
+
+    /// Presigns the disprove tx spending connector C of `assert_tx`, the assert tx of the graph
+    /// as completed by the committee for one circuit, see [`disprove_tx`] for `leaves`.
+    pub fn disprove_tx(
+        &self,
+        assert_tx: &Transaction,
+        leaves: Option<(&[LeafScript], XOnlyPublicKey)>,
+    ) -> anyhow::Result<Transaction> {
+        let assert_txid = assert_tx.compute_txid();
+        let spent = |tx: &Transaction| {
+            tx.input
+                .iter()
+                .map(|input| input.previous_output)
+                .collect::<Vec<_>>()
+        };
+        if spent(assert_tx) != spent(&self.assert_tx) {
+            anyhow::bail!(GraphError::ForeignAssertTx(assert_txid));
+        }
+
+        let inputs = self
+            .prevouts(assert_tx)?
+            .iter()
+            .map(|txout| txout.value)
+            .sum::<Amount>();
+        let outputs = assert_tx
+            .output
+            .iter()
+            .map(|txout| txout.value)
+            .sum::<Amount>();
+        if outputs > inputs {
+            anyhow::bail!(GraphError::Unbalanced(
+                assert_txid,
+                inputs.to_sat(),
+                outputs.to_sat(),
+                self.params.assert_fee.to_sat()
+            ));
+        }
+
+        disprove_tx(
+            &self.operator_key,
+            &self.multi_sig_script,
+            &self.params,
+            assert_tx,
+            leaves,
+        )
+    }
+}
+
+/// Presigns the disprove tx spending connector C of `assert_tx`. Unlike
+/// [`TxGraph::disprove_tx`] it does not check that the assert tx spends the validator's stake,
+/// for validators that no longer hold the funding of their stake tx.
+///
+/// Connector C must hold the connector amount and be locked to the taproot tree of the
+/// circuit's leaf scripts under the internal key when `leaves` are given, to the multi sig
+/// script otherwise. The tree has no key path and its leaves check no signature, so the
+/// disprove tx of a leaf commitment is left unsigned for the committee to complete with the
+/// witness of the leaf that succeeds.
+pub fn disprove_tx(
+    operator_key: &PrivateKey,
+    multi_sig_script: &ScriptBuf,
+    params: &ProtocolParams,
+    assert_tx: &Transaction,
+    leaves: Option<(&[LeafScript], XOnlyPublicKey)>,
+) -> anyhow::Result<Transaction> {
+    let assert_txid = assert_tx.compute_txid();
+    let vout = params.connector_c_index;
+    let prevout = connector_c(params, assert_tx)
+        .ok_or(GraphError::MissingOutput(assert_txid, vout))?
+        .clone();
+    let outpoint = OutPoint {
+        txid: assert_txid,
+        vout,
+    };
+
+    let expected = match leaves {
+        Some((leaves, internal_key)) => TxOut {
+            value: params.dust_amount,
+            script_pubkey: leaf_script_pubkey(&leaf_spend_info(leaves, internal_key)?),
+        },
+        None => connector_txout(params, multi_sig_script),
+    };
+    if prevout.value != expected.value {
+        anyhow::bail!(GraphError::EdgeValueMismatch(
+            outpoint.to_string(),
+            prevout.value.to_sat(),
+            expected.value.to_sat()
+        ));
+    }
+    if prevout.script_pubkey != expected.script_pubkey {
+        anyhow::bail!(GraphError::EdgeScriptMismatch(
+            outpoint.to_string(),
+            prevout.script_pubkey.to_hex_string(),
+            expected.script_pubkey.to_hex_string()
+        ));
+    }
+
+    if leaves.is_some() {
+        return Ok(Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                script_sig: ScriptBuf::default(),
+                sequence: Sequence::MAX,
+                witness: Witness::default(),
+            }],
+            output: vec![],
+        });
+    }
+    Ok(DisproveTransaction::new(
+        operator_key,
+        vec![(outpoint, prevout)],
+        multi_sig_script.clone(),
+    )
+    .tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin::{
+        hashes::Hash,
+        opcodes::{all::OP_CHECKSIG, OP_TRUE},
+        script::Builder,
+        Network,
+    };
+    use types::{bitvm::NUMS_INTERNAL_KEY, committee::CommitteeVersionId};
+
+    use super::*;
+
+    fn spec(funding_amount: Amount) -> GraphSpec {
+        let secp = Secp256k1::new();
+        let operator_key = PrivateKey::from_slice(&[7; 32], Network::Regtest).unwrap();
+        let (internal_key, _parity) = operator_key.inner.x_only_public_key(&secp);
+        let script_pubkey = ScriptBuf::new_p2tr(&secp, internal_key, None);
+
+        GraphSpec {
+            operator_key,
+            multi_sig_script: Builder::new()
+                .push_key(&operator_key.public_key(&secp))
+                .push_opcode(OP_CHECKSIG)
+                .into_script(),
+            params: ProtocolParams::new(Network::Regtest, CommitteeVersionId::latest()).unwrap(),
+            funding: (
+                OutPoint {
+                    txid: Txid::all_zeros(),
+                    vout: 0,
+                },
+                TxOut {
+                    value: funding_amount,
+                    script_pubkey: script_pubkey.clone(),
+                },
+            ),
+            change_script_pubkey: script_pubkey,
+        }
+    }
+
+    /// The assert tx as the committee completes it: the stake and connector C outputs.
+    fn complete_assert_tx(graph: &TxGraph, connector_c_amount: Amount) -> Transaction {
+        let mut assert_tx = graph.assert_tx.clone();
+        assert_tx.output = vec![TxOut {
+            value: graph.params.stake_amount,
+            script_pubkey: multi_sig_script_pubkey(&graph.multi_sig_script),
+        }];
+        assert_tx.output.insert(
+            graph.params.connector_c_index as usize,
+            TxOut {
+                value: connector_c_amount,
+                script_pubkey: multi_sig_script_pubkey(&graph.multi_sig_script),
+            },
+        );
+        assert_tx
+    }
+
+    #[test]
+    fn test_graph_is_consistent() {
+        let params = spec(Amount::ZERO).params;
+        let graph =
+            TxGraph::new(spec(params.stake_input_amount() + Amount::from_sat(1_000))).unwrap();
+
+        let stake_txid = graph.stake_txid();
+        assert_eq!(graph.stake_tx.output[4].value, Amount::from_sat(1_000));
+        for (tx, vouts) in [
+            (
+                &graph.assert_tx,
+                vec![params.stake_value_index, params.connector_b_index],
+            ),
+            (&graph.challenge_tx, vec![params.connector_a_index]),
+        ] {
+            let spent = tx
+                .input
+                .iter()
+                .map(|input| input.previous_output)
+                .collect::<Vec<_>>();
+            let expected = vouts
+                .into_iter()
+                .map(|vout| OutPoint {
+                    txid: stake_txid,
+                    vout,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(spent, expected);
+        }
+
+        let assert_tx = complete_assert_tx(&graph, params.dust_amount);
+        let disprove_tx = graph.disprove_tx(&assert_tx, None).unwrap();
+        let connector_c = OutPoint {
+            txid: assert_tx.compute_txid(),
+            vout: params.connector_c_index,
+        };
+        assert_eq!(disprove_tx.input[0].previous_output, connector_c);
+        assert_eq!(disprove_tx.input[0].witness.len(), 2);
+
+        // Connector C committing to the leaf scripts is spent through a leaf, not signed.
+        let leaves = [LeafScript {
+            script: Builder::new().push_opcode(OP_TRUE).into_script(),
+            witness: vec![],
+        }];
+        let internal_key = XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY).unwrap();
+        let mut assert_tx = assert_tx;
+        assert_tx.output[params.connector_c_index as usize].script_pubkey =
+            leaf_script_pubkey(&leaf_spend_info(&leaves, internal_key).unwrap());
+        let disprove_tx = graph
+            .disprove_tx(&assert_tx, Some((&leaves, internal_key)))
+            .unwrap();
+        assert_eq!(
+            disprove_tx.input[0].previous_output.txid,
+            assert_tx.compute_txid()
+        );
+        assert!(disprove_tx.input[0].witness.is_empty());
+    }
+
+    #[test]
+    fn test_graph_rejects_invalid_edges() {
+        let params = spec(Amount::ZERO).params;
+        let err = TxGraph::new(spec(params.stake_input_amount() - Amount::ONE_SAT)).unwrap_err();
+        assert!(err.is::<FcliError>());
+
+        let mut invalid_layout = spec(params.stake_input_amount());
+        invalid_layout.params.connector_a_index = invalid_layout.params.connector_b_index;
+        let err = TxGraph::new(invalid_layout).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<GraphError>(),
+            Some(GraphError::InvalidLayout(_))
+        ));
+
+        let graph = TxGraph::new(spec(params.stake_input_amount())).unwrap();
+        let err = graph
+            .disprove_tx(
+                &complete_assert_tx(&graph, params.dust_amount + Amount::ONE_SAT),
+                None,
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<GraphError>(),
+            Some(GraphError::EdgeValueMismatch(..))
+        ));
+
+        // Connector C locked to the multi sig script does not commit to any leaf script.
+        let leaves = [LeafScript {
+            script: Builder::new().push_opcode(OP_TRUE).into_script(),
+            witness: vec![],
+        }];
+        let internal_key = XOnlyPublicKey::from_str(NUMS_INTERNAL_KEY).unwrap();
+        let err = graph
+            .disprove_tx(
+                &complete_assert_tx(&graph, params.dust_amount),
+                Some((&leaves, internal_key)),
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<GraphError>(),
+            Some(GraphError::EdgeScriptMismatch(..))
+        ));
+
+        let mut overspending = complete_assert_tx(&graph, params.dust_amount);
+        overspending.output[0].value = params.stake_output_amount() + Amount::ONE_SAT;
+        let err = graph.disprove_tx(&overspending, None).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<GraphError>(),
+            Some(GraphError::Unbalanced(..))
+        ));
+
+        let mut foreign = complete_assert_tx(&graph, params.dust_amount);
+        foreign.input.push(TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        });
+        let err = graph.disprove_tx(&foreign, None).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<GraphError>(),
+            Some(GraphError::ForeignAssertTx(_))
+        ));
+    }
+}
@@ -14,8 +14,11 @@ use bitcoin::{
 use types::P2trUtxo;
 
 pub mod assert;
+pub mod bitvm;
 pub mod challenge;
 pub mod disprove;
+pub mod graph;
+pub mod policy;
 pub mod stake;
 pub mod types;
 
@@ -47,8 +50,7 @@ fn generate_bip86_key_spend_tx(
 
     let mut input = Input {
         witness_utxo: {
-            let script_pubkey = ScriptBuf::from_hex(input_utxo.script_pubkey)
-                .expect("failed to parse input utxo scriptPubkey");
+            let script_pubkey = ScriptBuf::from_hex(input_utxo.script_pubkey)?;
             Some(TxOut {
                 value: from_amount,
                 script_pubkey,
@@ -118,13 +120,13 @@ fn generate_bip86_key_spend_tx(
 
     // EXTRACTOR
     let tx = psbt.extract_tx_unchecked_fee_rate();
+    let script_pubkey = ScriptBuf::from_hex(input_utxo.script_pubkey)?;
     tx.verify(|_| {
         Some(TxOut {
             value: from_amount,
-            script_pubkey: ScriptBuf::from_hex(input_utxo.script_pubkey).unwrap(),
+            script_pubkey: script_pubkey.clone(),
         })
-    })
-    .expect("failed to verify transaction");
+    })?;
 
     Ok(tx)
 }
@@ -189,7 +191,7 @@ pub fn compute_sighash_p2wsh(
     sighash_type: EcdsaSighashType,
     witness_script: ScriptBuf,
 ) -> anyhow::Result<SegwitV0Sighash> {
-    let tx: Transaction = consensus::deserialize(raw_tx).unwrap();
+    let tx: Transaction = consensus::deserialize(raw_tx).context("failed to decode tx")?;
 
     let mut cache = sighash::SighashCache::new(&tx);
     cache
@@ -0,0 +1,290 @@
+//! Relay policy pre-checks, the standardness rules of bitcoin core a tx must meet to be
+//! relayed, so a non-standard tx fails with a per-rule message before it is submitted rather
+//! than when the committee or the bitcoin backend broadcasts it.
+
+use bitcoin::{absolute, Amount, OutPoint, Script, Transaction, TxOut, Witness};
+use types::error::PolicyError;
+
+/// Highest tx version bitcoin core relays.
+pub const MAX_STANDARD_VERSION: i32 = 2;
+/// Weight limit of a standard tx, 400 000 wu.
+pub const MAX_STANDARD_TX_WEIGHT: u64 = 400_000;
+/// Minimum relay fee rate in sats per vbyte.
+pub const MIN_RELAY_FEE_RATE: u64 = 1;
+/// Stack items of a standard p2wsh witness, the witness script excluded.
+pub const MAX_STANDARD_P2WSH_STACK_ITEMS: usize = 100;
+/// Size of a stack item of a standard p2wsh witness.
+pub const MAX_STANDARD_P2WSH_STACK_ITEM_SIZE: usize = 80;
+/// Size of the witness script of a standard p2wsh witness.
+pub const MAX_STANDARD_P2WSH_SCRIPT_SIZE: usize = 3_600;
+/// Size of a stack item of a standard tapscript witness.
+pub const MAX_STANDARD_TAPSCRIPT_STACK_ITEM_SIZE: usize = 80;
+
+const TAPROOT_ANNEX_PREFIX: u8 = 0x50;
+const TAPROOT_LEAF_MASK: u8 = 0xfe;
+const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+
+/// Which rules apply to a tx.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PolicyScope {
+    /// A tx about to be broadcast, every rule applies.
+    Complete,
+    /// A tx presigned with `SIGHASH_NONE | ANYONECANPAY` that the committee completes with its
+    /// outputs and fee inputs. Only the rules of the presigned inputs apply: the version,
+    /// the sequences and the witness limits.
+    Presigned,
+}
+
+/// Every relay policy rule `tx` breaks. `spent` looks up the output an input spends, like
+/// for [`Transaction::verify`].
+pub fn check<S>(tx: &Transaction, scope: PolicyScope, mut spent: S) -> Vec<PolicyError>
+where
+    S: FnMut(&OutPoint) -> Option<TxOut>,
+{
+    let mut violations = vec![];
+    let version = tx.version.0;
+    if !(1..=MAX_STANDARD_VERSION).contains(&version) {
+        violations.push(PolicyError::NonStandardVersion(version));
+    }
+    for (index, input) in tx.input.iter().enumerate() {
+        if version < 2 && input.sequence.is_relative_lock_time() {
+            violations.push(PolicyError::RelativeLockIgnored(index, version));
+        }
+    }
+    if tx.lock_time != absolute::LockTime::ZERO && !tx.is_lock_time_enabled() {
+        violations.push(PolicyError::LockTimeIgnored(
+            tx.lock_time.to_consensus_u32(),
+        ));
+    }
+
+    let mut input_value = Some(Amount::ZERO);
+    for (index, input) in tx.input.iter().enumerate() {
+        let Some(prevout) = spent(&input.previous_output) else {
+            violations.push(PolicyError::MissingPrevout(
+                index,
+                input.previous_output.to_string(),
+            ));
+            input_value = None;
+            continue;
+        };
+        input_value = input_value.and_then(|value| value.checked_add(prevout.value));
+        check_witness(
+            index,
+            &input.witness,
+            &prevout.script_pubkey,
+            &mut violations,
+        );
+    }
+
+    if scope == PolicyScope::Presigned {
+        return violations;
+    }
+
+    if tx.output.is_empty() {
+        violations.push(PolicyError::NoOutputs);
+    }
+    let weight = tx.weight().to_wu();
+    if weight > MAX_STANDARD_TX_WEIGHT {
+        violations.push(PolicyError::WeightTooHigh(weight, MAX_STANDARD_TX_WEIGHT));
+    }
+    for (index, output) in tx.output.iter().enumerate() {
+        let threshold = output.script_pubkey.minimal_non_dust();
+        if output.value < threshold {
+            violations.push(PolicyError::DustOutput(
+                index,
+                output.value.to_sat(),
+                threshold.to_sat(),
+            ));
+        }
+    }
+
+    // The fee is only known when every prevout is.
+    if let Some(input_value) = input_value {
+        let output_value = tx.output.iter().map(|output| output.value.to_sat()).sum();
+        match input_value.to_sat().checked_sub(output_value) {
+            Some(fee) => {
+                let vsize = tx.vsize() as u64;
+                let min_fee = vsize * MIN_RELAY_FEE_RATE;
+                if fee < min_fee {
+                    violations.push(PolicyError::FeeBelowMinRelay(fee, min_fee, vsize));
+                }
+            }
+            None => violations.push(PolicyError::OutputsExceedInputs(
+                output_value,
+                input_value.to_sat(),
+            )),
+        }
+    }
+
+    violations
+}
+
+/// Fails with every relay policy rule `tx` breaks, see [`check`].
+pub fn ensure<S>(tx: &Transaction, scope: PolicyScope, spent: S) -> Result<(), PolicyError>
+where
+    S: FnMut(&OutPoint) -> Option<TxOut>,
+{
+    let violations = check(tx, scope, spent);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(PolicyError::Violations(tx.compute_txid(), violations))
+    }
+}
+
+/// Witness limits of the p2wsh and taproot spends, other witness programs are not used by
+/// the protocol and left to the backend.
+fn check_witness(
+    index: usize,
+    witness: &Witness,
+    script_pubkey: &Script,
+    violations: &mut Vec<PolicyError>,
+) {
+    let mut items = witness.iter().collect::<Vec<_>>();
+    if script_pubkey.is_p2wsh() {
+        let Some(script) = items.pop() else {
+            return;
+        };
+        if script.len() > MAX_STANDARD_P2WSH_SCRIPT_SIZE {
+            violations.push(PolicyError::WitnessScriptTooLarge(
+                index,
+                script.len(),
+                MAX_STANDARD_P2WSH_SCRIPT_SIZE,
+            ));
+        }
+        if items.len() > MAX_STANDARD_P2WSH_STACK_ITEMS {
+            violations.push(PolicyError::WitnessStackTooLarge(
+                index,
+                items.len(),
+                MAX_STANDARD_P2WSH_STACK_ITEMS,
+            ));
+        }
+        check_item_sizes(
+            index,
+            &items,
+            MAX_STANDARD_P2WSH_STACK_ITEM_SIZE,
+            violations,
+        );
+    } else if script_pubkey.is_p2tr() {
+        if items.len() >= 2
+            && items
+                .last()
+                .is_some_and(|item| item.first() == Some(&TAPROOT_ANNEX_PREFIX))
+        {
+            violations.push(PolicyError::TaprootAnnex(index));
+            items.pop();
+        }
+        // A key path spend is a single signature, a script path spend ends with the script
+        // and the control block.
+        if items.len() >= 2 {
+            let control_block = items.pop().unwrap_or_default();
+            items.pop();
+            if control_block.first().map(|leaf| leaf & TAPROOT_LEAF_MASK)
+                == Some(TAPROOT_LEAF_TAPSCRIPT)
+            {
+                check_item_sizes(
+                    index,
+                    &items,
+                    MAX_STANDARD_TAPSCRIPT_STACK_ITEM_SIZE,
+                    violations,
+                );
+            }
+        }
+    }
+}
+
+fn check_item_sizes(
+    index: usize,
+    items: &[&[u8]],
+    max_size: usize,
+    violations: &mut Vec<PolicyError>,
+) {
+    for (item_index, item) in items.iter().enumerate() {
+        if item.len() > max_size {
+            violations.push(PolicyError::WitnessItemTooLarge(
+                index,
+                item_index,
+                item.len(),
+                max_size,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{
+        hashes::Hash, opcodes::OP_TRUE, script::Builder, transaction, ScriptBuf, Sequence, TxIn,
+        Txid,
+    };
+
+    use super::*;
+
+    fn p2wsh_true() -> ScriptBuf {
+        Builder::new().push_opcode(OP_TRUE).into_script()
+    }
+
+    fn tx(witness: Witness, outputs: Vec<TxOut>) -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::all_zeros(), 0),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness,
+            }],
+            output: outputs,
+        }
+    }
+
+    fn prevout(value: u64) -> TxOut {
+        TxOut {
+            value: Amount::from_sat(value),
+            script_pubkey: ScriptBuf::new_p2wsh(&p2wsh_true().wscript_hash()),
+        }
+    }
+
+    #[test]
+    fn test_standard_tx() {
+        let witness = Witness::from_slice(&[p2wsh_true().as_bytes()]);
+        let tx = tx(witness, vec![prevout(1_000)]);
+        assert_eq!(
+            check(&tx, PolicyScope::Complete, |_| Some(prevout(2_000))),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_policy_violations() {
+        let witness = Witness::from_slice(&[vec![0; 81], p2wsh_true().to_bytes()]);
+        let mut tx = tx(witness, vec![prevout(300)]);
+        tx.version = transaction::Version(1);
+        tx.input[0].sequence = Sequence::from_height(10);
+        let vsize = tx.vsize() as u64;
+
+        let violations = check(&tx, PolicyScope::Complete, |_| Some(prevout(301)));
+        assert_eq!(
+            violations,
+            vec![
+                PolicyError::RelativeLockIgnored(0, 1),
+                PolicyError::WitnessItemTooLarge(0, 0, 81, MAX_STANDARD_P2WSH_STACK_ITEM_SIZE),
+                PolicyError::DustOutput(0, 300, 330),
+                PolicyError::FeeBelowMinRelay(1, vsize, vsize),
+            ]
+        );
+
+        // The committee adds the outputs and the fee of a presigned tx.
+        tx.output.clear();
+        let violations = check(&tx, PolicyScope::Presigned, |_| None);
+        assert_eq!(
+            violations,
+            vec![
+                PolicyError::RelativeLockIgnored(0, 1),
+                PolicyError::MissingPrevout(0, tx.input[0].previous_output.to_string()),
+            ]
+        );
+        let err = ensure(&tx, PolicyScope::Complete, |_| Some(prevout(301))).unwrap_err();
+        assert!(err.to_string().contains("tx has no outputs"));
+    }
+}
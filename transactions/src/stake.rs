@@ -7,12 +7,16 @@ pub struct StakeTransaction {
 }
 
 impl StakeTransaction {
-    pub fn new(private_key: PrivateKey, input_utxo: P2trUtxo, outputs: Vec<TxOut>) -> Self {
+    pub fn new(
+        private_key: PrivateKey,
+        input_utxo: P2trUtxo,
+        outputs: Vec<TxOut>,
+    ) -> anyhow::Result<Self> {
         let secp = Secp256k1::new();
         let tx = generate_bip86_key_spend_tx(&secp, private_key, input_utxo, outputs)
-            .expect("failed to generate stake tx");
+            .map_err(|e| anyhow::anyhow!("failed to generate stake tx: {}", e))?;
 
-        StakeTransaction { tx }
+        Ok(StakeTransaction { tx })
     }
 
     pub fn extract_tx(&self) -> Transaction {
@@ -0,0 +1,47 @@
+use bitcoin::ScriptBuf;
+use serde::{Deserialize, Serialize};
+
+/// BIP341 NUMS point, the default internal key of the leaf script output since nobody may
+/// spend it through the key path.
+pub const NUMS_INTERNAL_KEY: &str =
+    "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
+/// Position of a witness element in the assert tx: element `element` of input `input`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WitnessRef {
+    pub input: usize,
+    pub element: usize,
+}
+
+/// One chunk of the BitVM2 verifier.
+///
+/// The leaf script checks the intermediate values the committee committed to in the assert tx,
+/// it only succeeds when those values are inconsistent, which is what the disprove tx proves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafScript {
+    pub script: ScriptBuf,
+    /// Assert tx witness elements consumed by the script, in stack order.
+    pub witness: Vec<WitnessRef>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leaf_script_file_format() {
+        let json = r#"[{"script":"5151","witness":[{"input":0,"element":3}]}]"#;
+        let leaves: Vec<LeafScript> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            leaves,
+            vec![LeafScript {
+                script: ScriptBuf::from_hex("5151").unwrap(),
+                witness: vec![WitnessRef {
+                    input: 0,
+                    element: 3
+                }],
+            }]
+        );
+        assert_eq!(serde_json::to_string(&leaves).unwrap(), json);
+    }
+}
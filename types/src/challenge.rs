@@ -1,10 +1,15 @@
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
 
+use crate::{circuit::CircuitType, committee::CommitteeVersionId};
+
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
 pub struct ChallengeRequest {
     pub proof_id: String,
     pub vk_hash: String,
+    /// Committee version the challenge was built against.
+    #[serde(default)]
+    pub committee_version: CommitteeVersionId,
 }
 
 impl ChallengeRequest {
@@ -12,8 +17,14 @@ impl ChallengeRequest {
         Self {
             proof_id: proof_id.to_string(),
             vk_hash: vk_hash.to_string(),
+            committee_version: CommitteeVersionId::default(),
         }
     }
+
+    pub fn with_committee_version(mut self, committee_version: CommitteeVersionId) -> Self {
+        self.committee_version = committee_version;
+        self
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
@@ -31,6 +42,17 @@ impl FinishChallengeRequest {
     }
 }
 
+/// A proof submitted to the committee, the proof is hex of the compressed arkworks
+/// serialization and the public inputs are decimal field elements.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProofRes {
+    pub proof_id: String,
+    pub vk_hash: String,
+    pub circuit_type: CircuitType,
+    pub proof: String,
+    pub public_inputs: Vec<String>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ChallengeInfoRes {
     pub proof_id: String,
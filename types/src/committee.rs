@@ -3,9 +3,20 @@ use serde::{Deserialize, Serialize};
 
 #[repr(u32)]
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, TryFromPrimitive, Serialize, Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    TryFromPrimitive,
+    Serialize,
+    Deserialize,
 )]
 pub enum CommitteeVersionId {
+    #[default]
     Version0 = 0,
     Version1,
     Version2,
@@ -20,3 +31,18 @@ impl CommitteeVersionId {
         Self::Version1
     }
 }
+
+/// Public keys of the committee members of one committee version.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CommitteeKeySet {
+    pub version: CommitteeVersionId,
+    pub public_keys: Vec<String>,
+}
+
+/// Committee versions returned by the version handshake, `next` is only set during a
+/// rotation window, while validators re-presign their txs for the next committee.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CommitteeVersionRes {
+    pub current: CommitteeKeySet,
+    pub next: Option<CommitteeKeySet>,
+}
@@ -1,5 +1,8 @@
 use bitcoin::Amount;
 
+// Tx layout and fees of committee version 0, look them up through `params::ProtocolParams`
+// which also holds the per network stake and challenge amounts.
+
 pub const DUST_AMOUNT: Amount = Amount::from_sat(1000);
 pub const STAKE_VALUE_INDEX: u32 = 0;
 pub const STAKE_TIMELOCK_INDEX: u32 = 1;
@@ -12,13 +15,3 @@ pub const STAKE_FEE_AMOUNT: Amount = Amount::from_sat(300);
 pub const CHALLENGE_FEE_AMOUNT: Amount = Amount::from_sat(300);
 pub const ASSERT_FEE_AMOUNT: Amount = Amount::from_sat(400);
 pub const DISPROVE_FEE_AMOUNT: Amount = Amount::from_sat(1_000);
-
-// Regtest
-// pub const STAKE_AMOUNT: Amount = Amount::from_sat(10_000_000);
-// pub const CHALLENGE_AMOUNT: Amount = Amount::from_sat(5_000_000);
-// pub const COMMITTEE_RESERVE_AMOUNT: Amount = Amount::from_sat(5_000_000);
-
-// Signet
-pub const STAKE_AMOUNT: Amount = Amount::from_sat(3_000);
-pub const CHALLENGE_AMOUNT: Amount = Amount::from_sat(1_000);
-pub const COMMITTEE_RESERVE_AMOUNT: Amount = Amount::from_sat(1_000);
@@ -1,7 +1,8 @@
-use bitcoin::Txid;
+use bitcoin::{Amount, Network, Txid};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, Serialize, Deserialize)]
 pub enum RegisterNodeError {
     #[error("generate multisig address failed with error: {0}")]
     GenerateMultisigAddressError(String),
@@ -113,9 +114,77 @@ pub enum RegisterNodeError {
     InvalidDisproveTxCount(u32, u32),
     #[error("verifier key of circuit hash {0} not found")]
     CircuitVKNotFound(String),
+    #[error("committee is not rotating, current version {0}")]
+    NoCommitteeRotation(String),
 }
 
-#[derive(Debug, Error)]
+impl RegisterNodeError {
+    /// JSON-RPC error code of the variant, part of the committee API: never renumber a
+    /// variant, new variants take the next free code. Never rename a variant either, its
+    /// name is the `data` of the JSON-RPC error.
+    pub fn code(&self) -> i32 {
+        match self {
+            RegisterNodeError::GenerateMultisigAddressError(..) => 1001,
+            RegisterNodeError::RecoverPublicKeyError(..) => 1002,
+            RegisterNodeError::RegisterIDNotFound(..) => 1003,
+            RegisterNodeError::RegisterPkNotFound(..) => 1004,
+            RegisterNodeError::ValidatorKeyRegistered(..) => 1005,
+            RegisterNodeError::StakeTxInvalidLockTime(..) => 1006,
+            RegisterNodeError::StakeTxInvalidVersion(..) => 1007,
+            RegisterNodeError::StakeTxMissing(..) => 1008,
+            RegisterNodeError::StakeTxMissingOutput => 1009,
+            RegisterNodeError::StakeTxInvalidStakeValue(..) => 1010,
+            RegisterNodeError::StakeTxInvalidConnectorValue(..) => 1011,
+            RegisterNodeError::StakeTxInvalidScriptPubkey(..) => 1012,
+            RegisterNodeError::AssertTxNotFound(..) => 1013,
+            RegisterNodeError::AssertTxInvalidVersion(..) => 1014,
+            RegisterNodeError::AssertTxInvalidLockTime(..) => 1015,
+            RegisterNodeError::AssertTxInvalidSequence(..) => 1016,
+            RegisterNodeError::AssertTxMissingInput => 1017,
+            RegisterNodeError::AssertTxInvalidPreviousOutput { .. } => 1018,
+            RegisterNodeError::AssertTxOutputNotEmpty => 1019,
+            RegisterNodeError::AssertTxInvalidInput(..) => 1020,
+            RegisterNodeError::AssertTxNotSegwitTx => 1021,
+            RegisterNodeError::AssertTxInvalidWitnessCount(..) => 1022,
+            RegisterNodeError::AssertTxWitnessInvalidScriptPubKey(..) => 1023,
+            RegisterNodeError::AssertTxRecoverSignatureFailed(..) => 1024,
+            RegisterNodeError::AssertTxVerifySignatureFailed(..) => 1025,
+            RegisterNodeError::AssertTxMissingOutput(..) => 1026,
+            RegisterNodeError::ChallengeTxInvalidVersion(..) => 1027,
+            RegisterNodeError::ChallengeTxInvalidLockTime(..) => 1028,
+            RegisterNodeError::ChallengeTxInvalidSequence(..) => 1029,
+            RegisterNodeError::ChallengeTxMissingInput => 1030,
+            RegisterNodeError::ChallengeTxInvalidPreviousOutput { .. } => 1031,
+            RegisterNodeError::ChallengeTxOutputNotEmpty => 1032,
+            RegisterNodeError::ChallengeTxInvalidInput(..) => 1033,
+            RegisterNodeError::ChallengeTxNotSegwitTx => 1034,
+            RegisterNodeError::ChallengeTxInvalidWitnessCount(..) => 1035,
+            RegisterNodeError::ChallengeTxWitnessInvalidScriptPubKey(..) => 1036,
+            RegisterNodeError::ChallengeTxRecoverSignatureFailed(..) => 1037,
+            RegisterNodeError::ChallengeTxVerifySignatureFailed(..) => 1038,
+            RegisterNodeError::DisproveTxInvalidVersion(..) => 1039,
+            RegisterNodeError::DisproveTxInvalidLockTime(..) => 1040,
+            RegisterNodeError::DisproveTxInvalidSequence(..) => 1041,
+            RegisterNodeError::DisproveTxMissingInput => 1042,
+            RegisterNodeError::DisproveTxInvalidPreviousOutput { .. } => 1043,
+            RegisterNodeError::DisproveTxOutputNotEmpty => 1044,
+            RegisterNodeError::DisproveTxInvalidInput(..) => 1045,
+            RegisterNodeError::DisproveTxNotSegwitTx => 1046,
+            RegisterNodeError::DisproveTxInvalidWitnessCount(..) => 1047,
+            RegisterNodeError::DisproveTxWitnessInvalidScriptPubKey(..) => 1048,
+            RegisterNodeError::DisproveTxRecoverSignatureFailed(..) => 1049,
+            RegisterNodeError::DisproveTxVerifySignatureFailed(..) => 1050,
+            RegisterNodeError::ComputeSigHashP2wshFailed(..) => 1051,
+            RegisterNodeError::TweakedCommitteeKeyNotFound => 1052,
+            RegisterNodeError::CommitteePrivatekeyNotFound(..) => 1053,
+            RegisterNodeError::InvalidDisproveTxCount(..) => 1054,
+            RegisterNodeError::CircuitVKNotFound(..) => 1055,
+            RegisterNodeError::NoCommitteeRotation(..) => 1056,
+        }
+    }
+}
+
+#[derive(Debug, Error, Serialize, Deserialize)]
 pub enum ChallengeError {
     #[error("register id of validator {0} is missing")]
     RegisterIDNotFound(String),
@@ -151,9 +220,40 @@ pub enum ChallengeError {
     ChallengeRawCommitteeTxNotReady,
     #[error("Circuit of hash {0} is not ready or exist")]
     CircuitNotRegistered(String),
+    #[error("proof {0} is not a proof of circuit {1}")]
+    ProofCircuitMismatch(String, String),
+    #[error("proof {0} is valid, pass --force to challenge it anyway")]
+    ProofIsValid(String),
 }
 
-#[derive(Debug, Error)]
+impl ChallengeError {
+    /// JSON-RPC error code of the variant, see [`RegisterNodeError::code`].
+    pub fn code(&self) -> i32 {
+        match self {
+            ChallengeError::RegisterIDNotFound(..) => 2001,
+            ChallengeError::StakeTxNotFound(..) => 2002,
+            ChallengeError::PublicKeyNotFound(..) => 2003,
+            ChallengeError::PublicKeyParseFailed(..) => 2004,
+            ChallengeError::AssertTxNotFound(..) => 2005,
+            ChallengeError::AssertTxOfProofNotFound(..) => 2006,
+            ChallengeError::ChallengeTxNotFound(..) => 2007,
+            ChallengeError::ChallengeIDNotFound(..) => 2008,
+            ChallengeError::DisproveTxNotFound(..) => 2009,
+            ChallengeError::CommitteePrivatekeyNotFound(..) => 2010,
+            ChallengeError::AssertTxNodeSignatureNotFound(..) => 2011,
+            ChallengeError::AssertTxPublicScriptNotFound(..) => 2012,
+            ChallengeError::ChallengeTxDeserializeError(..) => 2013,
+            ChallengeError::QueryChallengeDataFailed(..) => 2014,
+            ChallengeError::ChallengeExists(..) => 2015,
+            ChallengeError::ChallengeRawCommitteeTxNotReady => 2016,
+            ChallengeError::CircuitNotRegistered(..) => 2017,
+            ChallengeError::ProofCircuitMismatch(..) => 2018,
+            ChallengeError::ProofIsValid(..) => 2019,
+        }
+    }
+}
+
+#[derive(Debug, Error, Serialize, Deserialize)]
 pub enum DisproveError {
     #[error("generate multisig address failed with error: {0}")]
     GenerateMultisigAddressError(String),
@@ -187,9 +287,39 @@ pub enum DisproveError {
     TweakedCommitteeKeyNotFound,
     #[error("failed to get tweaked public key of register {0}")]
     TweakedRegisterNotFound(String),
+    #[error("connector C of the assert tx does not commit to the leaf scripts")]
+    ConnectorCMismatch,
+    #[error("disprove reward of {0} sats is below the dust limit of {1} sats")]
+    RewardBelowDust(u64, u64),
 }
 
-#[derive(Debug, Error)]
+impl DisproveError {
+    /// JSON-RPC error code of the variant, see [`RegisterNodeError::code`].
+    pub fn code(&self) -> i32 {
+        match self {
+            DisproveError::GenerateMultisigAddressError(..) => 3001,
+            DisproveError::NetworkParseError(..) => 3002,
+            DisproveError::ProofIDNotFound(..) => 3003,
+            DisproveError::ChallengerAddressParseError(..) => 3004,
+            DisproveError::AssertTxNotFound(..) => 3005,
+            DisproveError::DisproveTxNotFound(..) => 3006,
+            DisproveError::ChallengeIDNotFound(..) => 3007,
+            DisproveError::RegisterIDNotFound(..) => 3008,
+            DisproveError::RegisterKeyNotFound(..) => 3009,
+            DisproveError::AssertTxIsNotConfirmed(..) => 3010,
+            DisproveError::ScriptInvalid(..) => 3011,
+            DisproveError::WitnessInvalid(..) => 3012,
+            DisproveError::WrongScriptInedx(..) => 3013,
+            DisproveError::FailedToCreateControl => 3014,
+            DisproveError::TweakedCommitteeKeyNotFound => 3015,
+            DisproveError::TweakedRegisterNotFound(..) => 3016,
+            DisproveError::ConnectorCMismatch => 3017,
+            DisproveError::RewardBelowDust(..) => 3018,
+        }
+    }
+}
+
+#[derive(Debug, Error, Serialize, Deserialize)]
 pub enum CircuitError {
     #[error("invalid circuit with hash {0}")]
     InvalidCircuitHash(String),
@@ -199,4 +329,348 @@ pub enum CircuitError {
     CircuitNotExists(String),
     #[error("circuit with hash {0} is invalid")]
     CircuitInvalid(String),
+    #[error("verify key stored for circuit {0} does not match its hash")]
+    VerifyKeyMismatch(String),
+    #[error("assert tx of circuit {0} does not commit to the local leaf scripts")]
+    ScriptCommitmentMismatch(String),
+    #[error("assert tx of circuit {0} commits to leaf scripts missing from the circuit store")]
+    ScriptsNotStored(String),
+}
+
+impl CircuitError {
+    /// JSON-RPC error code of the variant, see [`RegisterNodeError::code`].
+    pub fn code(&self) -> i32 {
+        match self {
+            CircuitError::InvalidCircuitHash(..) => 4001,
+            CircuitError::CircuitAlreadyExists(..) => 4002,
+            CircuitError::CircuitNotExists(..) => 4003,
+            CircuitError::CircuitInvalid(..) => 4004,
+            CircuitError::VerifyKeyMismatch(..) => 4005,
+            CircuitError::ScriptCommitmentMismatch(..) => 4006,
+            CircuitError::ScriptsNotStored(..) => 4007,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TransactionError {
+    #[error("invalid value {0} for transaction type")]
+    InvalidTransactionType(i64),
+    #[error("transaction id {0:?} is invalid with error: {1}")]
+    InvalidTxId(Vec<u8>, String),
+    #[error("transaction {0} has invalid data with error: {1}")]
+    InvalidData(String, String),
+    #[error("transaction {0} has invalid register id {1}")]
+    InvalidRegisterId(String, i64),
+}
+
+#[derive(Debug, Error)]
+pub enum VkError {
+    #[error("failed to decode verify key: {0}")]
+    Decode(String),
+    #[error("invalid field element {0}")]
+    InvalidFieldElement(String),
+    #[error("point {0} of verify key is not on the curve")]
+    PointNotOnCurve(String),
+    #[error("point {0} of verify key is not in the prime order subgroup")]
+    PointNotInSubgroup(String),
+    #[error("point {0} of verify key is the identity")]
+    PointAtInfinity(String),
+    #[error("verify key has no IC points")]
+    EmptyIc,
+    #[error("parameter {0} of verify key is invalid")]
+    InvalidParameter(String),
+    #[error("unsupported verify key: {0}")]
+    Unsupported(String),
+}
+
+#[derive(Debug, Error)]
+pub enum ProofError {
+    #[error("failed to decode proof: {0}")]
+    Decode(String),
+    #[error("invalid public input {0}")]
+    InvalidPublicInput(String),
+    #[error("expected {0} public inputs, got {1}")]
+    PublicInputCount(usize, usize),
+    #[error("invalid verify key: {0}")]
+    InvalidVk(#[from] VkError),
+    #[error("unsupported proof: {0}")]
+    Unsupported(String),
+}
+
+#[derive(Debug, Error)]
+pub enum GraphError {
+    #[error("stake tx layout of the protocol params is invalid: {0}")]
+    InvalidLayout(String),
+    #[error("input {0} does not spend an output of the tx graph")]
+    UnknownPrevout(String),
+    #[error("tx {0} spends {1} sats into {2} sats, expected a fee of {3} sats")]
+    Unbalanced(Txid, u64, u64, u64),
+    #[error("output {0} holds {1} sats but its spending tx was signed for {2} sats")]
+    EdgeValueMismatch(String, u64, u64),
+    #[error("output {0} is locked to {1} but its spending tx expects {2}")]
+    EdgeScriptMismatch(String, String, String),
+    #[error("tx {0} misses output {1}")]
+    MissingOutput(Txid, u32),
+    #[error("assert tx {0} does not spend the stake tx of the graph")]
+    ForeignAssertTx(Txid),
+}
+
+/// Relay policy rules of bitcoin core a tx breaks, see `transactions::policy`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum PolicyError {
+    #[error("version {0} is not standard, only versions 1 and 2 are relayed")]
+    NonStandardVersion(i32),
+    #[error("tx has no outputs")]
+    NoOutputs,
+    #[error("weight of {0} wu exceeds the standard maximum of {1} wu")]
+    WeightTooHigh(u64, u64),
+    #[error("prevout {1} of input {0} is unknown")]
+    MissingPrevout(usize, String),
+    #[error("outputs of {0} sats exceed the inputs of {1} sats")]
+    OutputsExceedInputs(u64, u64),
+    #[error("fee of {0} sats is below the minimum relay fee of {1} sats for {2} vbytes")]
+    FeeBelowMinRelay(u64, u64, u64),
+    #[error("output {0} of {1} sats is dust, the threshold of its script type is {2} sats")]
+    DustOutput(usize, u64, u64),
+    #[error("input {0} has {1} witness stack items, at most {2} are standard")]
+    WitnessStackTooLarge(usize, usize, usize),
+    #[error("witness item {1} of input {0} has {2} bytes, at most {3} are standard")]
+    WitnessItemTooLarge(usize, usize, usize, usize),
+    #[error("witness script of input {0} has {1} bytes, at most {2} are standard")]
+    WitnessScriptTooLarge(usize, usize, usize),
+    #[error("input {0} carries a taproot annex")]
+    TaprootAnnex(usize),
+    #[error("input {0} sets a relative timelock, version {1} txs do not enforce it")]
+    RelativeLockIgnored(usize, i32),
+    #[error("lock time {0} is not enforced, every input sequence is final")]
+    LockTimeIgnored(u32),
+    #[error(
+        "tx {0} violates the relay policy:\n  {}",
+        .1.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n  ")
+    )]
+    Violations(Txid, Vec<PolicyError>),
+}
+
+/// Registration bundle passed between the online and the offline machine, see
+/// `types::offline`.
+#[derive(Debug, Error)]
+pub enum BundleError {
+    #[error("bundle version {0} is not supported, expected version {1}")]
+    UnsupportedVersion(u32, u32),
+    #[error("bundle was prepared for public key {0}, the signing key has public key {1}")]
+    KeyMismatch(String, String),
+    #[error("bundle was prepared for network {0}, not {1}")]
+    NetworkMismatch(String, String),
+    #[error("bundle of validator {0} holds no assert txs to presign disprove txs for")]
+    NoAssertTxs(String),
+    #[error(
+        "bundle params differ from the protocol params of its network and committee version: {0}"
+    )]
+    ParamsMismatch(String),
+    #[error("multi sig script of the bundle does not include the signing key {0}")]
+    MultiSigMismatch(String),
+    #[error("funding output {0} of the bundle is not locked to the signing key")]
+    FundingMismatch(String),
+}
+
+/// Protocol params lookup, see `types::params`.
+#[derive(Debug, Error)]
+pub enum ParamsError {
+    #[error("no protocol params for bitcoin network {0}, only regtest and signet are supported")]
+    UnsupportedNetwork(Network),
+}
+
+/// Failure categories of fcli, each one exits with its own code, see [`FcliError::exit_code`].
+#[derive(Debug, Error)]
+pub enum FcliError {
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("invalid private key: {0}")]
+    Key(String),
+    #[error("committee rpc error: {0}")]
+    Committee(String),
+    #[error("validation failed: {0}")]
+    Validation(String),
+    #[error("insufficient funds: funding input holds {available}, {required} needed")]
+    InsufficientFunds { available: Amount, required: Amount },
+}
+
+impl FcliError {
+    /// Exit code of any error not covered by a category.
+    pub const EXIT_OTHER: i32 = 1;
+    pub const EXIT_INVALID_ARGUMENT: i32 = 2;
+    pub const EXIT_KEY: i32 = 3;
+    /// Exit code of a `bitcoin_client::errors::ChainError`, bitcoin backend failures carry no
+    /// category of their own.
+    pub const EXIT_CHAIN: i32 = 4;
+    pub const EXIT_COMMITTEE: i32 = 5;
+    pub const EXIT_VALIDATION: i32 = 6;
+    pub const EXIT_INSUFFICIENT_FUNDS: i32 = 7;
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FcliError::InvalidArgument(_) => Self::EXIT_INVALID_ARGUMENT,
+            FcliError::Key(_) => Self::EXIT_KEY,
+            FcliError::Committee(_) => Self::EXIT_COMMITTEE,
+            FcliError::Validation(_) => Self::EXIT_VALIDATION,
+            FcliError::InsufficientFunds { .. } => Self::EXIT_INSUFFICIENT_FUNDS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::de::DeserializeOwned;
+    use serde_json::json;
+
+    use super::*;
+
+    /// Decodes every `(code, name)` from the wire and checks it encodes back to the same name.
+    fn check_wire_names<E: Serialize + DeserializeOwned>(
+        wire: &[(i32, &str)],
+        code: fn(&E) -> i32,
+    ) {
+        let txid = "0".repeat(64);
+        for &(expected, name) in wire {
+            let payloads = [
+                json!(name),
+                json!({ name: "0" }),
+                json!({ name: 0 }),
+                json!({ name: ["0", "0"] }),
+                json!({ name: [0, 0] }),
+                json!({ name: { "txid": txid, "vout": 0 } }),
+            ];
+            let (payload, err) = payloads
+                .into_iter()
+                .find_map(|p| serde_json::from_value::<E>(p.clone()).ok().map(|e| (p, e)))
+                .unwrap_or_else(|| panic!("{} does not decode", name));
+            assert_eq!(code(&err), expected, "{}", name);
+            assert_eq!(serde_json::to_value(&err).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn test_wire_names() {
+        check_wire_names::<RegisterNodeError>(
+            &[
+                (1001, "GenerateMultisigAddressError"),
+                (1002, "RecoverPublicKeyError"),
+                (1003, "RegisterIDNotFound"),
+                (1004, "RegisterPkNotFound"),
+                (1005, "ValidatorKeyRegistered"),
+                (1006, "StakeTxInvalidLockTime"),
+                (1007, "StakeTxInvalidVersion"),
+                (1008, "StakeTxMissing"),
+                (1009, "StakeTxMissingOutput"),
+                (1010, "StakeTxInvalidStakeValue"),
+                (1011, "StakeTxInvalidConnectorValue"),
+                (1012, "StakeTxInvalidScriptPubkey"),
+                (1013, "AssertTxNotFound"),
+                (1014, "AssertTxInvalidVersion"),
+                (1015, "AssertTxInvalidLockTime"),
+                (1016, "AssertTxInvalidSequence"),
+                (1017, "AssertTxMissingInput"),
+                (1018, "AssertTxInvalidPreviousOutput"),
+                (1019, "AssertTxOutputNotEmpty"),
+                (1020, "AssertTxInvalidInput"),
+                (1021, "AssertTxNotSegwitTx"),
+                (1022, "AssertTxInvalidWitnessCount"),
+                (1023, "AssertTxWitnessInvalidScriptPubKey"),
+                (1024, "AssertTxRecoverSignatureFailed"),
+                (1025, "AssertTxVerifySignatureFailed"),
+                (1026, "AssertTxMissingOutput"),
+                (1027, "ChallengeTxInvalidVersion"),
+                (1028, "ChallengeTxInvalidLockTime"),
+                (1029, "ChallengeTxInvalidSequence"),
+                (1030, "ChallengeTxMissingInput"),
+                (1031, "ChallengeTxInvalidPreviousOutput"),
+                (1032, "ChallengeTxOutputNotEmpty"),
+                (1033, "ChallengeTxInvalidInput"),
+                (1034, "ChallengeTxNotSegwitTx"),
+                (1035, "ChallengeTxInvalidWitnessCount"),
+                (1036, "ChallengeTxWitnessInvalidScriptPubKey"),
+                (1037, "ChallengeTxRecoverSignatureFailed"),
+                (1038, "ChallengeTxVerifySignatureFailed"),
+                (1039, "DisproveTxInvalidVersion"),
+                (1040, "DisproveTxInvalidLockTime"),
+                (1041, "DisproveTxInvalidSequence"),
+                (1042, "DisproveTxMissingInput"),
+                (1043, "DisproveTxInvalidPreviousOutput"),
+                (1044, "DisproveTxOutputNotEmpty"),
+                (1045, "DisproveTxInvalidInput"),
+                (1046, "DisproveTxNotSegwitTx"),
+                (1047, "DisproveTxInvalidWitnessCount"),
+                (1048, "DisproveTxWitnessInvalidScriptPubKey"),
+                (1049, "DisproveTxRecoverSignatureFailed"),
+                (1050, "DisproveTxVerifySignatureFailed"),
+                (1051, "ComputeSigHashP2wshFailed"),
+                (1052, "TweakedCommitteeKeyNotFound"),
+                (1053, "CommitteePrivatekeyNotFound"),
+                (1054, "InvalidDisproveTxCount"),
+                (1055, "CircuitVKNotFound"),
+                (1056, "NoCommitteeRotation"),
+            ],
+            RegisterNodeError::code,
+        );
+        check_wire_names::<ChallengeError>(
+            &[
+                (2001, "RegisterIDNotFound"),
+                (2002, "StakeTxNotFound"),
+                (2003, "PublicKeyNotFound"),
+                (2004, "PublicKeyParseFailed"),
+                (2005, "AssertTxNotFound"),
+                (2006, "AssertTxOfProofNotFound"),
+                (2007, "ChallengeTxNotFound"),
+                (2008, "ChallengeIDNotFound"),
+                (2009, "DisproveTxNotFound"),
+                (2010, "CommitteePrivatekeyNotFound"),
+                (2011, "AssertTxNodeSignatureNotFound"),
+                (2012, "AssertTxPublicScriptNotFound"),
+                (2013, "ChallengeTxDeserializeError"),
+                (2014, "QueryChallengeDataFailed"),
+                (2015, "ChallengeExists"),
+                (2016, "ChallengeRawCommitteeTxNotReady"),
+                (2017, "CircuitNotRegistered"),
+                (2018, "ProofCircuitMismatch"),
+                (2019, "ProofIsValid"),
+            ],
+            ChallengeError::code,
+        );
+        check_wire_names::<DisproveError>(
+            &[
+                (3001, "GenerateMultisigAddressError"),
+                (3002, "NetworkParseError"),
+                (3003, "ProofIDNotFound"),
+                (3004, "ChallengerAddressParseError"),
+                (3005, "AssertTxNotFound"),
+                (3006, "DisproveTxNotFound"),
+                (3007, "ChallengeIDNotFound"),
+                (3008, "RegisterIDNotFound"),
+                (3009, "RegisterKeyNotFound"),
+                (3010, "AssertTxIsNotConfirmed"),
+                (3011, "ScriptInvalid"),
+                (3012, "WitnessInvalid"),
+                (3013, "WrongScriptInedx"),
+                (3014, "FailedToCreateControl"),
+                (3015, "TweakedCommitteeKeyNotFound"),
+                (3016, "TweakedRegisterNotFound"),
+                (3017, "ConnectorCMismatch"),
+                (3018, "RewardBelowDust"),
+            ],
+            DisproveError::code,
+        );
+        check_wire_names::<CircuitError>(
+            &[
+                (4001, "InvalidCircuitHash"),
+                (4002, "CircuitAlreadyExists"),
+                (4003, "CircuitNotExists"),
+                (4004, "CircuitInvalid"),
+                (4005, "VerifyKeyMismatch"),
+                (4006, "ScriptCommitmentMismatch"),
+                (4007, "ScriptsNotStored"),
+            ],
+            CircuitError::code,
+        );
+    }
 }
@@ -5,40 +5,56 @@ use std::{
     str::FromStr,
 };
 
-use crate::{circuit::CircuitType, error::CircuitError};
+use crate::{bitvm::LeafScript, circuit::CircuitType, error::CircuitError};
 
 const CHUNK_SIZE: usize = 4096;
 
-pub fn write_vk_to_file(vk_hash: &str, vk: &[u8]) -> anyhow::Result<()> {
-    let file_path = get_vk_path(vk_hash, CircuitType::Groth16);
+pub const CIRCUITS_DIR_NAME: &str = "circuits-metadata";
+pub const VK_FILE_NAME: &str = "verify-key";
+pub const BUNDLES_DIR_NAME: &str = "bundles";
 
-    let path = Path::new(&file_path);
-
-    if path.exists() {
-        fs::remove_file(path)?;
-    }
+pub fn write_vk_to_file(
+    data_dir: &Path,
+    vk_hash: &str,
+    circuit_type: CircuitType,
+    vk: &[u8],
+) -> anyhow::Result<()> {
+    write_file_atomic(&get_vk_path(data_dir, vk_hash, circuit_type), vk)
+}
 
+/// Writes `data` to a temporary file next to `path` and renames it over `path`, so readers
+/// never see a partially written file.
+pub fn write_file_atomic(path: &Path, data: &[u8]) -> anyhow::Result<()> {
     if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)?;
+        fs::create_dir_all(parent)?;
     }
 
-    let file = std::fs::OpenOptions::new()
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(".tmp-{}", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let file = fs::OpenOptions::new()
         .write(true)
         .create(true)
-        .open(file_path)?;
+        .truncate(true)
+        .open(&tmp_path)?;
     let mut writer = BufWriter::new(file);
-
-    for chunk in vk.chunks(CHUNK_SIZE) {
+    for chunk in data.chunks(CHUNK_SIZE) {
         writer.write_all(chunk)?;
     }
-
     writer.flush()?;
+    writer.get_ref().sync_all()?;
 
+    fs::rename(&tmp_path, path)?;
     Ok(())
 }
 
-pub fn read_vk_from_file(vk_hash: &str) -> anyhow::Result<Vec<u8>> {
-    let file_path = get_vk_path(vk_hash, CircuitType::Groth16);
+pub fn read_vk_from_file(
+    data_dir: &Path,
+    vk_hash: &str,
+    circuit_type: CircuitType,
+) -> anyhow::Result<Vec<u8>> {
+    let file_path = get_vk_path(data_dir, vk_hash, circuit_type);
 
     read_vk_from_path(file_path.to_str().unwrap())
 }
@@ -67,33 +83,78 @@ pub fn read_vk_from_path(path: &str) -> anyhow::Result<Vec<u8>> {
     Ok(vk_bytes)
 }
 
-pub fn get_vk_path(vk_hash: &str, circuit_type: CircuitType) -> PathBuf {
-    let mut base_path = std::env::current_dir().expect("Failed to determine the current directory");
-    base_path.push("circuits-metadata");
-    base_path.push(circuit_type.to_string());
-    base_path.push(vk_hash);
-    base_path.push("verify-key");
-    base_path
+/// Reads the BitVM2 leaf scripts stored as a JSON list of [`LeafScript`], in taproot leaf
+/// order. See the README for the file format and how to convert the committee's scripts.
+pub fn read_leaf_scripts_from_path(path: &Path) -> anyhow::Result<Vec<LeafScript>> {
+    if !path.exists() {
+        anyhow::bail!("leaf scripts {} not found", path.display())
+    }
+
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// Reads the leaf scripts of a circuit, the validator's own `leaf-scripts-<validator>` when
+/// present, otherwise the `context-scripts` shared by all validators.
+pub fn read_circuit_leaf_scripts(
+    data_dir: &Path,
+    vk_hash: &str,
+    validator_key: &str,
+    circuit_type: CircuitType,
+) -> anyhow::Result<Vec<LeafScript>> {
+    let leaf_script_path =
+        get_leaf_script_path(data_dir, vk_hash, validator_key, circuit_type.clone());
+    if leaf_script_path.exists() {
+        return read_leaf_scripts_from_path(&leaf_script_path);
+    }
+
+    read_leaf_scripts_from_path(&get_context_script_path(data_dir, vk_hash, circuit_type))
+}
+
+/// Root of the circuit metadata, `circuits-metadata` under the data directory.
+pub fn get_circuits_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(CIRCUITS_DIR_NAME)
+}
+
+/// Directory of one circuit under the circuit metadata root `root`.
+pub fn get_circuit_dir(root: &Path, vk_hash: &str, circuit_type: CircuitType) -> PathBuf {
+    root.join(circuit_type.to_string()).join(vk_hash)
+}
+
+pub fn get_vk_path(data_dir: &Path, vk_hash: &str, circuit_type: CircuitType) -> PathBuf {
+    get_circuit_dir(&get_circuits_dir(data_dir), vk_hash, circuit_type).join(VK_FILE_NAME)
 }
 
-pub fn get_groth16_context_script_path(vk_hash: &str, circuit_type: CircuitType) -> PathBuf {
-    let mut base_path = std::env::current_dir().expect("Failed to determine the current directory");
-    base_path.push("circuits-metadata");
-    base_path.push(circuit_type.to_string());
-    base_path.push(vk_hash);
-    base_path.push("context-scripts");
-    base_path
+pub fn get_context_script_path(
+    data_dir: &Path,
+    vk_hash: &str,
+    circuit_type: CircuitType,
+) -> PathBuf {
+    get_circuit_dir(&get_circuits_dir(data_dir), vk_hash, circuit_type).join("context-scripts")
 }
 
-pub fn get_groth16_leaf_script_path(
+pub fn get_leaf_script_path(
+    data_dir: &Path,
     vk_hash: &str,
     validator_key: &str,
     circuit_type: CircuitType,
 ) -> PathBuf {
-    let mut base_path = std::env::current_dir().expect("Failed to determine the current directory");
-    base_path.push("circuits-metadata");
-    base_path.push(circuit_type.to_string());
-    base_path.push(vk_hash);
-    base_path.push(format!("leaf-scripts-{}", validator_key));
-    base_path
+    get_circuit_dir(&get_circuits_dir(data_dir), vk_hash, circuit_type)
+        .join(format!("leaf-scripts-{}", validator_key))
+}
+
+/// Presigned tx bundle of a validator, see `presigned_transactions`.
+pub fn get_presigned_bundle_path(data_dir: &Path, validator_key: &str) -> PathBuf {
+    data_dir
+        .join(BUNDLES_DIR_NAME)
+        .join(format!("{}.json", validator_key))
+}
+
+/// Default root directory of the local fcli state, `~/.fcli`.
+pub fn default_data_dir() -> PathBuf {
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."));
+    home.join(".fcli")
 }
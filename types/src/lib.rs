@@ -1,6 +1,8 @@
+use committee::CommitteeVersionId;
 use register::CircuitTx;
 use serde::{Deserialize, Serialize};
 
+pub mod bitvm;
 pub mod challenge;
 pub mod circuit;
 pub mod committee;
@@ -8,6 +10,8 @@ pub mod constants;
 pub mod disprove;
 pub mod error;
 pub mod file;
+pub mod offline;
+pub mod params;
 pub mod presigned_transactions;
 pub mod register;
 pub mod storage_transaction;
@@ -20,6 +24,9 @@ pub struct RegisterRequest {
     pub stake_tx: Vec<u8>,
     pub assert_tx: Vec<u8>,
     pub challenge_tx: Vec<u8>,
+    /// Committee version the txs were presigned for.
+    #[serde(default)]
+    pub committee_version: CommitteeVersionId,
 }
 
 impl RegisterRequest {
@@ -39,8 +46,14 @@ impl RegisterRequest {
             stake_tx,
             assert_tx,
             challenge_tx,
+            committee_version: CommitteeVersionId::default(),
         }
     }
+
+    pub fn with_committee_version(mut self, committee_version: CommitteeVersionId) -> Self {
+        self.committee_version = committee_version;
+        self
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
@@ -0,0 +1,63 @@
+//! Registration bundles of the air-gapped registration: an online machine without keys
+//! prepares an [`UnsignedRegistration`], an offline machine signs it into a
+//! [`SignedRegistration`] and the online machine submits that to the committee.
+
+use bitcoin::{OutPoint, ScriptBuf, TxOut};
+use serde::{Deserialize, Serialize};
+
+use crate::{params::ProtocolParams, register::CircuitTx};
+
+/// Format version of the registration bundles, bumped on every incompatible change.
+pub const REGISTRATION_BUNDLE_VERSION: u32 = 1;
+
+/// Everything the offline machine needs to presign a registration step.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UnsignedRegistration {
+    pub version: u32,
+    pub validator_key: String,
+    /// Public key of the validator's bitcoin key, the signing key must match it.
+    pub public_key: String,
+    /// Params of the committee version the txs are presigned for.
+    pub params: ProtocolParams,
+    /// Multi sig script of the committee and the validator, locking the stake.
+    pub multi_sig_script: ScriptBuf,
+    #[serde(flatten)]
+    pub step: UnsignedStep,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum UnsignedStep {
+    /// Presign the stake, assert and challenge txs, funded by the UTXO.
+    Start {
+        funding_outpoint: OutPoint,
+        funding_txout: TxOut,
+    },
+    /// Presign the disprove txs of the assert txs the committee generated.
+    Finish { assert_txs: Vec<CircuitTx> },
+}
+
+/// Presigned txs of a registration step, ready to submit.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignedRegistration {
+    pub version: u32,
+    pub validator_key: String,
+    pub public_key: String,
+    pub params: ProtocolParams,
+    #[serde(flatten)]
+    pub step: SignedStep,
+}
+
+/// Txs are hex encoded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum SignedStep {
+    Start {
+        stake_tx: String,
+        assert_tx: String,
+        challenge_tx: String,
+    },
+    Finish {
+        disprove_txs: Vec<CircuitTx>,
+    },
+}
@@ -0,0 +1,228 @@
+use bitcoin::{Amount, Network};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    circuit::CircuitType,
+    committee::CommitteeVersionId,
+    constants::{
+        ASSERT_FEE_AMOUNT, CHALLENGE_FEE_AMOUNT, CHALLENGE_FEE_INDEX, CONNECTOR_A_INDEX,
+        CONNECTOR_B_INDEX, CONNECTOR_C_INDEX, DISPROVE_FEE_AMOUNT, DUST_AMOUNT, STAKE_FEE_AMOUNT,
+        STAKE_TIMELOCK_INDEX, STAKE_VALUE_INDEX,
+    },
+    error::ParamsError,
+};
+
+/// Amounts, fees and output indices of the stake, assert, challenge and disprove txs.
+///
+/// The amounts depend on the bitcoin network and the layout of the txs on the committee
+/// version, so they are looked up with [`ProtocolParams::new`] instead of being compiled in.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolParams {
+    pub network: Network,
+    pub committee_version: CommitteeVersionId,
+
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub stake_amount: Amount,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub challenge_amount: Amount,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub committee_reserve_amount: Amount,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub dust_amount: Amount,
+
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub stake_fee: Amount,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub challenge_fee: Amount,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub assert_fee: Amount,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub disprove_fee: Amount,
+
+    /// Output of the stake tx holding the stake, spent by the assert tx.
+    pub stake_value_index: u32,
+    pub stake_timelock_index: u32,
+    /// Output of the stake tx spent by the challenge tx.
+    pub connector_a_index: u32,
+    /// Output of the stake tx spent by the assert tx.
+    pub connector_b_index: u32,
+    /// Output of the assert tx committing to the disprove scripts.
+    pub connector_c_index: u32,
+    pub challenge_fee_index: u32,
+}
+
+impl ProtocolParams {
+    /// Params of `network`, which must be one the committee runs on: there are no amounts
+    /// for the other networks yet.
+    pub fn new(
+        network: Network,
+        committee_version: CommitteeVersionId,
+    ) -> Result<Self, ParamsError> {
+        // Only the amounts differ between networks. The local committee runs on regtest with
+        // the signet amounts, so a local setup keeps staking what it always did.
+        let (stake_amount, challenge_amount, committee_reserve_amount) = match network {
+            Network::Regtest | Network::Signet => (
+                Amount::from_sat(3_000),
+                Amount::from_sat(1_000),
+                Amount::from_sat(1_000),
+            ),
+            _ => return Err(ParamsError::UnsupportedNetwork(network)),
+        };
+
+        // All committee versions so far share the tx layout of version 0.
+        match committee_version {
+            CommitteeVersionId::Version0
+            | CommitteeVersionId::Version1
+            | CommitteeVersionId::Version2 => Ok(Self {
+                network,
+                committee_version,
+                stake_amount,
+                challenge_amount,
+                committee_reserve_amount,
+                dust_amount: DUST_AMOUNT,
+                stake_fee: STAKE_FEE_AMOUNT,
+                challenge_fee: CHALLENGE_FEE_AMOUNT,
+                assert_fee: ASSERT_FEE_AMOUNT,
+                disprove_fee: DISPROVE_FEE_AMOUNT,
+                stake_value_index: STAKE_VALUE_INDEX,
+                stake_timelock_index: STAKE_TIMELOCK_INDEX,
+                connector_a_index: CONNECTOR_A_INDEX,
+                connector_b_index: CONNECTOR_B_INDEX,
+                connector_c_index: CONNECTOR_C_INDEX,
+                challenge_fee_index: CHALLENGE_FEE_INDEX,
+            }),
+        }
+    }
+
+    /// Value of the stake tx output spent by the assert tx, the stake plus the assert fee.
+    pub fn stake_output_amount(&self) -> Amount {
+        self.stake_amount.unchecked_add(self.assert_fee)
+    }
+
+    /// Describes every field in which `other`, the committee's params, differs from these.
+    pub fn mismatches(&self, other: &ProtocolParams) -> Vec<String> {
+        let mut mismatches = vec![];
+        if self.network != other.network {
+            mismatches.push(format!(
+                "network: expected {}, committee runs on {}",
+                self.network, other.network
+            ));
+        }
+        if self.committee_version != other.committee_version {
+            mismatches.push(format!(
+                "committee version: expected {:?}, committee is at {:?}",
+                self.committee_version, other.committee_version
+            ));
+        }
+
+        let amounts = [
+            ("stake amount", self.stake_amount, other.stake_amount),
+            (
+                "challenge amount",
+                self.challenge_amount,
+                other.challenge_amount,
+            ),
+            (
+                "committee reserve amount",
+                self.committee_reserve_amount,
+                other.committee_reserve_amount,
+            ),
+            ("dust amount", self.dust_amount, other.dust_amount),
+            ("stake fee", self.stake_fee, other.stake_fee),
+            ("challenge fee", self.challenge_fee, other.challenge_fee),
+            ("assert fee", self.assert_fee, other.assert_fee),
+            ("disprove fee", self.disprove_fee, other.disprove_fee),
+        ];
+        for (name, expected, actual) in amounts {
+            if expected != actual {
+                mismatches.push(format!(
+                    "{}: expected {} sats, committee uses {} sats",
+                    name,
+                    expected.to_sat(),
+                    actual.to_sat()
+                ));
+            }
+        }
+
+        let indices = [
+            (
+                "stake value index",
+                self.stake_value_index,
+                other.stake_value_index,
+            ),
+            (
+                "stake timelock index",
+                self.stake_timelock_index,
+                other.stake_timelock_index,
+            ),
+            (
+                "connector A index",
+                self.connector_a_index,
+                other.connector_a_index,
+            ),
+            (
+                "connector B index",
+                self.connector_b_index,
+                other.connector_b_index,
+            ),
+            (
+                "connector C index",
+                self.connector_c_index,
+                other.connector_c_index,
+            ),
+            (
+                "challenge fee index",
+                self.challenge_fee_index,
+                other.challenge_fee_index,
+            ),
+        ];
+        for (name, expected, actual) in indices {
+            if expected != actual {
+                mismatches.push(format!(
+                    "{}: expected {}, committee uses {}",
+                    name, expected, actual
+                ));
+            }
+        }
+
+        mismatches
+    }
+
+    /// Amount the stake input must cover besides the change: the stake output, the timelock
+    /// and connector A, B outputs and the stake fee.
+    pub fn stake_input_amount(&self) -> Amount {
+        self.stake_fee
+            .unchecked_add(self.stake_output_amount())
+            .unchecked_add(self.dust_amount * 3)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolParamsRes {
+    pub params: ProtocolParams,
+    pub circuit_types: Vec<CircuitType>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amounts_per_network() {
+        let version = CommitteeVersionId::latest();
+
+        for network in [Network::Regtest, Network::Signet] {
+            let params = ProtocolParams::new(network, version).unwrap();
+            assert_eq!(params.stake_amount, Amount::from_sat(3_000));
+            assert_eq!(params.challenge_amount, Amount::from_sat(1_000));
+            assert_eq!(params.committee_reserve_amount, Amount::from_sat(1_000));
+        }
+
+        for network in [Network::Bitcoin, Network::Testnet] {
+            assert!(matches!(
+                ProtocolParams::new(network, version),
+                Err(ParamsError::UnsupportedNetwork(n)) if n == network
+            ));
+        }
+    }
+}
@@ -1,26 +1,67 @@
-#[derive(Debug)]
+use bitcoin::{Network, OutPoint, ScriptBuf, TxOut, Txid};
+use serde::{Deserialize, Serialize};
+
+use crate::transaction::TransactionType;
+
+/// Format version of the presigned tx bundle, bumped on every incompatible change.
+pub const PRESIGNED_BUNDLE_VERSION: u32 = 1;
+
+/// Every presigned tx of a validator with what is needed to broadcast it without the
+/// committee: the outputs its inputs spend and their witness scripts.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PresignedTransactions {
+    pub version: u32,
     pub validator_key: String,
-    pub circuit_hash: String,
-    pub taproot_address: String,
-    pub committee_assert_tx: String,
-    pub committee_challenge_tx: String,
+    pub network: Network,
+    pub txs: Vec<PresignedTx>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PresignedTx {
+    pub tx_type: TransactionType,
+    /// Vk hash of the circuit of an assert or disprove tx, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_hash: Option<String>,
+    pub txid: Txid,
+    pub tx_hex: String,
+    /// One per input, in input order.
+    pub inputs: Vec<PresignedInput>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PresignedInput {
+    pub outpoint: OutPoint,
+    /// Output the input spends, `None` when neither the bundle nor the chain knew it.
+    pub prevout: Option<TxOut>,
+    /// Witness script of a p2wsh input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub witness_script: Option<ScriptBuf>,
 }
 
 impl PresignedTransactions {
-    pub fn new(
-        validator_key: &str,
-        circuit_hash: &str,
-        taproot_address: &str,
-        committee_assert_tx: &str,
-        committee_challenge_tx: &str,
-    ) -> Self {
+    pub fn new(validator_key: &str, network: Network, txs: Vec<PresignedTx>) -> Self {
         Self {
+            version: PRESIGNED_BUNDLE_VERSION,
             validator_key: validator_key.to_string(),
-            circuit_hash: circuit_hash.to_string(),
-            taproot_address: taproot_address.to_string(),
-            committee_assert_tx: committee_assert_tx.to_string(),
-            committee_challenge_tx: committee_challenge_tx.to_string(),
+            network,
+            txs,
         }
     }
+
+    /// Txs with the txid or the tx type, e.g. `stake_tx`, `selector`.
+    pub fn select(&self, selector: &str) -> Vec<&PresignedTx> {
+        self.txs
+            .iter()
+            .filter(|tx| tx.txid.to_string() == selector || tx.tx_type.to_string() == selector)
+            .collect()
+    }
+
+    /// Output `outpoint` spends as recorded in the bundle.
+    pub fn prevout(&self, outpoint: &OutPoint) -> Option<TxOut> {
+        self.txs
+            .iter()
+            .flat_map(|tx| &tx.inputs)
+            .find(|input| input.outpoint == *outpoint)
+            .and_then(|input| input.prevout.clone())
+    }
 }
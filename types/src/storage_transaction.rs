@@ -1,4 +1,7 @@
-use crate::transaction::{FiammaTransaction, TransactionType};
+use crate::{
+    error::TransactionError,
+    transaction::{FiammaTransaction, TransactionType},
+};
 
 #[derive(Debug, Clone)]
 pub struct StorageFiammaTransaction {
@@ -6,28 +9,46 @@ pub struct StorageFiammaTransaction {
     pub tx_type: i64,
     pub data: Vec<u8>,
     pub ext_info: Option<serde_json::Value>,
-    pub register_id: i64,
+    pub register_id: Option<i64>,
 }
 
-impl From<StorageFiammaTransaction> for FiammaTransaction {
-    fn from(tx: StorageFiammaTransaction) -> Self {
-        let tx_id = String::from_utf8(tx.tx_id.clone()).unwrap_or_else(|e| {
-            panic!(
-                "Transaction ID {:#?} is invalid for transaction. Error: {}",
-                tx.tx_id, e
-            )
-        });
-        let tx_type = TransactionType::from(tx.tx_type as i32);
-        let data = String::from_utf8(tx.data).unwrap_or_else(|e| {
-            panic!("Transaction ID {:#?} has invalid data. Error: {}", tx_id, e)
-        });
+impl TryFrom<StorageFiammaTransaction> for FiammaTransaction {
+    type Error = TransactionError;
+
+    fn try_from(tx: StorageFiammaTransaction) -> Result<Self, Self::Error> {
+        let tx_id = String::from_utf8(tx.tx_id.clone())
+            .map_err(|e| TransactionError::InvalidTxId(tx.tx_id, e.to_string()))?;
+        let tx_type = i32::try_from(tx.tx_type)
+            .map_err(|_| TransactionError::InvalidTransactionType(tx.tx_type))
+            .and_then(TransactionType::try_from)?;
+        let data = String::from_utf8(tx.data)
+            .map_err(|e| TransactionError::InvalidData(tx_id.clone(), e.to_string()))?;
         let ext_info = tx.ext_info.and_then(|v| v.as_str().map(|s| s.to_string()));
-        Self {
+        let register_id = tx
+            .register_id
+            .map(|id| {
+                u32::try_from(id)
+                    .map_err(|_| TransactionError::InvalidRegisterId(tx_id.clone(), id))
+            })
+            .transpose()?;
+        Ok(Self {
             tx_id,
             tx_type,
             data,
             ext_info,
-            register_id: tx.register_id as u32,
+            register_id,
+        })
+    }
+}
+
+impl From<FiammaTransaction> for StorageFiammaTransaction {
+    fn from(tx: FiammaTransaction) -> Self {
+        Self {
+            tx_id: tx.tx_id.into_bytes(),
+            tx_type: i32::from(tx.tx_type) as i64,
+            data: tx.data.into_bytes(),
+            ext_info: tx.ext_info.map(serde_json::Value::String),
+            register_id: tx.register_id.map(i64::from),
         }
     }
 }
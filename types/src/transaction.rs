@@ -2,6 +2,8 @@ use bitcoin::{consensus::encode, Transaction};
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
 
+use crate::error::TransactionError;
+
 #[derive(Debug, EnumString, Display, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum TransactionType {
     #[strum(serialize = "stake_tx")]
@@ -14,14 +16,27 @@ pub enum TransactionType {
     DisproveTx,
 }
 
-impl From<i32> for TransactionType {
-    fn from(value: i32) -> Self {
+impl TryFrom<i32> for TransactionType {
+    type Error = TransactionError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
         match value {
-            0 => Self::StakeTx,
-            1 => Self::ChallengeTx,
-            2 => Self::AssertTx,
-            3 => Self::DisproveTx,
-            _ => panic!("Invalid value for TransactionType"),
+            0 => Ok(Self::StakeTx),
+            1 => Ok(Self::ChallengeTx),
+            2 => Ok(Self::AssertTx),
+            3 => Ok(Self::DisproveTx),
+            _ => Err(TransactionError::InvalidTransactionType(value as i64)),
+        }
+    }
+}
+
+impl From<TransactionType> for i32 {
+    fn from(value: TransactionType) -> Self {
+        match value {
+            TransactionType::StakeTx => 0,
+            TransactionType::ChallengeTx => 1,
+            TransactionType::AssertTx => 2,
+            TransactionType::DisproveTx => 3,
         }
     }
 }
@@ -48,11 +63,12 @@ pub struct FiammaTransaction {
     pub tx_type: TransactionType,
     pub data: String,
     pub ext_info: Option<String>,
-    pub register_id: u32,
+    /// Registration the tx belongs to, `None` for the txs of a challenger.
+    pub register_id: Option<u32>,
 }
 
 impl FiammaTransaction {
-    pub fn new_stake_tx(raw_tx: &Transaction, register_id: u32) -> Self {
+    pub fn new_stake_tx(raw_tx: &Transaction, register_id: Option<u32>) -> Self {
         let tx_id = raw_tx.compute_txid().to_string();
         Self {
             tx_id,
@@ -63,7 +79,7 @@ impl FiammaTransaction {
         }
     }
 
-    pub fn new_challenge_tx(raw_tx: &Transaction, register_id: u32) -> Self {
+    pub fn new_challenge_tx(raw_tx: &Transaction, register_id: Option<u32>) -> Self {
         let tx_id = raw_tx.compute_txid().to_string();
         Self {
             tx_id,
@@ -74,7 +90,7 @@ impl FiammaTransaction {
         }
     }
 
-    pub fn new_assert_tx(raw_tx: &Transaction, register_id: u32) -> Self {
+    pub fn new_assert_tx(raw_tx: &Transaction, register_id: Option<u32>) -> Self {
         let tx_id = raw_tx.compute_txid().to_string();
         Self {
             tx_id,
@@ -85,7 +101,7 @@ impl FiammaTransaction {
         }
     }
 
-    pub fn new_disprove_tx(raw_tx: &Transaction, register_id: u32) -> Self {
+    pub fn new_disprove_tx(raw_tx: &Transaction, register_id: Option<u32>) -> Self {
         let tx_id = raw_tx.compute_txid().to_string();
         Self {
             tx_id,
@@ -95,4 +111,9 @@ impl FiammaTransaction {
             register_id,
         }
     }
+
+    pub fn with_ext_info(mut self, ext_info: &str) -> Self {
+        self.ext_info = Some(ext_info.to_string());
+        self
+    }
 }
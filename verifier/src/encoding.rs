@@ -0,0 +1,96 @@
+use std::str::FromStr;
+
+use ark_bn254::{Fq, Fq2, G1Affine, G2Affine};
+use ark_ec::{
+    short_weierstrass::{Affine, SWCurveConfig},
+    AffineRepr,
+};
+use ark_ff::{One, PrimeField, Zero};
+use num_bigint::BigUint;
+use types::error::VkError;
+
+/// Checks that `point` is on the curve and in the prime order subgroup.
+pub(crate) fn check_point<P: SWCurveConfig>(
+    name: &str,
+    point: &Affine<P>,
+    allow_infinity: bool,
+) -> Result<(), VkError> {
+    if point.is_zero() {
+        return match allow_infinity {
+            true => Ok(()),
+            false => Err(VkError::PointAtInfinity(name.to_string())),
+        };
+    }
+    if !point.is_on_curve() {
+        return Err(VkError::PointNotOnCurve(name.to_string()));
+    }
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(VkError::PointNotInSubgroup(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Converts `value` into a field element, rejecting values not below the modulus.
+pub(crate) fn field_from_biguint<F: PrimeField>(
+    value: BigUint,
+    repr: impl Fn() -> String,
+) -> Result<F, VkError> {
+    F::BigInt::try_from(value)
+        .ok()
+        .and_then(F::from_bigint)
+        .ok_or_else(|| VkError::InvalidFieldElement(repr()))
+}
+
+/// Parses a field element written as a decimal string, as snarkjs does.
+pub(crate) fn snarkjs_field<F: PrimeField>(value: &str) -> Result<F, VkError> {
+    let n =
+        BigUint::from_str(value).map_err(|_| VkError::InvalidFieldElement(value.to_string()))?;
+    field_from_biguint(n, || value.to_string())
+}
+
+fn snarkjs_fq2(value: &[String]) -> Result<Fq2, VkError> {
+    match value {
+        [c0, c1] => Ok(Fq2::new(snarkjs_field(c0)?, snarkjs_field(c1)?)),
+        _ => Err(VkError::Decode(format!(
+            "expected 2 coordinates, got {}",
+            value.len()
+        ))),
+    }
+}
+
+/// Builds an affine point from snarkjs projective coordinates, which are normalized to
+/// `z = 1`, or `z = 0` for the identity.
+fn snarkjs_point<P: SWCurveConfig>(
+    name: &str,
+    coords: &[P::BaseField],
+) -> Result<Affine<P>, VkError> {
+    match coords {
+        [_, _, z] if z.is_zero() => Ok(Affine::identity()),
+        [x, y, z] if z.is_one() => Ok(Affine::new_unchecked(*x, *y)),
+        [_, _, _] => Err(VkError::Unsupported(format!(
+            "point {} is not normalized",
+            name
+        ))),
+        _ => Err(VkError::Decode(format!(
+            "point {} has {} coordinates",
+            name,
+            coords.len()
+        ))),
+    }
+}
+
+pub(crate) fn snarkjs_g1(name: &str, value: &[String]) -> Result<G1Affine, VkError> {
+    let coords = value
+        .iter()
+        .map(|c| snarkjs_field::<Fq>(c))
+        .collect::<Result<Vec<_>, _>>()?;
+    snarkjs_point(name, &coords)
+}
+
+pub(crate) fn snarkjs_g2(name: &str, value: &[Vec<String>]) -> Result<G2Affine, VkError> {
+    let coords = value
+        .iter()
+        .map(|c| snarkjs_fq2(c))
+        .collect::<Result<Vec<_>, _>>()?;
+    snarkjs_point(name, &coords)
+}
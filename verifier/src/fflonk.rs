@@ -0,0 +1,252 @@
+use ark_bn254::{Fr, G1Affine, G2Affine};
+use ark_ff::{FftField, Field, One, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::Deserialize;
+use types::error::VkError;
+
+use crate::{
+    encoding::{check_point, snarkjs_field, snarkjs_g1, snarkjs_g2},
+    VkFormat,
+};
+
+/// A Fflonk verifying key over BN254, as produced by `snarkjs fflonk setup`.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct FflonkVerifyingKey {
+    pub n_public: u32,
+    /// The circuit domain has `2^power` elements.
+    pub power: u32,
+    pub k1: Fr,
+    pub k2: Fr,
+    /// Generator of the circuit domain.
+    pub w: Fr,
+    pub w3: Fr,
+    pub w4: Fr,
+    pub w8: Fr,
+    pub wr: Fr,
+    pub x_2: G2Affine,
+    pub c0: G1Affine,
+}
+
+/// Decodes a `vk.bitvm` Fflonk verify key, the compressed arkworks serialization, and
+/// validates it.
+pub fn decode_vk(bytes: &[u8]) -> Result<FflonkVerifyingKey, VkError> {
+    let mut reader = bytes;
+    let vk = FflonkVerifyingKey::deserialize_compressed_unchecked(&mut reader)
+        .map_err(|e| VkError::Decode(e.to_string()))?;
+    if !reader.is_empty() {
+        return Err(VkError::Decode(format!(
+            "{} trailing bytes after the verify key",
+            reader.len()
+        )));
+    }
+
+    validate_vk(&vk)?;
+    Ok(vk)
+}
+
+/// Encodes the verify key in the `vk.bitvm` encoding.
+pub fn encode_vk(vk: &FflonkVerifyingKey) -> Vec<u8> {
+    let mut bytes = vec![];
+    vk.serialize_compressed(&mut bytes)
+        .expect("serializing to a vec never fails");
+    bytes
+}
+
+/// Converts a verify key serialized in `format` and validates it. Only snarkjs exports
+/// Fflonk keys.
+pub fn convert_vk(bytes: &[u8], format: VkFormat) -> Result<FflonkVerifyingKey, VkError> {
+    match format {
+        VkFormat::Bitvm => decode_vk(bytes),
+        VkFormat::Snarkjs => {
+            let vk = from_snarkjs(bytes)?;
+            validate_vk(&vk)?;
+            Ok(vk)
+        }
+        VkFormat::Gnark | VkFormat::Arkworks => Err(VkError::Unsupported(format!(
+            "{} fflonk verify key",
+            format
+        ))),
+    }
+}
+
+/// Whether `root` is a primitive `2^log_order`-th root of unity.
+fn is_primitive_root(root: &Fr, log_order: u32) -> bool {
+    let mut power = *root;
+    for _ in 1..log_order {
+        power.square_in_place();
+    }
+    // `power` is now root^(2^(log_order - 1)), which must be -1 for a primitive root.
+    log_order > 0 && power == -Fr::one()
+}
+
+/// Checks the domain parameters and the points of the verify key.
+pub fn validate_vk(vk: &FflonkVerifyingKey) -> Result<(), VkError> {
+    if vk.power == 0 || vk.power > Fr::TWO_ADICITY {
+        return Err(VkError::InvalidParameter("power".into()));
+    }
+    if !is_primitive_root(&vk.w, vk.power) {
+        return Err(VkError::InvalidParameter("w".into()));
+    }
+    if vk.w3.is_one() || vk.w3.pow([3]) != Fr::one() {
+        return Err(VkError::InvalidParameter("w3".into()));
+    }
+    if !is_primitive_root(&vk.w4, 2) {
+        return Err(VkError::InvalidParameter("w4".into()));
+    }
+    if !is_primitive_root(&vk.w8, 3) {
+        return Err(VkError::InvalidParameter("w8".into()));
+    }
+    if vk.k1.is_zero() || vk.k2.is_zero() || vk.k1 == vk.k2 {
+        return Err(VkError::InvalidParameter("k1, k2".into()));
+    }
+
+    check_point("x_2", &vk.x_2, false)?;
+    check_point("c0", &vk.c0, false)?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct SnarkjsVk {
+    protocol: String,
+    curve: String,
+    #[serde(rename = "nPublic")]
+    n_public: u32,
+    power: u32,
+    k1: String,
+    k2: String,
+    w: String,
+    w3: String,
+    w4: String,
+    w8: String,
+    wr: String,
+    #[serde(rename = "X_2")]
+    x_2: Vec<Vec<String>>,
+    #[serde(rename = "C0")]
+    c0: Vec<String>,
+}
+
+/// Parses the `verification_key.json` exported by snarkjs.
+pub fn from_snarkjs(json: &[u8]) -> Result<FflonkVerifyingKey, VkError> {
+    let vk: SnarkjsVk = serde_json::from_slice(json).map_err(|e| VkError::Decode(e.to_string()))?;
+
+    if vk.protocol != "fflonk" {
+        return Err(VkError::Unsupported(format!("protocol {}", vk.protocol)));
+    }
+    if vk.curve != "bn128" && vk.curve != "bn254" {
+        return Err(VkError::Unsupported(format!("curve {}", vk.curve)));
+    }
+
+    Ok(FflonkVerifyingKey {
+        n_public: vk.n_public,
+        power: vk.power,
+        k1: snarkjs_field(&vk.k1)?,
+        k2: snarkjs_field(&vk.k2)?,
+        w: snarkjs_field(&vk.w)?,
+        w3: snarkjs_field(&vk.w3)?,
+        w4: snarkjs_field(&vk.w4)?,
+        w8: snarkjs_field(&vk.w8)?,
+        wr: snarkjs_field(&vk.wr)?,
+        x_2: snarkjs_g2("x_2", &vk.x_2)?,
+        c0: snarkjs_g1("c0", &vk.c0)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::{AffineRepr, CurveGroup};
+    use ark_ff::PrimeField;
+    use num_bigint::BigUint;
+
+    use super::*;
+
+    /// Primitive `2^log_order`-th root of unity.
+    fn root_of_unity(log_order: u32) -> Fr {
+        let mut root = Fr::TWO_ADIC_ROOT_OF_UNITY;
+        for _ in log_order..Fr::TWO_ADICITY {
+            root.square_in_place();
+        }
+        root
+    }
+
+    fn test_vk() -> FflonkVerifyingKey {
+        // A primitive cube root of unity is g^((r - 1) / 3) for a generator g.
+        let third = (BigUint::from(Fr::MODULUS) - 1u32) / 3u32;
+        FflonkVerifyingKey {
+            n_public: 2,
+            power: 10,
+            k1: Fr::from(2u64),
+            k2: Fr::from(3u64),
+            w: root_of_unity(10),
+            w3: Fr::GENERATOR.pow(third.to_u64_digits()),
+            w4: root_of_unity(2),
+            w8: root_of_unity(3),
+            wr: root_of_unity(12),
+            x_2: (G2Affine::generator() * Fr::from(5u64)).into_affine(),
+            c0: (G1Affine::generator() * Fr::from(7u64)).into_affine(),
+        }
+    }
+
+    fn decimal<F: PrimeField>(value: &F) -> String {
+        let value: BigUint = value.into_bigint().into();
+        value.to_string()
+    }
+
+    #[test]
+    fn test_bitvm_encoding_roundtrip() {
+        let vk = test_vk();
+        let bytes = encode_vk(&vk);
+        assert_eq!(decode_vk(&bytes).unwrap(), vk);
+        assert!(decode_vk(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_parameters_are_rejected() {
+        let mut vk = test_vk();
+        vk.w = root_of_unity(9);
+        assert!(matches!(
+            validate_vk(&vk),
+            Err(VkError::InvalidParameter(name)) if name == "w"
+        ));
+
+        let mut vk = test_vk();
+        vk.w3 = Fr::one();
+        assert!(matches!(
+            validate_vk(&vk),
+            Err(VkError::InvalidParameter(name)) if name == "w3"
+        ));
+
+        let mut vk = test_vk();
+        vk.c0 = G1Affine::identity();
+        assert!(matches!(
+            validate_vk(&vk),
+            Err(VkError::PointAtInfinity(name)) if name == "c0"
+        ));
+    }
+
+    #[test]
+    fn test_from_snarkjs() {
+        let vk = test_vk();
+        let json = serde_json::json!({
+            "protocol": "fflonk",
+            "curve": "bn128",
+            "nPublic": vk.n_public,
+            "power": vk.power,
+            "k1": decimal(&vk.k1),
+            "k2": decimal(&vk.k2),
+            "w": decimal(&vk.w),
+            "w3": decimal(&vk.w3),
+            "w4": decimal(&vk.w4),
+            "w8": decimal(&vk.w8),
+            "wr": decimal(&vk.wr),
+            "X_2": [
+                [decimal(&vk.x_2.x.c0), decimal(&vk.x_2.x.c1)],
+                [decimal(&vk.x_2.y.c0), decimal(&vk.x_2.y.c1)],
+                ["1", "0"],
+            ],
+            "C0": [decimal(&vk.c0.x), decimal(&vk.c0.y), "1"],
+        });
+
+        let bytes = serde_json::to_vec(&json).unwrap();
+        assert_eq!(convert_vk(&bytes, VkFormat::Snarkjs).unwrap(), vk);
+    }
+}
@@ -0,0 +1,542 @@
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ff::Zero;
+use ark_groth16::{prepare_verifying_key, Groth16, Proof, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use num_bigint::BigUint;
+use serde::Deserialize;
+use types::error::{ProofError, VkError};
+
+use crate::{
+    encoding::{check_point, field_from_biguint, snarkjs_g1, snarkjs_g2},
+    VkFormat,
+};
+
+pub type Groth16VerifyingKey = VerifyingKey<Bn254>;
+pub type Groth16Proof = Proof<Bn254>;
+
+/// Decodes a `vk.bitvm` verify key, the compressed arkworks serialization, and validates it.
+pub fn decode_vk(bytes: &[u8]) -> Result<Groth16VerifyingKey, VkError> {
+    let vk = deserialize_exact(bytes, true)?;
+    validate_vk(&vk)?;
+    Ok(vk)
+}
+
+/// Encodes the verify key in the `vk.bitvm` encoding.
+pub fn encode_vk(vk: &Groth16VerifyingKey) -> Vec<u8> {
+    let mut bytes = vec![];
+    vk.serialize_compressed(&mut bytes)
+        .expect("serializing to a vec never fails");
+    bytes
+}
+
+/// Converts a verify key serialized in `format` and validates it.
+pub fn convert_vk(bytes: &[u8], format: VkFormat) -> Result<Groth16VerifyingKey, VkError> {
+    let vk = match format {
+        VkFormat::Bitvm => deserialize_exact(bytes, true)?,
+        VkFormat::Arkworks => {
+            deserialize_exact(bytes, true).or_else(|_| deserialize_exact(bytes, false))?
+        }
+        VkFormat::Snarkjs => from_snarkjs(bytes)?,
+        VkFormat::Gnark => from_gnark(bytes)?,
+    };
+    validate_vk(&vk)?;
+    Ok(vk)
+}
+
+/// Checks that every point of the verify key is a valid, non-trivial curve point.
+pub fn validate_vk(vk: &Groth16VerifyingKey) -> Result<(), VkError> {
+    check_point("alpha_g1", &vk.alpha_g1, false)?;
+    check_point("beta_g2", &vk.beta_g2, false)?;
+    check_point("gamma_g2", &vk.gamma_g2, false)?;
+    check_point("delta_g2", &vk.delta_g2, false)?;
+
+    if vk.gamma_abc_g1.is_empty() {
+        return Err(VkError::EmptyIc);
+    }
+    for (i, point) in vk.gamma_abc_g1.iter().enumerate() {
+        check_point(&format!("ic[{}]", i), point, true)?;
+    }
+    Ok(())
+}
+
+/// Deserializes an arkworks verify key, rejecting trailing bytes. Points are validated by
+/// [`validate_vk`] so that errors name the offending point.
+fn deserialize_exact(bytes: &[u8], compressed: bool) -> Result<Groth16VerifyingKey, VkError> {
+    let mut reader = bytes;
+    let vk = match compressed {
+        true => Groth16VerifyingKey::deserialize_compressed_unchecked(&mut reader),
+        false => Groth16VerifyingKey::deserialize_uncompressed_unchecked(&mut reader),
+    }
+    .map_err(|e| VkError::Decode(e.to_string()))?;
+
+    if !reader.is_empty() {
+        return Err(VkError::Decode(format!(
+            "{} trailing bytes after the verify key",
+            reader.len()
+        )));
+    }
+    Ok(vk)
+}
+
+#[derive(Deserialize)]
+struct SnarkjsVk {
+    protocol: String,
+    curve: String,
+    #[serde(rename = "nPublic")]
+    n_public: usize,
+    vk_alpha_1: Vec<String>,
+    vk_beta_2: Vec<Vec<String>>,
+    vk_gamma_2: Vec<Vec<String>>,
+    vk_delta_2: Vec<Vec<String>>,
+    #[serde(rename = "IC")]
+    ic: Vec<Vec<String>>,
+}
+
+/// Parses the `verification_key.json` exported by snarkjs.
+pub fn from_snarkjs(json: &[u8]) -> Result<Groth16VerifyingKey, VkError> {
+    let vk: SnarkjsVk = serde_json::from_slice(json).map_err(|e| VkError::Decode(e.to_string()))?;
+
+    if vk.protocol != "groth16" {
+        return Err(VkError::Unsupported(format!("protocol {}", vk.protocol)));
+    }
+    if vk.curve != "bn128" && vk.curve != "bn254" {
+        return Err(VkError::Unsupported(format!("curve {}", vk.curve)));
+    }
+    if vk.ic.len() != vk.n_public + 1 {
+        return Err(VkError::Decode(format!(
+            "{} IC points for {} public inputs",
+            vk.ic.len(),
+            vk.n_public
+        )));
+    }
+
+    Ok(Groth16VerifyingKey {
+        alpha_g1: snarkjs_g1("alpha_g1", &vk.vk_alpha_1)?,
+        beta_g2: snarkjs_g2("beta_g2", &vk.vk_beta_2)?,
+        gamma_g2: snarkjs_g2("gamma_g2", &vk.vk_gamma_2)?,
+        delta_g2: snarkjs_g2("delta_g2", &vk.vk_delta_2)?,
+        gamma_abc_g1: vk
+            .ic
+            .iter()
+            .enumerate()
+            .map(|(i, point)| snarkjs_g1(&format!("ic[{}]", i), point))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+/// Decodes a proof in the compressed arkworks serialization.
+pub fn decode_proof(bytes: &[u8]) -> Result<Groth16Proof, ProofError> {
+    let mut reader = bytes;
+    let proof = Groth16Proof::deserialize_compressed(&mut reader)
+        .map_err(|e| ProofError::Decode(e.to_string()))?;
+    if !reader.is_empty() {
+        return Err(ProofError::Decode(format!(
+            "{} trailing bytes after the proof",
+            reader.len()
+        )));
+    }
+    Ok(proof)
+}
+
+/// Verifies the proof against the verify key and public inputs.
+pub fn verify_proof(
+    vk: &Groth16VerifyingKey,
+    proof: &Groth16Proof,
+    public_inputs: &[Fr],
+) -> Result<bool, ProofError> {
+    let expected = vk
+        .gamma_abc_g1
+        .len()
+        .checked_sub(1)
+        .ok_or(VkError::EmptyIc)?;
+    if public_inputs.len() != expected {
+        return Err(ProofError::PublicInputCount(expected, public_inputs.len()));
+    }
+
+    let pvk = prepare_verifying_key(vk);
+    Groth16::<Bn254>::verify_proof(&pvk, proof, public_inputs)
+        .map_err(|e| ProofError::Decode(e.to_string()))
+}
+
+const GNARK_MASK: u8 = 0b11 << 6;
+const GNARK_UNCOMPRESSED: u8 = 0b00 << 6;
+const GNARK_UNCOMPRESSED_INFINITY: u8 = 0b01 << 6;
+const GNARK_COMPRESSED_INFINITY: u8 = 0b01 << 6;
+const GNARK_COMPRESSED_SMALLEST: u8 = 0b10 << 6;
+const GNARK_COMPRESSED_LARGEST: u8 = 0b11 << 6;
+const GNARK_FQ_SIZE: usize = 32;
+
+/// Reads gnark encoded values. The points of a key are either all compressed or all raw, the
+/// infinity flag is the same in both encodings.
+struct GnarkReader<'a> {
+    bytes: &'a [u8],
+    compressed: bool,
+}
+
+impl<'a> GnarkReader<'a> {
+    /// Tells the encoding of the key from its first point, alpha, which is never the identity.
+    fn new(bytes: &'a [u8]) -> Result<Self, VkError> {
+        let mut reader = Self {
+            bytes,
+            compressed: false,
+        };
+        reader.compressed = match reader.flag()? {
+            GNARK_UNCOMPRESSED => false,
+            GNARK_COMPRESSED_SMALLEST | GNARK_COMPRESSED_LARGEST => true,
+            _ => return Err(VkError::PointAtInfinity("alpha_g1".into())),
+        };
+        Ok(reader)
+    }
+
+    fn take(&mut self, size: usize) -> Result<&'a [u8], VkError> {
+        if self.bytes.len() < size {
+            return Err(VkError::Decode("unexpected end of gnark verify key".into()));
+        }
+        let (head, tail) = self.bytes.split_at(size);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, VkError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes(bytes.try_into().expect("4 bytes")))
+    }
+
+    /// Reads the flag bits of the next point without consuming them.
+    fn flag(&self) -> Result<u8, VkError> {
+        self.bytes
+            .first()
+            .map(|b| b & GNARK_MASK)
+            .ok_or_else(|| VkError::Decode("unexpected end of gnark verify key".into()))
+    }
+
+    /// Reads a big endian field element, clearing the flag bits of its first byte.
+    fn fq(&mut self, clear_flag: bool) -> Result<Fq, VkError> {
+        let mut bytes = self.take(GNARK_FQ_SIZE)?.to_vec();
+        if clear_flag {
+            bytes[0] &= !GNARK_MASK;
+        }
+        field_from_biguint(BigUint::from_bytes_be(&bytes), || hex::encode(&bytes))
+    }
+
+    fn fq2(&mut self, clear_flag: bool) -> Result<Fq2, VkError> {
+        // gnark writes the imaginary part first.
+        let c1 = self.fq(clear_flag)?;
+        let c0 = self.fq(false)?;
+        Ok(Fq2::new(c0, c1))
+    }
+
+    fn point<P: SWCurveConfig>(
+        &mut self,
+        name: &str,
+        read: fn(&mut Self, bool) -> Result<P::BaseField, VkError>,
+    ) -> Result<Affine<P>, VkError> {
+        match (self.compressed, self.flag()?) {
+            (false, flag @ (GNARK_UNCOMPRESSED | GNARK_UNCOMPRESSED_INFINITY)) => {
+                let x = read(self, true)?;
+                let y = read(self, false)?;
+                // gnark writes the identity as (0, 0), or flags it.
+                match flag == GNARK_UNCOMPRESSED_INFINITY || (x.is_zero() && y.is_zero()) {
+                    true => Ok(Affine::identity()),
+                    false => Ok(Affine::new_unchecked(x, y)),
+                }
+            }
+            (true, GNARK_COMPRESSED_INFINITY) => {
+                read(self, true)?;
+                Ok(Affine::identity())
+            }
+            (true, flag @ (GNARK_COMPRESSED_SMALLEST | GNARK_COMPRESSED_LARGEST)) => {
+                let x = read(self, true)?;
+                Affine::get_point_from_x_unchecked(x, flag == GNARK_COMPRESSED_LARGEST)
+                    .ok_or_else(|| VkError::PointNotOnCurve(name.to_string()))
+            }
+            (compressed, flag) => Err(VkError::Decode(format!(
+                "{} has flag {:#04x} in a {} gnark verify key",
+                name,
+                flag,
+                if compressed { "compressed" } else { "raw" }
+            ))),
+        }
+    }
+
+    fn g1(&mut self, name: &str) -> Result<G1Affine, VkError> {
+        self.point(name, Self::fq)
+    }
+
+    fn g2(&mut self, name: &str) -> Result<G2Affine, VkError> {
+        self.point(name, Self::fq2)
+    }
+}
+
+/// Parses a BN254 verify key written by gnark's `WriteTo` (compressed) or `WriteRawTo`.
+/// Keys with Pedersen commitments are not supported.
+pub fn from_gnark(bytes: &[u8]) -> Result<Groth16VerifyingKey, VkError> {
+    let mut reader = GnarkReader::new(bytes)?;
+
+    let alpha_g1 = reader.g1("alpha_g1")?;
+    let _beta_g1 = reader.g1("beta_g1")?;
+    let beta_g2 = reader.g2("beta_g2")?;
+    let gamma_g2 = reader.g2("gamma_g2")?;
+    let _delta_g1 = reader.g1("delta_g1")?;
+    let delta_g2 = reader.g2("delta_g2")?;
+
+    let ic_len = reader.u32()?;
+    let gamma_abc_g1 = (0..ic_len)
+        .map(|i| reader.g1(&format!("ic[{}]", i)))
+        .collect::<Result<_, _>>()?;
+
+    // Newer gnark versions append the commitment info, which is empty without commitments.
+    if !reader.bytes.is_empty() && reader.u32()? != 0 {
+        return Err(VkError::Unsupported(
+            "gnark verify key with commitments".into(),
+        ));
+    }
+
+    Ok(Groth16VerifyingKey {
+        alpha_g1,
+        beta_g2,
+        gamma_g2,
+        delta_g2,
+        gamma_abc_g1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bn254::Fr;
+    use ark_ec::{AffineRepr, CurveGroup};
+    use ark_ff::{BigInteger, PrimeField};
+    use ark_relations::{
+        lc,
+        r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+    };
+
+    use super::*;
+
+    fn test_vk() -> Groth16VerifyingKey {
+        let g1 = |k: u64| (G1Affine::generator() * Fr::from(k)).into_affine();
+        let g2 = |k: u64| (G2Affine::generator() * Fr::from(k)).into_affine();
+        Groth16VerifyingKey {
+            alpha_g1: g1(3),
+            beta_g2: g2(5),
+            gamma_g2: g2(7),
+            delta_g2: g2(11),
+            gamma_abc_g1: vec![g1(13), g1(17), g1(19)],
+        }
+    }
+
+    fn decimal(fq: &Fq) -> String {
+        BigUint::from(fq.into_bigint()).to_string()
+    }
+
+    #[test]
+    fn test_bitvm_encoding_roundtrip() {
+        let vk = test_vk();
+        let bytes = encode_vk(&vk);
+        assert_eq!(decode_vk(&bytes).unwrap(), vk);
+
+        assert!(decode_vk(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode_vk(&[bytes.as_slice(), &[0]].concat()).is_err());
+
+        let mut uncompressed = vec![];
+        vk.serialize_uncompressed(&mut uncompressed).unwrap();
+        assert!(decode_vk(&uncompressed).is_err());
+        assert_eq!(convert_vk(&uncompressed, VkFormat::Arkworks).unwrap(), vk);
+    }
+
+    #[test]
+    fn test_invalid_points_are_rejected() {
+        let mut vk = test_vk();
+        vk.alpha_g1 = G1Affine::new_unchecked(Fq::from(1u64), Fq::from(1u64));
+        assert!(matches!(
+            validate_vk(&vk),
+            Err(VkError::PointNotOnCurve(name)) if name == "alpha_g1"
+        ));
+
+        let mut vk = test_vk();
+        vk.delta_g2 = G2Affine::identity();
+        assert!(matches!(
+            validate_vk(&vk),
+            Err(VkError::PointAtInfinity(name)) if name == "delta_g2"
+        ));
+
+        let mut vk = test_vk();
+        vk.gamma_abc_g1.clear();
+        assert!(matches!(validate_vk(&vk), Err(VkError::EmptyIc)));
+    }
+
+    #[test]
+    fn test_from_snarkjs() {
+        let vk = test_vk();
+        let g1 = |p: &G1Affine| vec![decimal(&p.x), decimal(&p.y), "1".to_string()];
+        let g2 = |p: &G2Affine| {
+            vec![
+                vec![decimal(&p.x.c0), decimal(&p.x.c1)],
+                vec![decimal(&p.y.c0), decimal(&p.y.c1)],
+                vec!["1".to_string(), "0".to_string()],
+            ]
+        };
+        let json = serde_json::json!({
+            "protocol": "groth16",
+            "curve": "bn128",
+            "nPublic": 2,
+            "vk_alpha_1": g1(&vk.alpha_g1),
+            "vk_beta_2": g2(&vk.beta_g2),
+            "vk_gamma_2": g2(&vk.gamma_g2),
+            "vk_delta_2": g2(&vk.delta_g2),
+            "vk_alphabeta_12": [],
+            "IC": vk.gamma_abc_g1.iter().map(g1).collect::<Vec<_>>(),
+        });
+
+        let bytes = serde_json::to_vec(&json).unwrap();
+        assert_eq!(convert_vk(&bytes, VkFormat::Snarkjs).unwrap(), vk);
+    }
+
+    fn gnark_fq(fq: &Fq) -> Vec<u8> {
+        fq.into_bigint().to_bytes_be()
+    }
+
+    fn gnark_g1(p: &G1Affine, compressed: bool) -> Vec<u8> {
+        let mut bytes = gnark_fq(&p.x);
+        match compressed {
+            true => {
+                let largest = p.y > -p.y;
+                bytes[0] |= match largest {
+                    true => GNARK_COMPRESSED_LARGEST,
+                    false => GNARK_COMPRESSED_SMALLEST,
+                };
+            }
+            false => bytes.extend(gnark_fq(&p.y)),
+        }
+        bytes
+    }
+
+    fn gnark_g2(p: &G2Affine, compressed: bool) -> Vec<u8> {
+        let mut bytes = [gnark_fq(&p.x.c1), gnark_fq(&p.x.c0)].concat();
+        match compressed {
+            true => {
+                let largest = p.y > -p.y;
+                bytes[0] |= match largest {
+                    true => GNARK_COMPRESSED_LARGEST,
+                    false => GNARK_COMPRESSED_SMALLEST,
+                };
+            }
+            false => bytes.extend([gnark_fq(&p.y.c1), gnark_fq(&p.y.c0)].concat()),
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_from_gnark() {
+        let vk = test_vk();
+        for compressed in [true, false] {
+            let mut bytes = [
+                gnark_g1(&vk.alpha_g1, compressed),
+                gnark_g1(&vk.alpha_g1, compressed),
+                gnark_g2(&vk.beta_g2, compressed),
+                gnark_g2(&vk.gamma_g2, compressed),
+                gnark_g1(&vk.alpha_g1, compressed),
+                gnark_g2(&vk.delta_g2, compressed),
+                (vk.gamma_abc_g1.len() as u32).to_be_bytes().to_vec(),
+            ]
+            .concat();
+            for point in &vk.gamma_abc_g1 {
+                bytes.extend(gnark_g1(point, compressed));
+            }
+            assert_eq!(convert_vk(&bytes, VkFormat::Gnark).unwrap(), vk);
+
+            bytes.extend(1u32.to_be_bytes());
+            assert!(matches!(
+                convert_vk(&bytes, VkFormat::Gnark),
+                Err(VkError::Unsupported(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_from_gnark_raw_infinity() {
+        let mut vk = test_vk();
+        vk.gamma_abc_g1.insert(1, G1Affine::identity());
+        let mut raw_infinity = vec![0; 2 * GNARK_FQ_SIZE];
+        raw_infinity[0] = GNARK_UNCOMPRESSED_INFINITY;
+
+        // The flagged identity spans both coordinates like the (0, 0) one.
+        for infinity in [raw_infinity, vec![0; 2 * GNARK_FQ_SIZE]] {
+            let mut bytes = [
+                gnark_g1(&vk.alpha_g1, false),
+                gnark_g1(&vk.alpha_g1, false),
+                gnark_g2(&vk.beta_g2, false),
+                gnark_g2(&vk.gamma_g2, false),
+                gnark_g1(&vk.alpha_g1, false),
+                gnark_g2(&vk.delta_g2, false),
+                (vk.gamma_abc_g1.len() as u32).to_be_bytes().to_vec(),
+            ]
+            .concat();
+            for point in &vk.gamma_abc_g1 {
+                match point.is_zero() {
+                    true => bytes.extend(&infinity),
+                    false => bytes.extend(gnark_g1(point, false)),
+                }
+            }
+            assert_eq!(convert_vk(&bytes, VkFormat::Gnark).unwrap(), vk);
+        }
+
+        // A compressed point in a raw key is rejected instead of shifting the next fields.
+        let mut bytes = gnark_g1(&vk.alpha_g1, false);
+        bytes.extend(gnark_g1(&vk.alpha_g1, true));
+        assert!(matches!(from_gnark(&bytes), Err(VkError::Decode(_))));
+    }
+
+    /// Proves knowledge of `x` and `y` with `x * y = z`, `z` being the public input.
+    struct MulCircuit {
+        x: Fr,
+        y: Fr,
+    }
+
+    impl ConstraintSynthesizer<Fr> for MulCircuit {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            let x = cs.new_witness_variable(|| Ok(self.x))?;
+            let y = cs.new_witness_variable(|| Ok(self.y))?;
+            let z = cs.new_input_variable(|| Ok(self.x * self.y))?;
+            cs.enforce_constraint(lc!() + x, lc!() + y, lc!() + z)
+        }
+    }
+
+    #[test]
+    fn test_verify_proof() {
+        let rng = &mut ark_std::test_rng();
+        let circuit = MulCircuit {
+            x: Fr::from(3u64),
+            y: Fr::from(5u64),
+        };
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(
+            MulCircuit {
+                x: Fr::from(1u64),
+                y: Fr::from(1u64),
+            },
+            rng,
+        )
+        .unwrap();
+        let proof =
+            Groth16::<Bn254>::create_random_proof_with_reduction(circuit, &pk, rng).unwrap();
+
+        let mut bytes = vec![];
+        proof.serialize_compressed(&mut bytes).unwrap();
+        let proof = decode_proof(&bytes).unwrap();
+
+        assert!(verify_proof(&pk.vk, &proof, &[Fr::from(15u64)]).unwrap());
+        assert!(!verify_proof(&pk.vk, &proof, &[Fr::from(16u64)]).unwrap());
+        assert!(matches!(
+            verify_proof(&pk.vk, &proof, &[]),
+            Err(ProofError::PublicInputCount(1, 0))
+        ));
+
+        let mut vk = pk.vk.clone();
+        vk.gamma_abc_g1.clear();
+        assert!(matches!(
+            verify_proof(&vk, &proof, &[]),
+            Err(ProofError::InvalidVk(VkError::EmptyIc))
+        ));
+    }
+}
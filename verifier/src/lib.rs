@@ -0,0 +1,63 @@
+mod encoding;
+pub mod fflonk;
+pub mod groth16;
+
+use strum::{Display, EnumString};
+use types::{
+    circuit::CircuitType,
+    error::{ProofError, VkError},
+};
+
+/// Serialization formats a verify key can be converted from.
+#[derive(Debug, Clone, Copy, EnumString, Display, PartialEq, Eq)]
+pub enum VkFormat {
+    /// Compressed arkworks serialization, the encoding of `vk.bitvm`.
+    #[strum(serialize = "bitvm")]
+    Bitvm,
+    /// `verification_key.json` exported by snarkjs.
+    #[strum(serialize = "snarkjs")]
+    Snarkjs,
+    /// gnark `WriteTo` or `WriteRawTo` output.
+    #[strum(serialize = "gnark")]
+    Gnark,
+    /// Compressed or uncompressed arkworks serialization.
+    #[strum(serialize = "arkworks")]
+    Arkworks,
+}
+
+/// Decodes and validates a `vk.bitvm` verify key of the given circuit type.
+pub fn validate_vk_bytes(bytes: &[u8], circuit_type: &CircuitType) -> Result<(), VkError> {
+    match circuit_type {
+        CircuitType::Groth16 => groth16::decode_vk(bytes).map(|_| ()),
+        CircuitType::Fflonk => fflonk::decode_vk(bytes).map(|_| ()),
+    }
+}
+
+/// Verifies a proof given as hex of its compressed serialization, with decimal public inputs,
+/// against a `vk.bitvm` verify key. Only groth16 proofs can be verified natively.
+pub fn verify_proof(
+    vk: &[u8],
+    circuit_type: &CircuitType,
+    proof: &str,
+    public_inputs: &[String],
+) -> Result<bool, ProofError> {
+    match circuit_type {
+        CircuitType::Groth16 => {
+            let vk = groth16::decode_vk(vk)?;
+            let proof = hex::decode(proof.trim_start_matches("0x"))
+                .map_err(|e| ProofError::Decode(e.to_string()))?;
+            let proof = groth16::decode_proof(&proof)?;
+            let public_inputs = public_inputs
+                .iter()
+                .map(|input| {
+                    encoding::snarkjs_field(input)
+                        .map_err(|_| ProofError::InvalidPublicInput(input.clone()))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            groth16::verify_proof(&vk, &proof, &public_inputs)
+        }
+        CircuitType::Fflonk => Err(ProofError::Unsupported(
+            "fflonk proofs can not be verified natively".into(),
+        )),
+    }
+}
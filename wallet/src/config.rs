@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use web3_decl::jsonrpsee::{
+    core::ClientError,
+    http_client::{HttpClient, HttpClientBuilder},
+};
+
+/// Settings of the JSON-RPC client talking to the committee.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+    pub request_timeout: Duration,
+    /// Largest response accepted, in bytes.
+    pub max_response_size: u32,
+    /// Attempts after the first one of a query failing with a transport error or a timeout.
+    /// Calls changing the committee state are never retried.
+    pub max_retries: usize,
+    pub retry_interval: Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(60),
+            max_response_size: 10 * 1024 * 1024,
+            max_retries: 3,
+            retry_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn build_http_client(&self, rpc_address: &str) -> Result<HttpClient, ClientError> {
+        HttpClientBuilder::default()
+            .request_timeout(self.request_timeout)
+            .max_response_size(self.max_response_size)
+            .build(rpc_address)
+    }
+
+    /// Whether a call failing with `err` may succeed when sent again.
+    pub(crate) fn is_transient(err: &ClientError) -> bool {
+        matches!(
+            err,
+            ClientError::Transport(_) | ClientError::RequestTimeout | ClientError::RestartNeeded(_)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use web3_decl::jsonrpsee::types::ErrorObjectOwned;
+
+    use super::*;
+
+    #[test]
+    fn test_is_transient() {
+        assert!(ClientConfig::is_transient(&ClientError::RequestTimeout));
+        assert!(!ClientConfig::is_transient(&ClientError::Call(
+            ErrorObjectOwned::owned(1011, "invalid connector value", None::<()>)
+        )));
+    }
+}
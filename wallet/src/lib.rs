@@ -1,15 +1,22 @@
+use std::future::Future;
+
 use anyhow::Context;
 use bitcoin::{ScriptBuf, Txid};
+use config::ClientConfig;
 use errors::ClientError;
 use signer::Signer;
-use types::challenge::{ChallengeInfoRes, ChallengeRequest, FinishChallengeRequest};
+use types::challenge::{ChallengeInfoRes, ChallengeRequest, FinishChallengeRequest, ProofRes};
 use types::circuit::RegisterCircuitRequest;
+use types::committee::{CommitteeVersionId, CommitteeVersionRes};
 use types::disprove::DisproveRequest;
+use types::params::ProtocolParamsRes;
 use types::register::{CircuitTx, QueryAssertTxReq};
 use types::{FinishRegisterRequest, RegisterRequest};
-use web3_decl::jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use web3_decl::error::{ClientRpcContext, EnrichedClientResult};
+use web3_decl::jsonrpsee::http_client::HttpClient;
 use web3_decl::namespaces::committee::CommitteeNamespaceClient;
 
+pub mod config;
 pub mod errors;
 pub mod provider;
 pub mod signer;
@@ -17,18 +24,21 @@ pub mod signer;
 pub struct Wallet<P> {
     pub provider: P,
     pub signer: Signer,
+    pub config: ClientConfig,
 }
 
 impl Wallet<HttpClient> {
     pub fn with_http_client(
         rpc_address: &str,
         signer: Signer,
+        config: ClientConfig,
     ) -> Result<Wallet<HttpClient>, ClientError> {
-        let client = HttpClientBuilder::default().build(rpc_address)?;
+        let client = config.build_http_client(rpc_address)?;
 
         Ok(Wallet {
             provider: client,
             signer,
+            config,
         })
     }
 }
@@ -38,99 +48,224 @@ where
     P: CommitteeNamespaceClient + Sync,
 {
     pub fn new(provider: P, signer: Signer) -> Self {
-        Self { provider, signer }
+        Self {
+            provider,
+            signer,
+            config: ClientConfig::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sends the query built by `call` until it succeeds, fails with a non transient error or
+    /// runs out of retries.
+    async fn query<T, F>(&self, mut call: impl FnMut() -> F) -> EnrichedClientResult<T>
+    where
+        F: Future<Output = EnrichedClientResult<T>>,
+    {
+        let mut retries = 0;
+        loop {
+            match call().await {
+                Err(err)
+                    if retries < self.config.max_retries
+                        && ClientConfig::is_transient(err.as_ref()) =>
+                {
+                    retries += 1;
+                    tokio::time::sleep(self.config.retry_interval).await;
+                }
+                res => return res,
+            }
+        }
     }
 
     pub async fn get_multi_sig_script(&self, register_pk: &str) -> anyhow::Result<ScriptBuf> {
-        self.provider
-            .get_multi_sig_address(register_pk)
-            .await
-            .context("Failed to get multiple signature account")
+        self.query(|| {
+            self.provider
+                .get_multi_sig_address(register_pk)
+                .rpc_context("getMultiSigAddress")
+                .with_arg("register_pk", &register_pk)
+        })
+        .await
+        .context("Failed to get multiple signature account")
     }
 
     pub async fn get_multi_sig_script_of_proof(&self, proof_id: &str) -> anyhow::Result<ScriptBuf> {
-        self.provider
-            .get_multi_sig_address_of_proof(proof_id)
-            .await
-            .context("Failed to get multiple signature account")
+        self.query(|| {
+            self.provider
+                .get_multi_sig_address_of_proof(proof_id)
+                .rpc_context("getMultiSigAddressOfProof")
+                .with_arg("proof_id", &proof_id)
+        })
+        .await
+        .context("Failed to get multiple signature account")
+    }
+
+    pub async fn get_multi_sig_script_of_version(
+        &self,
+        register_pk: &str,
+        version: CommitteeVersionId,
+    ) -> anyhow::Result<ScriptBuf> {
+        self.query(|| {
+            self.provider
+                .get_multi_sig_address_of_version(register_pk, version)
+                .rpc_context("getMultiSigAddressOfVersion")
+                .with_arg("register_pk", &register_pk)
+                .with_arg("version", &version)
+        })
+        .await
+        .context("Failed to get multiple signature account")
+    }
+
+    pub async fn get_committee_version(&self) -> anyhow::Result<CommitteeVersionRes> {
+        self.query(|| {
+            self.provider
+                .get_committee_version()
+                .rpc_context("getCommitteeVersion")
+        })
+        .await
+        .context("Failed to query committee version")
     }
 
     pub async fn start_register(&self, request: RegisterRequest) -> anyhow::Result<u32> {
+        let validator_key = request.validator_key.clone();
+        let public_key = request.public_key.clone();
+        let committee_version = request.committee_version;
         self.provider
             .start_register(request)
+            .rpc_context("startRegister")
+            .with_arg("validator_key", &validator_key)
+            .with_arg("public_key", &public_key)
+            .with_arg("committee_version", &committee_version)
             .await
             .context("Failed to register")
     }
 
     pub async fn finish_register(&self, request: FinishRegisterRequest) -> anyhow::Result<u32> {
+        let validator_key = request.validator_key.clone();
+        let disprove_txs = request.disprove_txs.len();
         self.provider
             .finish_register(request)
+            .rpc_context("finishRegister")
+            .with_arg("validator_key", &validator_key)
+            .with_arg("disprove_txs", &disprove_txs)
             .await
             .context("Failed to register")
     }
 
     pub async fn start_challenge(&self, request: ChallengeRequest) -> anyhow::Result<u32> {
         self.provider
-            .start_challenge(request)
+            .start_challenge(request.clone())
+            .rpc_context("startChallenge")
+            .with_arg("request", &request)
             .await
             .context("Failed to start a challenge")
     }
 
     pub async fn challenge_status(&self, request: ChallengeRequest) -> anyhow::Result<String> {
-        self.provider
-            .challenge_status(request)
-            .await
-            .context("Failed to query challenge status")
+        self.query(|| {
+            self.provider
+                .challenge_status(request.clone())
+                .rpc_context("challengeStatus")
+                .with_arg("request", &request)
+        })
+        .await
+        .context("Failed to query challenge status")
     }
 
     pub async fn challenge_info(
         &self,
         request: ChallengeRequest,
     ) -> anyhow::Result<ChallengeInfoRes> {
-        self.provider
-            .challenge_info(request)
-            .await
-            .context("Failed to query challenge info")
+        self.query(|| {
+            self.provider
+                .challenge_info(request.clone())
+                .rpc_context("challengeInfo")
+                .with_arg("request", &request)
+        })
+        .await
+        .context("Failed to query challenge info")
     }
 
     pub async fn get_committee_challenge_tx(
         &self,
         request: ChallengeRequest,
     ) -> anyhow::Result<String> {
-        self.provider
-            .get_committee_challenge_tx(request)
-            .await
-            .context("Failed to query challenge transaction")
+        self.query(|| {
+            self.provider
+                .get_committee_challenge_tx(request.clone())
+                .rpc_context("getCommitteeChallengeTx")
+                .with_arg("request", &request)
+        })
+        .await
+        .context("Failed to query challenge transaction")
     }
 
     pub async fn get_committee_assert_txs(
         &self,
         request: QueryAssertTxReq,
     ) -> anyhow::Result<Vec<CircuitTx>> {
-        self.provider
-            .get_committee_assert_txs(request)
-            .await
-            .context("Failed to query assert transaction")
+        self.query(|| {
+            self.provider
+                .get_committee_assert_txs(request.clone())
+                .rpc_context("getCommitteeAssertTxs")
+                .with_arg("request", &request)
+        })
+        .await
+        .context("Failed to query assert transaction")
+    }
+
+    pub async fn get_protocol_params(&self) -> anyhow::Result<ProtocolParamsRes> {
+        self.query(|| {
+            self.provider
+                .get_protocol_params()
+                .rpc_context("getProtocolParams")
+        })
+        .await
+        .context("Failed to query protocol params")
+    }
+
+    pub async fn get_proof(&self, proof_id: &str) -> anyhow::Result<ProofRes> {
+        self.query(|| {
+            self.provider
+                .get_proof(proof_id)
+                .rpc_context("getProof")
+                .with_arg("proof_id", &proof_id)
+        })
+        .await
+        .context("Failed to query proof")
     }
 
     pub async fn finish_challenge(&self, request: FinishChallengeRequest) -> anyhow::Result<Txid> {
+        let proof_id = request.proof_id.clone();
         self.provider
             .finish_challenge(request)
+            .rpc_context("finishChallenge")
+            .with_arg("proof_id", &proof_id)
             .await
             .context("Failed to finish challenge")
     }
 
     pub async fn disprove(&self, request: DisproveRequest) -> anyhow::Result<()> {
         self.provider
-            .disprove(request)
+            .disprove(request.clone())
+            .rpc_context("disprove")
+            .with_arg("request", &request)
             .await
-            .map_err(|e| panic!("failed to disprove: {}", e))
+            .context("Failed to disprove")
     }
 
     pub async fn register_circuit(&self, request: RegisterCircuitRequest) -> anyhow::Result<u32> {
+        let circuit_type = request.circuit_type.clone();
+        let vk_len = request.vk.len();
         self.provider
             .register_circuit(request)
+            .rpc_context("registerCircuit")
+            .with_arg("circuit_type", &circuit_type)
+            .with_arg("vk_len", &vk_len)
             .await
-            .map_err(|e| panic!("failed to register circuit: {}", e))
+            .context("Failed to register circuit")
     }
 }
@@ -7,9 +7,14 @@ use std::{
     task::{Context, Poll},
 };
 
-use jsonrpsee::core::ClientError;
+use jsonrpsee::{
+    core::ClientError,
+    types::{error::INTERNAL_ERROR_CODE, ErrorObject, ErrorObjectOwned},
+};
 use pin_project_lite::pin_project;
+use serde::de::DeserializeOwned;
 use thiserror::Error;
+use types::error::{ChallengeError, CircuitError, DisproveError, RegisterNodeError};
 
 #[derive(Debug, Error)]
 pub enum Web3Error {
@@ -17,14 +22,71 @@ pub enum Web3Error {
     InternalError,
     #[error("Invalid register's public key {0}")]
     InvalidRegisterPublicKey(String),
-    #[error("RegisterNodeError error {0}")]
-    RegisterNodeError(String),
-    #[error("ChallengeNodeError error {0}")]
-    ChallengeNodeError(String),
-    #[error("DisproveError error {0}")]
-    DisproveError(String),
-    #[error("RegisterCircuitError error {0}")]
-    RegisterCircuitError(String),
+    #[error(transparent)]
+    RegisterNodeError(#[from] RegisterNodeError),
+    #[error(transparent)]
+    ChallengeNodeError(#[from] ChallengeError),
+    #[error(transparent)]
+    DisproveError(#[from] DisproveError),
+    #[error(transparent)]
+    RegisterCircuitError(#[from] CircuitError),
+}
+
+impl Web3Error {
+    /// Code of the JSON-RPC error object, the typed errors of `types::error` take the codes
+    /// 1000-1999 (register), 2000-2999 (challenge), 3000-3999 (disprove) and 4000-4999
+    /// (circuit).
+    pub fn code(&self) -> i32 {
+        match self {
+            Web3Error::InternalError => INTERNAL_ERROR_CODE,
+            Web3Error::InvalidRegisterPublicKey(_) => 1,
+            Web3Error::RegisterNodeError(err) => err.code(),
+            Web3Error::ChallengeNodeError(err) => err.code(),
+            Web3Error::DisproveError(err) => err.code(),
+            Web3Error::RegisterCircuitError(err) => err.code(),
+        }
+    }
+
+    /// Decodes the committee error carried by a JSON-RPC error object, `None` for errors
+    /// which are not committee errors, e.g. a transport failure or an unknown code.
+    pub fn from_client_error(err: &ClientError) -> Option<Self> {
+        match err {
+            ClientError::Call(object) => Self::from_error_object(object),
+            _ => None,
+        }
+    }
+
+    pub fn from_error_object(object: &ErrorObject<'_>) -> Option<Self> {
+        fn data<T: DeserializeOwned>(object: &ErrorObject<'_>) -> Option<T> {
+            serde_json::from_str(object.data()?.get()).ok()
+        }
+
+        let err = match object.code() {
+            INTERNAL_ERROR_CODE => Web3Error::InternalError,
+            1 => Web3Error::InvalidRegisterPublicKey(data(object)?),
+            1000..=1999 => Web3Error::RegisterNodeError(data(object)?),
+            2000..=2999 => Web3Error::ChallengeNodeError(data(object)?),
+            3000..=3999 => Web3Error::DisproveError(data(object)?),
+            4000..=4999 => Web3Error::RegisterCircuitError(data(object)?),
+            _ => return None,
+        };
+        // The data must decode to the variant the code stands for.
+        (err.code() == object.code()).then_some(err)
+    }
+}
+
+impl From<Web3Error> for ErrorObjectOwned {
+    fn from(err: Web3Error) -> Self {
+        let data = match &err {
+            Web3Error::InternalError => None,
+            Web3Error::InvalidRegisterPublicKey(key) => serde_json::to_value(key).ok(),
+            Web3Error::RegisterNodeError(inner) => serde_json::to_value(inner).ok(),
+            Web3Error::ChallengeNodeError(inner) => serde_json::to_value(inner).ok(),
+            Web3Error::DisproveError(inner) => serde_json::to_value(inner).ok(),
+            Web3Error::RegisterCircuitError(inner) => serde_json::to_value(inner).ok(),
+        };
+        ErrorObject::owned(err.code(), err.to_string(), data)
+    }
 }
 
 /// Client RPC error with additional details: the method name and arguments of the called method.
@@ -175,3 +237,43 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_object_roundtrip() {
+        let errors = [
+            Web3Error::RegisterNodeError(RegisterNodeError::StakeTxInvalidConnectorValue(546)),
+            Web3Error::ChallengeNodeError(ChallengeError::ChallengeRawCommitteeTxNotReady),
+            Web3Error::DisproveError(DisproveError::AssertTxIsNotConfirmed("proof".to_string())),
+            Web3Error::InvalidRegisterPublicKey("key".to_string()),
+            Web3Error::InternalError,
+        ];
+        for err in errors {
+            let message = err.to_string();
+            let object = ErrorObjectOwned::from(err);
+            assert_eq!(object.message(), message);
+
+            let client_error = ClientError::Call(object);
+            let decoded = Web3Error::from_client_error(&client_error).unwrap();
+            assert_eq!(decoded.to_string(), message);
+        }
+
+        let object = ErrorObject::owned(1011, "stake tx has invalid connector value", None::<()>);
+        assert!(Web3Error::from_error_object(&object).is_none());
+    }
+
+    #[test]
+    fn test_error_data_format() {
+        let object = ErrorObjectOwned::from(Web3Error::RegisterNodeError(
+            RegisterNodeError::StakeTxInvalidConnectorValue(546),
+        ));
+        assert_eq!(object.code(), 1011);
+        assert_eq!(
+            object.data().unwrap().get(),
+            r#"{"StakeTxInvalidConnectorValue":546}"#
+        );
+    }
+}
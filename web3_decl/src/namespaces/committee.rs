@@ -2,9 +2,11 @@
 use bitcoin::{ScriptBuf, Txid};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
 use types::{
-    challenge::{ChallengeInfoRes, ChallengeRequest, FinishChallengeRequest},
+    challenge::{ChallengeInfoRes, ChallengeRequest, FinishChallengeRequest, ProofRes},
     circuit::RegisterCircuitRequest,
+    committee::{CommitteeVersionId, CommitteeVersionRes},
     disprove::DisproveRequest,
+    params::ProtocolParamsRes,
     register::{CircuitTx, QueryAssertTxReq},
     FinishRegisterRequest, RegisterRequest,
 };
@@ -28,6 +30,16 @@ pub trait CommitteeNamespace {
     #[method(name = "getMultiSigAddressOfProof")]
     async fn get_multi_sig_address_of_proof(&self, proof_id: &str) -> RpcResult<ScriptBuf>;
 
+    #[method(name = "getMultiSigAddressOfVersion")]
+    async fn get_multi_sig_address_of_version(
+        &self,
+        register_pk: &str,
+        version: CommitteeVersionId,
+    ) -> RpcResult<ScriptBuf>;
+
+    #[method(name = "getCommitteeVersion")]
+    async fn get_committee_version(&self) -> RpcResult<CommitteeVersionRes>;
+
     #[method(name = "startRegister")]
     async fn start_register(&self, request: RegisterRequest) -> RpcResult<u32>;
 
@@ -52,6 +64,12 @@ pub trait CommitteeNamespace {
         request: QueryAssertTxReq,
     ) -> RpcResult<Vec<CircuitTx>>;
 
+    #[method(name = "getProtocolParams")]
+    async fn get_protocol_params(&self) -> RpcResult<ProtocolParamsRes>;
+
+    #[method(name = "getProof")]
+    async fn get_proof(&self, proof_id: &str) -> RpcResult<ProofRes>;
+
     #[method(name = "finishChallenge")]
     async fn finish_challenge(&self, request: FinishChallengeRequest) -> RpcResult<Txid>;
 